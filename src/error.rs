@@ -12,3 +12,27 @@ pub enum EasyDbError {
 
 /// Result returning Error
 pub type EasyDbResult<T> = std::result::Result<T, EasyDbError>;
+
+impl std::error::Error for EasyDbError {}
+
+impl Display for EasyDbError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EasyDbError::Internal(s) | EasyDbError::Parse(s) | EasyDbError::Value(s) => {
+                write!(f, "{}", s)
+            }
+        }
+    }
+}
+
+impl From<std::io::Error> for EasyDbError {
+    fn from(err: std::io::Error) -> Self {
+        EasyDbError::Internal(err.to_string())
+    }
+}
+
+impl From<bincode::Error> for EasyDbError {
+    fn from(err: bincode::Error) -> Self {
+        EasyDbError::Internal(err.to_string())
+    }
+}
@@ -1,6 +1,98 @@
 pub mod error;
 pub mod sql;
+pub mod storage;
+
+use sql::parser::ast::Parser;
+use std::process::exit;
+
+const USAGE: &str = "Usage: easy_db [--format text|json|csv] -c <SQL> | -f <script.sql>";
+
+/// Exit code for statements that failed
+const EXIT_ERROR: i32 = 1;
+/// Exit code for invalid arguments or unreadable scripts
+const EXIT_USAGE: i32 = 2;
+
+/// How the CLI prints its results
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Format {
+    /// Statements as SQL on stdout, errors on stderr
+    Text,
+    /// A JSON object on stdout, {"statements": [...], "errors": [...]}
+    Json,
+    /// CSV on stdout with a kind,text header and a row per statement
+    /// ("statement") or error ("error")
+    Csv,
+}
+
+fn usage() -> ! {
+    eprintln!("{}", USAGE);
+    exit(EXIT_USAGE)
+}
 
 fn main() {
-    println!("Hey man");
+    let mut args = std::env::args().skip(1);
+    let mut format = Format::Text;
+    let mut script = None;
+    while let Some(arg) = args.next() {
+        match (arg.as_str(), args.next()) {
+            ("--format", Some(name)) => {
+                format = match name.as_str() {
+                    "text" => Format::Text,
+                    "json" => Format::Json,
+                    "csv" => Format::Csv,
+                    _ => usage(),
+                }
+            }
+            ("-c", Some(sql)) if script.is_none() => script = Some(sql),
+            ("-f", Some(path)) if script.is_none() => match std::fs::read_to_string(&path) {
+                Ok(contents) => script = Some(contents),
+                Err(err) => {
+                    eprintln!("easy_db: {}: {}", path, err);
+                    exit(EXIT_USAGE)
+                }
+            },
+            _ => usage(),
+        }
+    }
+    let Some(script) = script else { usage() };
+
+    // There is no executor yet, so statements are validated and echoed back
+    // in normalized form. Every error in the script is reported.
+    let (statements, errors) = Parser::new(&script).parse_all_with_recovery();
+    let statements: Vec<String> = statements.iter().map(|s| s.to_string()).collect();
+    let errors: Vec<String> = errors.iter().map(|e| e.to_string()).collect();
+    match format {
+        Format::Text => {
+            for statement in &statements {
+                println!("{};", statement)
+            }
+            for err in &errors {
+                eprintln!("easy_db: {}", err);
+            }
+        }
+        Format::Json => println!(
+            "{}",
+            serde_json::json!({ "statements": statements, "errors": errors })
+        ),
+        Format::Csv => {
+            println!("kind,text");
+            for statement in &statements {
+                println!("statement,{}", csv_field(statement));
+            }
+            for err in &errors {
+                println!("error,{}", csv_field(err));
+            }
+        }
+    }
+    if !errors.is_empty() {
+        exit(EXIT_ERROR)
+    }
+}
+
+/// Quotes a CSV field if it contains a comma, quote or line break
+fn csv_field(text: &str) -> String {
+    match text.contains([',', '"', '\n', '\r']) {
+        true => format!("\"{}\"", text.replace('"', "\"\"")),
+        false => text.to_string(),
+    }
 }
@@ -0,0 +1,246 @@
+use super::super::types::{coerce, Collation, Decimal, Value};
+use super::expr::collate;
+use crate::error::{EasyDbError, EasyDbResult};
+
+use std::cmp::Ordering;
+use std::collections::HashSet;
+
+/// An aggregate function
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Aggregate {
+    Count,
+    Sum,
+    Average,
+    Min,
+    Max,
+}
+
+impl Aggregate {
+    /// Looks up an aggregate function by its (case-insensitive) name
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_ascii_uppercase().as_str() {
+            "COUNT" => Some(Self::Count),
+            "SUM" => Some(Self::Sum),
+            "AVG" => Some(Self::Average),
+            "MIN" => Some(Self::Min),
+            "MAX" => Some(Self::Max),
+            _ => None,
+        }
+    }
+}
+
+impl std::fmt::Display for Aggregate {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::Count => "COUNT",
+            Self::Sum => "SUM",
+            Self::Average => "AVG",
+            Self::Min => "MIN",
+            Self::Max => "MAX",
+        })
+    }
+}
+
+/// Computes an aggregate over a group of values, fed one at a time. NULLs
+/// are ignored, so COUNT(*) should be fed a non-NULL value per row. Over no
+/// values COUNT gives 0 and the other aggregates give NULL.
+///
+/// SUM and AVG use checked arithmetic, so an integer sum that overflows is an
+/// error. AVG of integers is computed exactly as a decimal. MIN, MAX and
+/// DISTINCT compare strings by the collation of the aggregated column, and
+/// of strings that compare equal MIN and MAX give the first.
+#[derive(Clone, Debug)]
+pub struct Accumulator {
+    aggregate: Aggregate,
+    collation: Collation,
+    /// The values seen so far as the collation sees them, for DISTINCT
+    /// aggregates
+    seen: Option<HashSet<Value>>,
+    /// The number of values accumulated
+    count: i64,
+    /// The running sum, minimum or maximum, NULL until the first value
+    value: Value,
+}
+
+impl Accumulator {
+    /// Creates an accumulator, which only counts each distinct value once
+    /// if distinct is set, comparing strings by the given collation
+    pub fn new(aggregate: Aggregate, distinct: bool, collation: Collation) -> Self {
+        Self {
+            aggregate,
+            collation,
+            seen: distinct.then(HashSet::new),
+            count: 0,
+            value: Value::Null,
+        }
+    }
+
+    pub fn accumulate(&mut self, value: &Value) -> EasyDbResult<()> {
+        if *value == Value::Null {
+            return Ok(());
+        }
+        if let Some(seen) = &mut self.seen {
+            if !seen.insert(self.collation.normalize(value)) {
+                return Ok(());
+            }
+        }
+        self.value = match (self.aggregate, &self.value, value) {
+            (Aggregate::Count, _, _) => Value::Null,
+            (
+                Aggregate::Sum | Aggregate::Average,
+                current,
+                Value::Integer(_) | Value::Float(_) | Value::Decimal(_),
+            )
+            | (Aggregate::Sum, current, Value::Interval(_)) => match current {
+                Value::Null => value.clone(),
+                current => current.checked_add(value)?,
+            },
+            (Aggregate::Sum | Aggregate::Average, _, value) => {
+                return Err(EasyDbError::Value(format!(
+                    "Can't compute {} of {}",
+                    self.aggregate,
+                    coerce::describe(value)
+                )))
+            }
+            (Aggregate::Min | Aggregate::Max, Value::Null, value) => value.clone(),
+            (Aggregate::Min | Aggregate::Max, current, value) => {
+                let wanted = match self.aggregate {
+                    Aggregate::Min => Ordering::Less,
+                    _ => Ordering::Greater,
+                };
+                match coerce::unify(value, current)? {
+                    Some((lhs, rhs)) if collate(&lhs, &rhs, self.collation) == wanted => {
+                        value.clone()
+                    }
+                    Some(_) => current.clone(),
+                    None => {
+                        return Err(EasyDbError::Value(format!(
+                            "Can't compare {} and {}",
+                            coerce::describe(value),
+                            coerce::describe(current)
+                        )))
+                    }
+                }
+            }
+        };
+        self.count = self
+            .count
+            .checked_add(1)
+            .ok_or_else(|| EasyDbError::Value("Integer overflow".into()))?;
+        Ok(())
+    }
+
+    /// Returns the aggregate of the values accumulated so far
+    pub fn aggregate(&self) -> EasyDbResult<Value> {
+        Ok(match (self.aggregate, &self.value) {
+            (Aggregate::Count, _) => Value::Integer(self.count),
+            (Aggregate::Average, Value::Null) => Value::Null,
+            (Aggregate::Average, Value::Integer(sum)) => {
+                Value::Decimal(Decimal::from(*sum).checked_div(&self.count.into())?)
+            }
+            (Aggregate::Average, Value::Float(sum)) => Value::Float(sum / self.count as f64),
+            (Aggregate::Average, Value::Decimal(sum)) => {
+                Value::Decimal(sum.checked_div(&self.count.into())?)
+            }
+            (Aggregate::Average, value) => {
+                return Err(EasyDbError::Internal(format!(
+                    "Unexpected sum {} for AVG",
+                    value
+                )))
+            }
+            (Aggregate::Sum | Aggregate::Min | Aggregate::Max, value) => value.clone(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::super::types::Interval;
+    use super::*;
+
+    /// Computes an aggregate over the given values
+    fn aggregate(aggregate: Aggregate, values: &[Value]) -> EasyDbResult<Value> {
+        aggregate_by(aggregate, false, Collation::Binary, values)
+    }
+
+    /// Computes an aggregate over the given values, comparing strings by a
+    /// collation
+    fn aggregate_by(
+        aggregate: Aggregate,
+        distinct: bool,
+        collation: Collation,
+        values: &[Value],
+    ) -> EasyDbResult<Value> {
+        let mut accumulator = Accumulator::new(aggregate, distinct, collation);
+        for value in values {
+            accumulator.accumulate(value)?;
+        }
+        accumulator.aggregate()
+    }
+
+    fn interval(s: &str) -> Value {
+        Value::Interval(s.parse::<Interval>().unwrap())
+    }
+
+    #[test]
+    fn intervals() {
+        let values = [interval("2 days"), Value::Null, interval("1 day")];
+        assert_eq!(
+            aggregate(Aggregate::Sum, &values).unwrap(),
+            interval("3 days")
+        );
+        assert_eq!(
+            aggregate(Aggregate::Min, &values).unwrap(),
+            interval("1 day")
+        );
+        assert_eq!(
+            aggregate(Aggregate::Max, &values).unwrap(),
+            interval("2 days")
+        );
+        assert_eq!(
+            aggregate(Aggregate::Count, &values).unwrap(),
+            Value::Integer(2)
+        );
+        assert!(aggregate(Aggregate::Average, &values).is_err());
+    }
+
+    #[test]
+    fn collations() {
+        let text = |s: &str| Value::String(s.into());
+        let values = [text("b"), text("A"), Value::Null, text("a"), text("B")];
+        let cases = [
+            (Aggregate::Min, false, Collation::Binary, text("A")),
+            (Aggregate::Max, false, Collation::Binary, text("b")),
+            (Aggregate::Count, true, Collation::Binary, Value::Integer(4)),
+            (Aggregate::Min, false, Collation::NoCase, text("A")),
+            (Aggregate::Max, false, Collation::NoCase, text("b")),
+            (Aggregate::Count, true, Collation::NoCase, Value::Integer(2)),
+            (
+                Aggregate::Count,
+                false,
+                Collation::NoCase,
+                Value::Integer(4),
+            ),
+        ];
+        for (function, distinct, collation, expect) in cases {
+            assert_eq!(
+                aggregate_by(function, distinct, collation, &values).unwrap(),
+                expect,
+                "{} {} {}",
+                function,
+                distinct,
+                collation
+            );
+        }
+        // Under NOCASE "a" sorts before "B", unlike byte by byte
+        let values = [text("B"), text("a")];
+        assert_eq!(
+            aggregate_by(Aggregate::Min, false, Collation::NoCase, &values).unwrap(),
+            text("a")
+        );
+        assert_eq!(
+            aggregate_by(Aggregate::Min, false, Collation::Binary, &values).unwrap(),
+            text("B")
+        );
+    }
+}
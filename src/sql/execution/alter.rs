@@ -0,0 +1,132 @@
+use super::super::parser::ast::AlterTableAction;
+use super::super::schema::Catalog;
+use super::rows::Rows;
+use crate::error::EasyDbResult;
+use crate::storage::Engine;
+
+/// Executes ALTER TABLE ... ADD/DROP COLUMN: changes the table's definition
+/// in the catalog and rewrites its rows in the engine to match, restoring
+/// the old definition if the rows can't be rewritten
+pub fn alter_table(
+    catalog: &mut dyn Catalog,
+    engine: &mut dyn Engine,
+    name: &str,
+    action: &AlterTableAction,
+) -> EasyDbResult<()> {
+    let old = catalog.must_read_table(name)?;
+    let mut table = old.clone();
+    table.alter(action)?;
+    catalog.update_table(table.clone())?;
+    let result = Rows::new(catalog, &old).and_then(|rows| rows.alter(catalog, engine, &table));
+    if let Err(err) = result {
+        catalog.update_table(old)?;
+        return Err(err);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::super::parser::ast::{Parser, Statement};
+    use super::super::super::schema::{MemoryCatalog, Table};
+    use super::super::super::types::Value;
+    use super::*;
+    use crate::storage::Memory;
+
+    /// Executes a CREATE TABLE or ALTER TABLE statement
+    fn execute(catalog: &mut MemoryCatalog, engine: &mut Memory, sql: &str) -> EasyDbResult<()> {
+        match Parser::new(sql).parse()? {
+            Statement::CreateTable {
+                name,
+                columns,
+                constraints,
+            } => catalog.create_table(Table::from_ast(&name, &columns, &constraints)?),
+            Statement::AlterTable { name, action } => alter_table(catalog, engine, &name, &action),
+            statement => panic!("unexpected statement {}", statement),
+        }
+    }
+
+    fn rows(catalog: &MemoryCatalog, engine: &mut Memory) -> EasyDbResult<Vec<Vec<Value>>> {
+        Rows::new(catalog, &catalog.must_read_table("t")?)?.scan(engine)
+    }
+
+    #[test]
+    fn rewrites_rows() -> EasyDbResult<()> {
+        let (mut catalog, mut engine) = (MemoryCatalog::new(), Memory::new());
+        execute(
+            &mut catalog,
+            &mut engine,
+            "CREATE TABLE t (id INT PRIMARY KEY, a TEXT INDEX, b INT)",
+        )?;
+        let table = catalog.must_read_table("t")?;
+        for (id, a, b) in [(1, "x", 10), (2, "y", 20)] {
+            let row = vec![
+                Value::Integer(id),
+                Value::String(a.into()),
+                Value::Integer(b),
+            ];
+            Rows::new(&catalog, &table)?.insert(&catalog, &mut engine, row)?;
+        }
+
+        execute(&mut catalog, &mut engine, "ALTER TABLE t DROP COLUMN a")?;
+        assert_eq!(
+            rows(&catalog, &mut engine)?,
+            vec![
+                vec![Value::Integer(1), Value::Integer(10)],
+                vec![Value::Integer(2), Value::Integer(20)]
+            ]
+        );
+        // The dropped column's index entries are gone too
+        assert_eq!(engine.status()?.keys, 2);
+
+        execute(
+            &mut catalog,
+            &mut engine,
+            "ALTER TABLE t ADD COLUMN c TEXT DEFAULT 'z'",
+        )?;
+        execute(
+            &mut catalog,
+            &mut engine,
+            "ALTER TABLE t ADD COLUMN d INT UNIQUE",
+        )?;
+        assert_eq!(
+            rows(&catalog, &mut engine)?[1],
+            vec![
+                Value::Integer(2),
+                Value::Integer(20),
+                Value::String("z".into()),
+                Value::Null
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn violations_leave_table_unchanged() -> EasyDbResult<()> {
+        let (mut catalog, mut engine) = (MemoryCatalog::new(), Memory::new());
+        execute(
+            &mut catalog,
+            &mut engine,
+            "CREATE TABLE t (id INT PRIMARY KEY)",
+        )?;
+        let table = catalog.must_read_table("t")?;
+        for id in [1, 2] {
+            Rows::new(&catalog, &table)?.insert(&catalog, &mut engine, vec![Value::Integer(id)])?;
+        }
+        let err = execute(
+            &mut catalog,
+            &mut engine,
+            "ALTER TABLE t ADD COLUMN a INT UNIQUE DEFAULT 1",
+        )
+        .unwrap_err();
+        assert!(err.to_string().contains("t_a_key"), "{}", err);
+        assert_eq!(catalog.must_read_table("t")?, table);
+        assert_eq!(
+            rows(&catalog, &mut engine)?,
+            vec![vec![Value::Integer(1)], vec![Value::Integer(2)]]
+        );
+        assert_eq!(engine.status()?.keys, 2);
+        assert!(execute(&mut catalog, &mut engine, "ALTER TABLE t RENAME TO u").is_err());
+        Ok(())
+    }
+}
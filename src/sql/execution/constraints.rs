@@ -0,0 +1,19 @@
+use super::super::schema::Table;
+use super::super::types::Value;
+use crate::error::{EasyDbError, EasyDbResult};
+
+/// Checks that a row, in table order, has no NULLs in NOT NULL columns
+pub fn check_not_null(table: &Table, row: &[Value]) -> EasyDbResult<()> {
+    match table
+        .columns
+        .iter()
+        .zip(row)
+        .find(|(column, value)| !column.nullable && **value == Value::Null)
+    {
+        Some((column, _)) => Err(EasyDbError::Value(format!(
+            "Column {} of table {} can't be NULL",
+            column.name, table.name
+        ))),
+        None => Ok(()),
+    }
+}
@@ -0,0 +1,680 @@
+use super::super::parser::ast::{Expression, Literal, Operation};
+use super::super::schema::{Catalog, Table};
+use super::super::types::{coerce, Collation, DataType, EnumType, Value};
+use crate::error::{EasyDbError, EasyDbResult};
+
+use regex::Regex;
+use std::cell::RefCell;
+use std::cmp::Ordering;
+use std::collections::HashMap;
+
+/// Resolves column references while evaluating an expression, typically to
+/// the values of the current input row
+pub trait Environment {
+    /// Looks up a column, optionally qualified with a table name
+    fn lookup(&self, table: Option<&str>, column: &str) -> EasyDbResult<Value>;
+
+    /// Returns the collation of a column, which strings read from it are
+    /// compared and sorted by
+    fn collation(&self, _table: Option<&str>, _column: &str) -> Collation {
+        Collation::Binary
+    }
+
+    /// Looks up a user-defined enum type, e.g. to cast to it
+    fn enum_type(&self, name: &str) -> EasyDbResult<EnumType> {
+        Err(EasyDbError::Value(format!("Type {} does not exist", name)))
+    }
+}
+
+/// A simple environment of unqualified column names
+impl Environment for HashMap<String, Value> {
+    fn lookup(&self, table: Option<&str>, column: &str) -> EasyDbResult<Value> {
+        match (table, self.get(column)) {
+            (None, Some(value)) => Ok(value.clone()),
+            (Some(table), _) => Err(EasyDbError::Value(format!(
+                "Unknown column {}.{}",
+                table, column
+            ))),
+            (None, None) => Err(EasyDbError::Value(format!("Unknown column {}", column))),
+        }
+    }
+}
+
+/// A row of a table, in table order, whose columns may be qualified with
+/// the table's name. Enum types are looked up in the catalog.
+pub struct TableRow<'a> {
+    pub catalog: &'a dyn Catalog,
+    pub table: &'a Table,
+    pub row: &'a [Value],
+}
+
+impl Environment for TableRow<'_> {
+    fn lookup(&self, table: Option<&str>, column: &str) -> EasyDbResult<Value> {
+        let position = self.table.columns.iter().position(|c| c.name == column);
+        match (table, position) {
+            (Some(table), _) if table != self.table.name => Err(EasyDbError::Value(format!(
+                "Unknown column {}.{}",
+                table, column
+            ))),
+            (_, Some(position)) => Ok(self.row[position].clone()),
+            (_, None) => Err(EasyDbError::Value(format!(
+                "Unknown column {} in table {}",
+                column, self.table.name
+            ))),
+        }
+    }
+
+    fn collation(&self, _table: Option<&str>, column: &str) -> Collation {
+        self.table
+            .column(column)
+            .map_or(Collation::Binary, |c| c.collation)
+    }
+
+    fn enum_type(&self, name: &str) -> EasyDbResult<EnumType> {
+        self.catalog.must_read_enum(name)
+    }
+}
+
+/// Compiled regular expressions by pattern. A query evaluating an expression
+/// once per row should share one cache across rows, so that each pattern is
+/// only compiled once.
+#[derive(Debug, Default)]
+pub struct RegexCache(RefCell<HashMap<String, Regex>>);
+
+impl RegexCache {
+    fn get(&self, pattern: &str) -> EasyDbResult<Regex> {
+        if let Some(regex) = self.0.borrow().get(pattern) {
+            return Ok(regex.clone());
+        }
+        let regex = Regex::new(pattern).map_err(|err| {
+            EasyDbError::Value(format!("Invalid regular expression '{}': {}", pattern, err))
+        })?;
+        self.0
+            .borrow_mut()
+            .insert(pattern.to_string(), regex.clone());
+        Ok(regex)
+    }
+}
+
+/// Evaluates an expression, resolving column references through the given
+/// environment. Without one, only constant expressions can be evaluated.
+///
+/// NULL follows SQL's three-valued logic: it propagates through operators and
+/// comparisons, while AND and OR treat it as an unknown truth value.
+pub fn evaluate(expr: &Expression, env: Option<&dyn Environment>) -> EasyDbResult<Value> {
+    evaluate_cached(expr, env, &RegexCache::default())
+}
+
+/// Like evaluate(), but compiling regular expressions through the given cache
+pub fn evaluate_cached(
+    expr: &Expression,
+    env: Option<&dyn Environment>,
+    regexes: &RegexCache,
+) -> EasyDbResult<Value> {
+    Ok(match expr {
+        Expression::Literal(literal) => evaluate_literal(literal),
+        Expression::Field(table, column) => match env {
+            Some(env) => env.lookup(table.as_deref(), column)?,
+            None => {
+                return Err(EasyDbError::Value(format!(
+                    "Can't reference column {} here",
+                    column
+                )))
+            }
+        },
+        Expression::Function(name, _) => {
+            return Err(EasyDbError::Value(format!("Unknown function {}", name)))
+        }
+        Expression::Window(name, _, _) => {
+            return Err(EasyDbError::Value(format!(
+                "Window function {} can't be evaluated here",
+                name
+            )))
+        }
+        Expression::Operation(operation) => evaluate_operation(operation, env, regexes)?,
+        Expression::Subquery(_) => {
+            return Err(EasyDbError::Value(
+                "Subqueries can't be evaluated here".into(),
+            ))
+        }
+    })
+}
+
+fn evaluate_literal(literal: &Literal) -> Value {
+    match literal {
+        Literal::Null => Value::Null,
+        Literal::Boolean(b) => Value::Boolean(*b),
+        Literal::Integer(i) => Value::Integer(*i),
+        Literal::Float(f) => Value::Float(*f),
+        Literal::String(s) => Value::String(s.clone()),
+        Literal::Bytes(b) => Value::Bytes(b.clone()),
+        Literal::Interval(i) => Value::Interval(*i),
+    }
+}
+
+fn evaluate_operation(
+    operation: &Operation,
+    env: Option<&dyn Environment>,
+    regexes: &RegexCache,
+) -> EasyDbResult<Value> {
+    let eval = |expr: &Expression| evaluate_cached(expr, env, regexes);
+    Ok(match operation {
+        Operation::Cast(expr, DataType::Enum(name)) => match env {
+            Some(env) => env.enum_type(name)?.convert(eval(expr)?)?,
+            None => return Err(EasyDbError::Value(format!("Type {} does not exist", name))),
+        },
+        Operation::Cast(expr, datatype) => coerce::cast(eval(expr)?, datatype)?,
+
+        // Logical operators. The right-hand operand isn't evaluated when
+        // the left-hand one decides the result.
+        Operation::And(lhs, rhs) => match truth(eval(lhs)?)? {
+            Some(false) => Value::Boolean(false),
+            lhs => match (lhs, truth(eval(rhs)?)?) {
+                (_, Some(false)) => Value::Boolean(false),
+                (None, _) | (_, None) => Value::Null,
+                (Some(_), Some(_)) => Value::Boolean(true),
+            },
+        },
+        Operation::Or(lhs, rhs) => match truth(eval(lhs)?)? {
+            Some(true) => Value::Boolean(true),
+            lhs => match (lhs, truth(eval(rhs)?)?) {
+                (_, Some(true)) => Value::Boolean(true),
+                (None, _) | (_, None) => Value::Null,
+                (Some(_), Some(_)) => Value::Boolean(false),
+            },
+        },
+        Operation::Not(expr) => match truth(eval(expr)?)? {
+            Some(b) => Value::Boolean(!b),
+            None => Value::Null,
+        },
+
+        // Comparison operators
+        Operation::Equal(lhs, rhs) => {
+            compare(eval(lhs)?, eval(rhs)?, collation(lhs, rhs, env), |o| {
+                o.is_eq()
+            })?
+        }
+        Operation::NotEqual(lhs, rhs) => {
+            compare(eval(lhs)?, eval(rhs)?, collation(lhs, rhs, env), |o| {
+                o.is_ne()
+            })?
+        }
+        Operation::GreaterThan(lhs, rhs) => {
+            compare(eval(lhs)?, eval(rhs)?, collation(lhs, rhs, env), |o| {
+                o.is_gt()
+            })?
+        }
+        Operation::GreaterThanOrEqual(lhs, rhs) => {
+            compare(eval(lhs)?, eval(rhs)?, collation(lhs, rhs, env), |o| {
+                o.is_ge()
+            })?
+        }
+        Operation::LessThan(lhs, rhs) => {
+            compare(eval(lhs)?, eval(rhs)?, collation(lhs, rhs, env), |o| {
+                o.is_lt()
+            })?
+        }
+        Operation::LessThanOrEqual(lhs, rhs) => {
+            compare(eval(lhs)?, eval(rhs)?, collation(lhs, rhs, env), |o| {
+                o.is_le()
+            })?
+        }
+        Operation::IsNull(expr) => Value::Boolean(eval(expr)? == Value::Null),
+        Operation::IsDistinctFrom(lhs, rhs) => match (eval(lhs)?, eval(rhs)?) {
+            (Value::Null, Value::Null) => Value::Boolean(false),
+            (Value::Null, _) | (_, Value::Null) => Value::Boolean(true),
+            (l, r) => compare(l, r, collation(lhs, rhs, env), |o| o.is_ne())?,
+        },
+
+        // Mathematical operators
+        Operation::Add(lhs, rhs) => eval(lhs)?.checked_add(&eval(rhs)?)?,
+        Operation::Subtract(lhs, rhs) => eval(lhs)?.checked_sub(&eval(rhs)?)?,
+        Operation::Multiply(lhs, rhs) => eval(lhs)?.checked_mul(&eval(rhs)?)?,
+        Operation::Divide(lhs, rhs) => eval(lhs)?.checked_div(&eval(rhs)?)?,
+        Operation::Modulo(lhs, rhs) => eval(lhs)?.checked_rem(&eval(rhs)?)?,
+        Operation::Exponentiate(lhs, rhs) => eval(lhs)?.checked_pow(&eval(rhs)?)?,
+        Operation::Negate(expr) => eval(expr)?.checked_neg()?,
+        Operation::Assert(expr) => match eval(expr)? {
+            value @ (Value::Null
+            | Value::Integer(_)
+            | Value::Float(_)
+            | Value::Decimal(_)
+            | Value::Interval(_)) => value,
+            value => {
+                return Err(EasyDbError::Value(format!(
+                    "Can't apply unary plus to {}",
+                    coerce::describe(&value)
+                )))
+            }
+        },
+
+        // String operators
+        Operation::Like(lhs, rhs, escape) => like(eval(lhs)?, eval(rhs)?, *escape, false)?,
+        Operation::ILike(lhs, rhs, escape) => like(eval(lhs)?, eval(rhs)?, *escape, true)?,
+        Operation::Regex(lhs, rhs) => match (eval(lhs)?, eval(rhs)?) {
+            (Value::String(text), Value::String(pattern)) => {
+                Value::Boolean(regexes.get(&pattern)?.is_match(&text))
+            }
+            (Value::Null, _) | (_, Value::Null) => Value::Null,
+            (text, pattern) => {
+                return Err(EasyDbError::Value(format!(
+                    "Can't match {} against {}",
+                    coerce::describe(&text),
+                    coerce::describe(&pattern)
+                )))
+            }
+        },
+
+        // JSON operators
+        Operation::JsonGet(lhs, rhs) => eval(lhs)?.json_get(&eval(rhs)?)?,
+        Operation::JsonGetText(lhs, rhs) => eval(lhs)?.json_get_text(&eval(rhs)?)?,
+    })
+}
+
+/// Interprets a value as a truth value, with NULL as unknown (None)
+fn truth(value: Value) -> EasyDbResult<Option<bool>> {
+    match value {
+        Value::Boolean(b) => Ok(Some(b)),
+        Value::Null => Ok(None),
+        value => Err(EasyDbError::Value(format!(
+            "Expected a boolean, found {}",
+            coerce::describe(&value)
+        ))),
+    }
+}
+
+/// Returns the collation of an expression which reads a column directly,
+/// or None
+pub fn column_collation(expr: &Expression, env: Option<&dyn Environment>) -> Option<Collation> {
+    match (expr, env) {
+        (Expression::Field(table, column), Some(env)) => {
+            Some(env.collation(table.as_deref(), column))
+        }
+        _ => None,
+    }
+}
+
+/// Returns the collation a comparison uses: that of the left operand if it
+/// is a column with a collation other than BINARY, or else the right one's
+fn collation(lhs: &Expression, rhs: &Expression, env: Option<&dyn Environment>) -> Collation {
+    match column_collation(lhs, env) {
+        Some(collation) if collation != Collation::Binary => collation,
+        _ => column_collation(rhs, env).unwrap_or_default(),
+    }
+}
+
+/// Orders two values of the same type, comparing strings by a collation
+pub fn collate(lhs: &Value, rhs: &Value, collation: Collation) -> Ordering {
+    match (lhs, rhs) {
+        (Value::String(lhs), Value::String(rhs)) => collation.compare(lhs, rhs),
+        (lhs, rhs) => lhs.cmp(rhs),
+    }
+}
+
+/// Compares two values by a collation after coercing them to a common type,
+/// giving NULL if either is NULL
+fn compare(
+    lhs: Value,
+    rhs: Value,
+    collation: Collation,
+    check: impl Fn(Ordering) -> bool,
+) -> EasyDbResult<Value> {
+    match coerce::unify(&lhs, &rhs)? {
+        Some((Value::Null, _)) | Some((_, Value::Null)) => Ok(Value::Null),
+        Some((lhs, rhs)) => Ok(Value::Boolean(check(collate(&lhs, &rhs, collation)))),
+        None => Err(EasyDbError::Value(format!(
+            "Can't compare {} and {}",
+            coerce::describe(&lhs),
+            coerce::describe(&rhs)
+        ))),
+    }
+}
+
+/// A LIKE pattern element
+#[derive(PartialEq)]
+enum Wildcard {
+    /// % matches any sequence of characters
+    Any,
+    /// _ matches any single character
+    One,
+    Char(char),
+}
+
+/// Evaluates [I]LIKE, giving NULL if either operand is NULL
+fn like(
+    text: Value,
+    pattern: Value,
+    escape: Option<char>,
+    case_insensitive: bool,
+) -> EasyDbResult<Value> {
+    let (text, pattern) = match (text, pattern) {
+        (Value::String(text), Value::String(pattern)) => (text, pattern),
+        (Value::Null, _) | (_, Value::Null) => return Ok(Value::Null),
+        (text, pattern) => {
+            return Err(EasyDbError::Value(format!(
+                "Can't match {} against {}",
+                coerce::describe(&text),
+                coerce::describe(&pattern)
+            )))
+        }
+    };
+    let fold = |c: char| -> Vec<char> {
+        match case_insensitive {
+            true => c.to_lowercase().collect(),
+            false => vec![c],
+        }
+    };
+
+    let mut wildcards = Vec::new();
+    let mut chars = pattern.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            c if Some(c) == escape => match chars.next() {
+                Some(c) => wildcards.extend(fold(c).into_iter().map(Wildcard::Char)),
+                None => {
+                    return Err(EasyDbError::Value(format!(
+                        "LIKE pattern '{}' ends with the escape character",
+                        pattern
+                    )))
+                }
+            },
+            '%' => wildcards.push(Wildcard::Any),
+            '_' => wildcards.push(Wildcard::One),
+            c => wildcards.extend(fold(c).into_iter().map(Wildcard::Char)),
+        }
+    }
+    let text: Vec<char> = text.chars().flat_map(fold).collect();
+    Ok(Value::Boolean(matches_wildcards(&text, &wildcards)))
+}
+
+/// Matches text against a LIKE pattern. On a mismatch after a %, the %
+/// is retried with one more character, which only ever needs to happen for
+/// the most recent % and so takes at most quadratic time.
+fn matches_wildcards(text: &[char], pattern: &[Wildcard]) -> bool {
+    let (mut t, mut p) = (0, 0);
+    // The positions after the most recent % and where its match ends
+    let mut backtrack = None;
+    while t < text.len() {
+        match pattern.get(p) {
+            Some(Wildcard::Any) => {
+                p += 1;
+                backtrack = Some((p, t));
+                continue;
+            }
+            Some(Wildcard::One) => {
+                (t, p) = (t + 1, p + 1);
+                continue;
+            }
+            Some(Wildcard::Char(c)) if *c == text[t] => {
+                (t, p) = (t + 1, p + 1);
+                continue;
+            }
+            _ => {}
+        }
+        match backtrack {
+            Some((after_any, end)) => {
+                (t, p) = (end + 1, after_any);
+                backtrack = Some((after_any, end + 1));
+            }
+            None => return false,
+        }
+    }
+    pattern[p..].iter().all(|w| *w == Wildcard::Any)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::super::parser::ast::{Parser, Statement};
+    use super::super::super::schema::MemoryCatalog;
+    use super::super::super::types::Interval;
+    use super::*;
+
+    /// Parses and evaluates an expression in an environment
+    fn eval_in(expr: &str, env: Option<&dyn Environment>) -> EasyDbResult<Value> {
+        match Parser::new(&format!("SELECT {}", expr)).parse()? {
+            Statement::Select { select, .. } => evaluate(&select[0].0, env),
+            statement => panic!("expected a SELECT, got {}", statement),
+        }
+    }
+
+    /// Parses and evaluates a constant expression
+    fn eval(expr: &str) -> EasyDbResult<Value> {
+        eval_in(expr, None)
+    }
+
+    #[test]
+    fn three_valued_logic() {
+        let cases = [
+            ("TRUE AND NULL", Value::Null),
+            ("FALSE AND NULL", Value::Boolean(false)),
+            ("TRUE OR NULL", Value::Boolean(true)),
+            ("FALSE OR NULL", Value::Null),
+            ("NOT NULL", Value::Null),
+            ("NOT FALSE", Value::Boolean(true)),
+            ("NULL = NULL", Value::Null),
+            ("1 < NULL", Value::Null),
+            ("NULL IS NULL", Value::Boolean(true)),
+            ("1 IS NULL", Value::Boolean(false)),
+            ("NULL IS DISTINCT FROM NULL", Value::Boolean(false)),
+            ("1 IS DISTINCT FROM NULL", Value::Boolean(true)),
+            ("1 IS NOT DISTINCT FROM 1", Value::Boolean(true)),
+            ("1 + NULL", Value::Null),
+        ];
+        for (expr, expect) in cases {
+            assert_eq!(eval(expr).unwrap(), expect, "{}", expr);
+        }
+        assert!(eval("1 AND TRUE").is_err());
+    }
+
+    #[test]
+    fn short_circuit() {
+        let cases = [
+            ("FALSE AND 1/0 = 1", Value::Boolean(false)),
+            ("TRUE OR 1/0 = 1", Value::Boolean(true)),
+            ("NULL AND FALSE", Value::Boolean(false)),
+            ("NULL OR TRUE", Value::Boolean(true)),
+            ("1 = 2 AND 'a' + 1 = 1", Value::Boolean(false)),
+        ];
+        for (expr, expect) in cases {
+            assert_eq!(eval(expr).unwrap(), expect, "{}", expr);
+        }
+        // The right-hand operand is evaluated when the left-hand one doesn't
+        // decide the result
+        assert!(eval("TRUE AND 1/0 = 1").is_err());
+        assert!(eval("NULL AND 1/0 = 1").is_err());
+        assert!(eval("FALSE OR 1/0 = 1").is_err());
+        assert!(eval("NULL OR 1/0 = 1").is_err());
+    }
+
+    #[test]
+    fn comparison_and_arithmetic() {
+        let cases = [
+            ("1 + 2 * 3", Value::Integer(7)),
+            ("(1 + 2) * 3", Value::Integer(9)),
+            ("7 / 2", Value::Integer(3)),
+            ("7 % 3", Value::Integer(1)),
+            ("-(2 + 3)", Value::Integer(-5)),
+            ("1 + 0.5", Value::Float(1.5)),
+            ("1 = 1.0", Value::Boolean(true)),
+            ("2 > 1.5", Value::Boolean(true)),
+            ("'a' < 'b'", Value::Boolean(true)),
+            ("'a' != 'a'", Value::Boolean(false)),
+            ("CAST('42' AS INTEGER)", Value::Integer(42)),
+            (
+                "CAST('1 day' AS INTERVAL)",
+                Value::Interval("1 day".parse().unwrap()),
+            ),
+            (
+                "'2 hours'::interval",
+                Value::Interval("2 hours".parse().unwrap()),
+            ),
+        ];
+        for (expr, expect) in cases {
+            assert_eq!(eval(expr).unwrap(), expect, "{}", expr);
+        }
+        assert!(eval("9223372036854775807 + 1").is_err());
+        assert!(eval("1 / 0").is_err());
+        assert!(eval("1 = 'a'").is_err());
+        assert!(eval("+'a'").is_err());
+    }
+
+    #[test]
+    fn pattern_matching() {
+        let cases = [
+            ("'abc' LIKE 'a%'", Value::Boolean(true)),
+            ("'abc' LIKE 'a_c'", Value::Boolean(true)),
+            ("'abc' LIKE 'b%'", Value::Boolean(false)),
+            ("'abc' LIKE '%b%c'", Value::Boolean(true)),
+            ("'ABC' LIKE 'a%'", Value::Boolean(false)),
+            ("'ABC' ILIKE 'a%'", Value::Boolean(true)),
+            ("'a%' LIKE 'a!%' ESCAPE '!'", Value::Boolean(true)),
+            ("'ab' LIKE 'a!%' ESCAPE '!'", Value::Boolean(false)),
+            ("'abc' NOT LIKE 'a%'", Value::Boolean(false)),
+            ("'abc' NOT LIKE 'b%'", Value::Boolean(true)),
+            ("'ABC' NOT ILIKE 'a%'", Value::Boolean(false)),
+            ("'a%' NOT LIKE 'a!%' ESCAPE '!'", Value::Boolean(false)),
+            ("NULL NOT LIKE 'a'", Value::Null),
+            ("NULL LIKE 'a'", Value::Null),
+            ("'abc' ~ '^a.c$'", Value::Boolean(true)),
+        ];
+        for (expr, expect) in cases {
+            assert_eq!(eval(expr).unwrap(), expect, "{}", expr);
+        }
+        assert!(eval("'a' LIKE 'a!' ESCAPE '!'").is_err());
+        assert!(eval("'a' ~ '('").is_err());
+    }
+
+    #[test]
+    fn columns() {
+        let env: HashMap<String, Value> = [("a".to_string(), Value::Integer(2))].into();
+        assert_eq!(eval_in("a * 3", Some(&env)).unwrap(), Value::Integer(6));
+        assert!(eval_in("b", Some(&env)).is_err());
+        assert!(eval_in("t.a", Some(&env)).is_err());
+        assert!(eval("a").is_err());
+    }
+
+    #[test]
+    fn collations() {
+        let Statement::CreateTable {
+            name,
+            columns,
+            constraints,
+        } = Parser::new("CREATE TABLE t (a TEXT COLLATE NOCASE, b TEXT)")
+            .parse()
+            .unwrap()
+        else {
+            panic!("expected CREATE TABLE");
+        };
+        let table = Table::from_ast(&name, &columns, &constraints).unwrap();
+        let row = [Value::String("Abc".into()), Value::String("Abc".into())];
+        let env = TableRow {
+            catalog: &MemoryCatalog::new(),
+            table: &table,
+            row: &row,
+        };
+        let cases = [
+            ("a = 'ABC'", true),
+            ("'abc' = t.a", true),
+            ("a <> 'aBC'", false),
+            ("a < 'abd'", true),
+            ("a IS DISTINCT FROM 'abc'", false),
+            ("b = 'ABC'", false),
+            ("b < 'abd'", true),
+            ("b > 'ABD'", true),
+            ("a = b", true),
+            ("b = 'Abc'", true),
+        ];
+        for (expr, expect) in cases {
+            assert_eq!(
+                eval_in(expr, Some(&env)).unwrap(),
+                Value::Boolean(expect),
+                "{}",
+                expr
+            );
+        }
+        assert!(eval_in("u.a", Some(&env)).is_err());
+        assert!(eval_in("c", Some(&env)).is_err());
+    }
+
+    #[test]
+    fn enums() -> EasyDbResult<()> {
+        let mut catalog = MemoryCatalog::new();
+        let Statement::CreateEnum { name, labels } =
+            Parser::new("CREATE TYPE mood AS ENUM ('sad', 'ok', 'happy')").parse()?
+        else {
+            panic!("expected CREATE TYPE");
+        };
+        catalog.create_enum(EnumType { name, labels })?;
+        let mood = catalog.must_read_enum("mood")?;
+        let Statement::CreateTable {
+            name,
+            columns,
+            constraints,
+        } = Parser::new("CREATE TABLE t (id INT PRIMARY KEY, m mood)").parse()?
+        else {
+            panic!("expected CREATE TABLE");
+        };
+        let table = Table::from_ast(&name, &columns, &constraints)?;
+        let row = [Value::Integer(1), mood.value(1).unwrap()];
+        let env = TableRow {
+            catalog: &catalog,
+            table: &table,
+            row: &row,
+        };
+
+        assert_eq!(
+            eval_in("CAST('happy' AS mood)", Some(&env))?,
+            mood.value(2).unwrap()
+        );
+        assert_eq!(eval_in("'sad'::mood", Some(&env))?, mood.value(0).unwrap());
+        assert_eq!(eval_in("CAST(NULL AS mood)", Some(&env))?, Value::Null);
+        assert_eq!(
+            eval_in("CAST(m AS TEXT)", Some(&env))?,
+            Value::String("ok".into())
+        );
+        // Labels compare by their order in the type, not alphabetically
+        let cases = [
+            ("m < 'sad'::mood", false),
+            ("m < 'happy'::mood", true),
+            ("m = 'ok'::mood", true),
+            ("'happy'::mood > 'sad'::mood", true),
+        ];
+        for (expr, expect) in cases {
+            assert_eq!(
+                eval_in(expr, Some(&env))?,
+                Value::Boolean(expect),
+                "{}",
+                expr
+            );
+        }
+        assert!(eval_in("CAST('glad' AS mood)", Some(&env)).is_err());
+        assert!(eval_in("CAST(1 AS mood)", Some(&env)).is_err());
+        assert!(eval_in("CAST('ok' AS unknown)", Some(&env)).is_err());
+        assert!(eval("CAST('ok' AS mood)").is_err());
+        Ok(())
+    }
+
+    fn interval(s: &str) -> Value {
+        Value::Interval(s.parse::<Interval>().unwrap())
+    }
+
+    #[test]
+    fn interval_comparison_and_arithmetic() {
+        assert_eq!(
+            eval("INTERVAL '1 day' = INTERVAL '1 day'").unwrap(),
+            Value::Boolean(true)
+        );
+        assert_eq!(
+            eval("INTERVAL '1 day' < INTERVAL '2 days'").unwrap(),
+            Value::Boolean(true)
+        );
+        assert_eq!(
+            eval("INTERVAL '1 day' + INTERVAL '2 days'").unwrap(),
+            interval("3 days")
+        );
+        assert_eq!(
+            eval("INTERVAL '3 days' - INTERVAL '1 day'").unwrap(),
+            interval("2 days")
+        );
+    }
+}
@@ -0,0 +1,276 @@
+use super::super::schema::{Catalog, Column, Table};
+use super::super::types::coerce::{self, LengthPolicy};
+use super::super::types::{DataType, Value};
+use super::constraints::check_not_null;
+use super::rows::Rows;
+use crate::error::{EasyDbError, EasyDbResult};
+use crate::storage::Engine;
+
+/// Builds a full row for INSERT from the values given for some of a table's
+/// columns, in table order. Without a column list the values are for the
+/// leading columns. Each value is converted to its column's type, see
+/// assign().
+///
+/// Omitted columns take their default, which was evaluated when the table
+/// was created, or NULL if they are nullable. AUTOINCREMENT columns that are
+/// omitted or given NULL take the next value of the table's sequence, which
+/// must be within the range of the column's type.
+/// NULLs are then rejected from NOT NULL columns.
+pub fn build_row(
+    catalog: &mut dyn Catalog,
+    table: &Table,
+    columns: Option<&[String]>,
+    values: Vec<Value>,
+) -> EasyDbResult<Vec<Value>> {
+    let names: Vec<&String> = match columns {
+        Some(columns) => columns.iter().collect(),
+        None => table.columns.iter().map(|c| &c.name).collect(),
+    };
+    if values.len() > names.len() {
+        return Err(EasyDbError::Value(format!(
+            "INSERT has {} values but only {} columns",
+            values.len(),
+            names.len()
+        )));
+    }
+    if columns.is_some() && values.len() < names.len() {
+        return Err(EasyDbError::Value(format!(
+            "INSERT has {} columns but only {} values",
+            names.len(),
+            values.len()
+        )));
+    }
+
+    let mut given: Vec<Option<Value>> = vec![None; table.columns.len()];
+    for (name, value) in names.into_iter().zip(values) {
+        let index = table
+            .columns
+            .iter()
+            .position(|c| &c.name == name)
+            .ok_or_else(|| {
+                EasyDbError::Value(format!("Unknown column {} in table {}", name, table.name))
+            })?;
+        if given[index].is_some() {
+            return Err(EasyDbError::Value(format!(
+                "Column {} is given more than once",
+                name
+            )));
+        }
+        given[index] = Some(value);
+    }
+
+    let mut row = Vec::with_capacity(table.columns.len());
+    for (column, value) in table.columns.iter().zip(given) {
+        let value = match (value, &column.default) {
+            (None | Some(Value::Null), _) if column.autoincrement => {
+                let value = catalog.next_sequence(&table.name)?;
+                assign(catalog, column, Value::Integer(value)).map_err(|_| {
+                    EasyDbError::Value(format!(
+                        "Sequence of table {} reached {}, which is out of range for column {} of type {}",
+                        table.name, value, column.name, column.datatype
+                    ))
+                })?
+            }
+            (Some(value), _) => assign(catalog, column, value)?,
+            (None, Some(default)) => assign(catalog, column, default.clone())?,
+            (None, None) if column.nullable => Value::Null,
+            (None, None) => {
+                return Err(EasyDbError::Value(format!(
+                    "No value given for NOT NULL column {} of table {}, which has no default",
+                    column.name, table.name
+                )))
+            }
+        };
+        row.push(value);
+    }
+    check_not_null(table, &row)?;
+    Ok(row)
+}
+
+/// Converts a value for storage in a column. Strings given for an enum
+/// column must be labels of its type, which is looked up in the catalog.
+pub fn assign(catalog: &dyn Catalog, column: &Column, value: Value) -> EasyDbResult<Value> {
+    match &column.datatype {
+        DataType::Enum(name) => catalog.must_read_enum(name)?.convert(value),
+        datatype => coerce::assign(value, datatype, LengthPolicy::Error),
+    }
+}
+
+/// Inserts a row built by build_row() into the table's rows in an engine,
+/// returning it
+pub fn insert_row(
+    catalog: &mut dyn Catalog,
+    engine: &mut dyn Engine,
+    table: &Table,
+    columns: Option<&[String]>,
+    values: Vec<Value>,
+) -> EasyDbResult<Vec<Value>> {
+    let row = build_row(catalog, table, columns, values)?;
+    Rows::new(catalog, table)?.insert(catalog, engine, row.clone())?;
+    Ok(row)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::super::parser::ast::{Parser, Statement};
+    use super::super::super::schema::MemoryCatalog;
+    use super::super::super::types::EnumType;
+    use super::*;
+
+    /// Creates a table in the catalog from a CREATE TABLE statement
+    fn create(catalog: &mut MemoryCatalog, sql: &str) -> Table {
+        let Statement::CreateTable {
+            name,
+            columns,
+            constraints,
+        } = Parser::new(sql).parse().unwrap()
+        else {
+            panic!("expected CREATE TABLE");
+        };
+        let table = Table::from_ast(&name, &columns, &constraints).unwrap();
+        catalog.create_table(table.clone()).unwrap();
+        table
+    }
+
+    #[test]
+    fn build() -> EasyDbResult<()> {
+        let mut catalog = MemoryCatalog::new();
+        let table = create(
+            &mut catalog,
+            "CREATE TABLE t (id INT PRIMARY KEY AUTOINCREMENT, a TEXT NOT NULL, b INT DEFAULT 7, c FLOAT)",
+        );
+        let columns = ["a".to_string()];
+        let row = build_row(
+            &mut catalog,
+            &table,
+            Some(&columns),
+            vec![Value::String("x".into())],
+        )?;
+        assert_eq!(
+            row,
+            vec![
+                Value::Integer(1),
+                Value::String("x".into()),
+                Value::Integer(7),
+                Value::Null
+            ]
+        );
+        let row = build_row(
+            &mut catalog,
+            &table,
+            None,
+            vec![Value::Null, Value::String("y".into()), Value::Integer(1)],
+        )?;
+        assert_eq!(
+            row[..3],
+            [
+                Value::Integer(2),
+                Value::String("y".into()),
+                Value::Integer(1)
+            ]
+        );
+
+        // A NOT NULL column without a default must be given
+        let columns = ["b".to_string()];
+        assert!(build_row(
+            &mut catalog,
+            &table,
+            Some(&columns),
+            vec![Value::Integer(1)]
+        )
+        .is_err());
+        assert!(build_row(&mut catalog, &table, None, vec![Value::Null, Value::Null]).is_err());
+        // Values must match the columns
+        assert!(build_row(&mut catalog, &table, Some(&columns), vec![]).is_err());
+        let columns = ["a".to_string(), "a".to_string()];
+        let values = vec![Value::String("x".into()), Value::String("y".into())];
+        assert!(build_row(&mut catalog, &table, Some(&columns), values).is_err());
+        let columns = ["z".to_string()];
+        assert!(build_row(&mut catalog, &table, Some(&columns), vec![Value::Null]).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn sequence_overflow() -> EasyDbResult<()> {
+        let mut catalog = MemoryCatalog::new();
+        let table = create(
+            &mut catalog,
+            "CREATE TABLE t (id SMALLINT PRIMARY KEY AUTOINCREMENT)",
+        );
+        for _ in 1..i16::MAX {
+            catalog.next_sequence("t")?;
+        }
+        assert_eq!(
+            build_row(&mut catalog, &table, None, vec![])?,
+            vec![Value::Integer(i16::MAX.into())]
+        );
+        let err = build_row(&mut catalog, &table, None, vec![]).unwrap_err();
+        assert!(err.to_string().contains("out of range"), "{}", err);
+        let values = vec![Value::Integer(i64::from(i16::MAX) + 1)];
+        assert!(build_row(&mut catalog, &table, None, values).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn enums() -> EasyDbResult<()> {
+        let mut catalog = MemoryCatalog::new();
+        catalog.create_enum(EnumType {
+            name: "mood".into(),
+            labels: vec!["sad".into(), "ok".into(), "happy".into()],
+        })?;
+        let mood = catalog.must_read_enum("mood")?;
+        let table = create(
+            &mut catalog,
+            "CREATE TABLE t (id INT PRIMARY KEY, m mood NOT NULL DEFAULT 'ok', n mood)",
+        );
+        let mut engine = crate::storage::Memory::new();
+        let columns = ["id".to_string(), "n".to_string()];
+        let row = insert_row(
+            &mut catalog,
+            &mut engine,
+            &table,
+            Some(&columns),
+            vec![Value::Integer(1), Value::String("happy".into())],
+        )?;
+        assert_eq!(
+            row,
+            vec![
+                Value::Integer(1),
+                mood.value(1).unwrap(),
+                mood.value(2).unwrap()
+            ]
+        );
+        assert_eq!(Rows::new(&catalog, &table)?.scan(&mut engine)?, vec![row]);
+
+        // Labels are validated
+        let values = vec![Value::Integer(2), Value::String("glad".into())];
+        let err = build_row(&mut catalog, &table, Some(&columns), values).unwrap_err();
+        assert!(err.to_string().contains("'glad'"), "{}", err);
+        let values = vec![Value::Integer(2), Value::Integer(1)];
+        assert!(build_row(&mut catalog, &table, Some(&columns), values).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn insert() -> EasyDbResult<()> {
+        let mut catalog = MemoryCatalog::new();
+        let table = create(
+            &mut catalog,
+            "CREATE TABLE t (id INT PRIMARY KEY AUTOINCREMENT, a TEXT UNIQUE)",
+        );
+        let mut engine = crate::storage::Memory::new();
+        let columns = ["a".to_string()];
+        let a = |s: &str| vec![Value::String(s.into())];
+        insert_row(&mut catalog, &mut engine, &table, Some(&columns), a("x"))?;
+        insert_row(&mut catalog, &mut engine, &table, Some(&columns), a("y"))?;
+        assert!(insert_row(&mut catalog, &mut engine, &table, Some(&columns), a("x")).is_err());
+        assert_eq!(
+            Rows::new(&catalog, &table)?.scan(&mut engine)?,
+            vec![
+                vec![Value::Integer(1), Value::String("x".into())],
+                vec![Value::Integer(2), Value::String("y".into())]
+            ]
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,8 @@
+pub mod aggregate;
+pub mod alter;
+pub mod constraints;
+pub mod expr;
+pub mod insert;
+pub mod rows;
+pub mod sort;
+pub mod status;
@@ -0,0 +1,942 @@
+use super::super::schema::{Catalog, ForeignKey, ReferentialAction, Table};
+use super::super::types::{coerce, DataType, EnumType, Value};
+use super::constraints::check_not_null;
+use crate::error::{EasyDbError, EasyDbResult};
+use crate::storage::keycode::{encode_values, write_bytes};
+use crate::storage::{decode_row, encode_row, Engine, SecondaryIndexes};
+
+/// A table's rows stored in an engine, the write path of INSERT, UPDATE and
+/// DELETE. Each row is stored under the key row/{table}/{primary key},
+/// encoded with keycode so that rows are ordered by primary key, and every
+/// write keeps the table's secondary indexes in sync with the rows.
+///
+/// Written rows are checked against the NOT NULL constraints and the
+/// unique ones, the primary key's included. Like unique indexes, primary
+/// keys in NOCASE columns ignore case. Values of enum columns must be
+/// labels of the column's type, and are stored as their ordinals.
+///
+/// Foreign keys are enforced too, against the tables in the catalog. A
+/// written row's foreign key must match a row of the referenced table,
+/// unless one of its values is NULL. Deleting a referenced row follows the
+/// foreign key's ON DELETE action, and a referenced row's key can't be
+/// changed.
+#[derive(Clone, Debug)]
+pub struct Rows {
+    table: Table,
+    indexes: SecondaryIndexes,
+    /// The type of each enum column, by column position
+    enums: Vec<Option<EnumType>>,
+}
+
+impl Rows {
+    /// Reads a table's rows, looking up the types of its enum columns in
+    /// the catalog
+    pub fn new(catalog: &dyn Catalog, table: &Table) -> EasyDbResult<Self> {
+        let enums = table
+            .columns
+            .iter()
+            .map(|column| match &column.datatype {
+                DataType::Enum(name) => catalog.must_read_enum(name).map(Some),
+                _ => Ok(None),
+            })
+            .collect::<EasyDbResult<_>>()?;
+        Ok(Self {
+            table: table.clone(),
+            indexes: SecondaryIndexes::new(table),
+            enums,
+        })
+    }
+
+    /// The table's secondary indexes, e.g. to look rows up with
+    pub fn indexes(&self) -> &SecondaryIndexes {
+        &self.indexes
+    }
+
+    /// Reads the row with a primary key, if it exists
+    pub fn get(
+        &self,
+        engine: &mut dyn Engine,
+        primary_key: &[Value],
+    ) -> EasyDbResult<Option<Vec<Value>>> {
+        engine
+            .get(&self.key(primary_key))?
+            .map(|bytes| self.decode(&bytes))
+            .transpose()
+    }
+
+    /// Reads all rows, in primary key order
+    pub fn scan(&self, engine: &mut dyn Engine) -> EasyDbResult<Vec<Vec<Value>>> {
+        engine
+            .scan_prefix(&self.prefix())
+            .map(|entry| self.decode(&entry?.1))
+            .collect()
+    }
+
+    /// Reads the rows holding a value in an indexed column, in primary key
+    /// order
+    pub fn lookup(
+        &self,
+        engine: &mut dyn Engine,
+        column: &str,
+        value: &Value,
+    ) -> EasyDbResult<Vec<Vec<Value>>> {
+        let mut rows = Vec::new();
+        for primary_key in self.indexes.lookup(engine, column, value)? {
+            rows.push(self.must_get(engine, &primary_key)?);
+        }
+        Ok(rows)
+    }
+
+    /// Reads the rows holding a tuple of values in some columns, through
+    /// the primary key or an index over those columns if there is one, or
+    /// else by scanning the table. A NULL value matches no rows.
+    pub fn find(
+        &self,
+        engine: &mut dyn Engine,
+        columns: &[String],
+        values: &[Value],
+    ) -> EasyDbResult<Vec<Vec<Value>>> {
+        if values.contains(&Value::Null) {
+            return Ok(Vec::new());
+        }
+        if columns == self.table.primary_key {
+            return Ok(self.get(engine, values)?.into_iter().collect());
+        }
+        if let Some(primary_keys) = self.indexes.find(engine, columns, values)? {
+            let mut rows = Vec::new();
+            for primary_key in primary_keys {
+                rows.push(self.must_get(engine, &primary_key)?);
+            }
+            return Ok(rows);
+        }
+        let values = self.normalize(columns, values);
+        Ok(self
+            .scan(engine)?
+            .into_iter()
+            .filter(|row| self.normalize(columns, &self.values(row, columns)) == values)
+            .collect())
+    }
+
+    /// Inserts a row, in table order, failing if it violates a constraint
+    pub fn insert(
+        &self,
+        catalog: &dyn Catalog,
+        engine: &mut dyn Engine,
+        row: Vec<Value>,
+    ) -> EasyDbResult<()> {
+        self.check_references(catalog, engine, &row)?;
+        self.write(engine, row)
+    }
+
+    /// Stores a new row, checking every constraint but foreign keys
+    fn write(&self, engine: &mut dyn Engine, row: Vec<Value>) -> EasyDbResult<()> {
+        check_not_null(&self.table, &row)?;
+        self.check_enums(&row)?;
+        let key = self.key(&self.primary_key(&row));
+        if engine.get(&key)?.is_some() {
+            return Err(self.duplicate(&row));
+        }
+        self.indexes.insert(engine, &row)?;
+        engine.set(&key, self.encode(&row)?)
+    }
+
+    /// Replaces the row with a primary key, which the new row may change,
+    /// failing if the row doesn't exist or the new one violates a constraint
+    pub fn update(
+        &self,
+        catalog: &dyn Catalog,
+        engine: &mut dyn Engine,
+        primary_key: &[Value],
+        row: Vec<Value>,
+    ) -> EasyDbResult<()> {
+        let old = self.must_get(engine, primary_key)?;
+        self.check_references(catalog, engine, &row)?;
+        for Referencing {
+            rows: child,
+            foreign_key,
+            children,
+        } in self.referencing(catalog, engine, &old)?
+        {
+            let columns = self.referenced(&foreign_key);
+            let (old_values, values) = (self.values(&old, columns), self.values(&row, columns));
+            if self.normalize(columns, &old_values) == self.normalize(columns, &values) {
+                continue;
+            }
+            if children.iter().any(|c| !child.same_row(c, self, &old)) {
+                return Err(EasyDbError::Value(format!(
+                    "Can't change key ({}) = ({}) of table {}, foreign key ({}) of table {} references it",
+                    columns.join(", "),
+                    display(&old_values),
+                    self.table.name,
+                    foreign_key.columns.join(", "),
+                    child.table.name
+                )));
+            }
+        }
+        self.rewrite(engine, primary_key, row)
+    }
+
+    /// Replaces a stored row, checking every constraint but foreign keys
+    fn rewrite(
+        &self,
+        engine: &mut dyn Engine,
+        primary_key: &[Value],
+        row: Vec<Value>,
+    ) -> EasyDbResult<()> {
+        check_not_null(&self.table, &row)?;
+        self.check_enums(&row)?;
+        let old = self.must_get(engine, primary_key)?;
+        let (old_key, key) = (self.key(primary_key), self.key(&self.primary_key(&row)));
+        if key != old_key && engine.get(&key)?.is_some() {
+            return Err(self.duplicate(&row));
+        }
+        self.indexes.update(engine, &old, &row)?;
+        if key != old_key {
+            engine.delete(&old_key)?;
+        }
+        engine.set(&key, self.encode(&row)?)
+    }
+
+    /// Rewrites every row for a new definition of the table, as ALTER TABLE
+    /// ADD/DROP COLUMN changes it, returning the rows of the new table.
+    /// Columns are matched by name: dropped ones are removed from the rows
+    /// and added ones take their default, or NULL. If a rewritten row
+    /// violates a constraint of the new table, e.g. an added unique column
+    /// whose default repeats, the rows are left as they were.
+    pub fn alter(
+        &self,
+        catalog: &dyn Catalog,
+        engine: &mut dyn Engine,
+        table: &Table,
+    ) -> EasyDbResult<Rows> {
+        let altered = Rows::new(catalog, table)?;
+        let defaults = table
+            .columns
+            .iter()
+            .zip(&altered.enums)
+            .map(|(column, datatype)| {
+                let default = column.default.clone().unwrap_or(Value::Null);
+                match datatype {
+                    Some(datatype) => datatype.convert(default),
+                    None => Ok(default),
+                }
+            })
+            .collect::<EasyDbResult<Vec<_>>>()?;
+        let old_rows = self.scan(engine)?;
+        let new_rows: Vec<Vec<Value>> = old_rows
+            .iter()
+            .map(|row| {
+                table
+                    .columns
+                    .iter()
+                    .zip(&defaults)
+                    .map(|(column, default)| {
+                        match self
+                            .table
+                            .columns
+                            .iter()
+                            .position(|c| c.name == column.name)
+                        {
+                            Some(position) => row[position].clone(),
+                            None => default.clone(),
+                        }
+                    })
+                    .collect()
+            })
+            .collect();
+        for row in &old_rows {
+            self.remove(engine, &self.primary_key(row))?;
+        }
+        for (i, row) in new_rows.iter().enumerate() {
+            if let Err(err) = altered.write(engine, row.clone()) {
+                for row in &new_rows[..i] {
+                    altered.remove(engine, &altered.primary_key(row))?;
+                }
+                for row in old_rows {
+                    self.write(engine, row)?;
+                }
+                return Err(err);
+            }
+        }
+        Ok(altered)
+    }
+
+    /// Deletes the row with a primary key, returning it if it existed. Rows
+    /// referencing it through a foreign key block the deletion, are deleted
+    /// too, or have their foreign key set to NULL, following its ON DELETE
+    /// action. Nothing is deleted if any row blocks the deletion.
+    pub fn delete(
+        &self,
+        catalog: &dyn Catalog,
+        engine: &mut dyn Engine,
+        primary_key: &[Value],
+    ) -> EasyDbResult<Option<Vec<Value>>> {
+        let Some(row) = self.get(engine, primary_key)? else {
+            return Ok(None);
+        };
+        // Find the rows ON DELETE CASCADE deletes along with this one and
+        // those ON DELETE SET NULL changes before changing anything
+        let mut deletes = vec![(self.clone(), row.clone())];
+        let mut set_null = Vec::new();
+        let mut i = 0;
+        while i < deletes.len() {
+            let (parent, parent_row) = deletes[i].clone();
+            for Referencing {
+                rows: child,
+                foreign_key,
+                children,
+            } in parent.referencing(catalog, engine, &parent_row)?
+            {
+                for child_row in children {
+                    if deletes
+                        .iter()
+                        .any(|(rows, row)| child.same_row(&child_row, rows, row))
+                    {
+                        continue;
+                    }
+                    match foreign_key.on_delete {
+                        ReferentialAction::Restrict => {
+                            let columns = parent.referenced(&foreign_key);
+                            return Err(EasyDbError::Value(format!(
+                                "Can't delete key ({}) = ({}) of table {}, foreign key ({}) of table {} references it",
+                                columns.join(", "),
+                                display(&parent.values(&parent_row, columns)),
+                                parent.table.name,
+                                foreign_key.columns.join(", "),
+                                child.table.name
+                            )));
+                        }
+                        ReferentialAction::Cascade => deletes.push((child.clone(), child_row)),
+                        ReferentialAction::SetNull => set_null.push((
+                            child.clone(),
+                            foreign_key.columns.clone(),
+                            child.primary_key(&child_row),
+                        )),
+                    }
+                }
+            }
+            i += 1;
+        }
+
+        for (rows, row) in &deletes {
+            rows.remove(engine, &rows.primary_key(row))?;
+        }
+        for (rows, columns, primary_key) in set_null {
+            // The row may have been deleted by a cascade, or had another
+            // foreign key set to NULL already
+            let Some(mut row) = rows.get(engine, &primary_key)? else {
+                continue;
+            };
+            for column in &columns {
+                if let Some(position) = rows.position(column) {
+                    row[position] = Value::Null;
+                }
+            }
+            rows.rewrite(engine, &primary_key, row)?;
+        }
+        Ok(Some(row))
+    }
+
+    /// Removes a stored row, ignoring foreign keys
+    fn remove(&self, engine: &mut dyn Engine, primary_key: &[Value]) -> EasyDbResult<()> {
+        if let Some(row) = self.get(engine, primary_key)? {
+            self.indexes.delete(engine, &row)?;
+            engine.delete(&self.key(primary_key))?;
+        }
+        Ok(())
+    }
+
+    /// Checks that a row's foreign keys match rows of the tables they
+    /// reference. A row may reference itself.
+    fn check_references(
+        &self,
+        catalog: &dyn Catalog,
+        engine: &mut dyn Engine,
+        row: &[Value],
+    ) -> EasyDbResult<()> {
+        for foreign_key in &self.table.foreign_keys {
+            let values = self.values(row, &foreign_key.columns);
+            if values.contains(&Value::Null) {
+                continue;
+            }
+            let target = match foreign_key.table == self.table.name {
+                true => self.clone(),
+                false => Rows::new(catalog, &catalog.must_read_table(&foreign_key.table)?)?,
+            };
+            let columns = target.referenced(foreign_key);
+            if foreign_key.table == self.table.name
+                && self.normalize(columns, &self.values(row, columns))
+                    == self.normalize(columns, &values)
+            {
+                continue;
+            }
+            if target.find(engine, columns, &values)?.is_empty() {
+                return Err(EasyDbError::Value(format!(
+                    "Key ({}) = ({}) of table {} references no row of table {}",
+                    foreign_key.columns.join(", "),
+                    display(&values),
+                    self.table.name,
+                    target.table.name
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    /// Finds the rows of every table referencing a row of this one through
+    /// a foreign key, along with the foreign key
+    fn referencing(
+        &self,
+        catalog: &dyn Catalog,
+        engine: &mut dyn Engine,
+        row: &[Value],
+    ) -> EasyDbResult<Vec<Referencing>> {
+        let mut referencing = Vec::new();
+        for table in catalog.scan_tables()? {
+            for foreign_key in table
+                .foreign_keys
+                .iter()
+                .filter(|fk| fk.table == self.table.name)
+            {
+                let child = match table.name == self.table.name {
+                    true => self.clone(),
+                    false => Rows::new(catalog, &table)?,
+                };
+                let values = self.values(row, self.referenced(foreign_key));
+                let children = child.find(engine, &foreign_key.columns, &values)?;
+                if !children.is_empty() {
+                    referencing.push(Referencing {
+                        rows: child,
+                        foreign_key: foreign_key.clone(),
+                        children,
+                    });
+                }
+            }
+        }
+        Ok(referencing)
+    }
+
+    /// The columns of this table a foreign key references
+    fn referenced<'a>(&'a self, foreign_key: &'a ForeignKey) -> &'a [String] {
+        match foreign_key.references.is_empty() {
+            true => &self.table.primary_key,
+            false => &foreign_key.references,
+        }
+    }
+
+    /// Whether a row of this table is the same as a row of another
+    fn same_row(&self, row: &[Value], other: &Rows, other_row: &[Value]) -> bool {
+        self.key(&self.primary_key(row)) == other.key(&other.primary_key(other_row))
+    }
+
+    fn position(&self, column: &str) -> Option<usize> {
+        self.table.columns.iter().position(|c| c.name == column)
+    }
+
+    /// Returns a row's values in some columns
+    fn values(&self, row: &[Value], columns: &[String]) -> Vec<Value> {
+        columns
+            .iter()
+            .filter_map(|name| self.position(name))
+            .map(|p| row[p].clone())
+            .collect()
+    }
+
+    /// Returns values of some columns as their collations see them
+    fn normalize(&self, columns: &[String], values: &[Value]) -> Vec<Value> {
+        columns
+            .iter()
+            .zip(values)
+            .map(|(name, value)| match self.table.column(name) {
+                Some(column) => column.collation.normalize(value),
+                None => value.clone(),
+            })
+            .collect()
+    }
+
+    fn must_get(&self, engine: &mut dyn Engine, primary_key: &[Value]) -> EasyDbResult<Vec<Value>> {
+        self.get(engine, primary_key)?.ok_or_else(|| {
+            EasyDbError::Value(format!(
+                "Table {} has no row with primary key ({})",
+                self.table.name,
+                display(primary_key)
+            ))
+        })
+    }
+
+    /// Checks that the values of enum columns are labels of their types
+    fn check_enums(&self, row: &[Value]) -> EasyDbResult<()> {
+        for ((value, datatype), column) in row.iter().zip(&self.enums).zip(&self.table.columns) {
+            match (value, datatype) {
+                (Value::Null, _) | (_, None) => {}
+                (Value::Enum(_), Some(datatype)) => {
+                    datatype.convert(value.clone())?;
+                }
+                (value, Some(datatype)) => {
+                    return Err(EasyDbError::Value(format!(
+                    "Can't store {} in column {} of table {}, which holds labels of enum type {}",
+                    coerce::describe(value),
+                    column.name,
+                    self.table.name,
+                    datatype.name
+                )))
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Encodes a row for storage, with enum labels as their ordinals
+    fn encode(&self, row: &[Value]) -> EasyDbResult<Vec<u8>> {
+        let row: Vec<Value> = row
+            .iter()
+            .map(|value| match value {
+                Value::Enum(e) => Value::Integer(e.ordinal.into()),
+                value => value.clone(),
+            })
+            .collect();
+        encode_row(&row)
+    }
+
+    /// Decodes a stored row, turning the ordinals of enum columns back into
+    /// labels
+    fn decode(&self, bytes: &[u8]) -> EasyDbResult<Vec<Value>> {
+        decode_row(bytes)?
+            .into_iter()
+            .zip(&self.enums)
+            .map(|(value, datatype)| match (value, datatype) {
+                (Value::Integer(ordinal), Some(datatype)) => u32::try_from(ordinal)
+                    .ok()
+                    .and_then(|ordinal| datatype.value(ordinal))
+                    .ok_or_else(|| {
+                        EasyDbError::Internal(format!(
+                            "Invalid ordinal {} of enum type {}",
+                            ordinal, datatype.name
+                        ))
+                    }),
+                (value, _) => Ok(value),
+            })
+            .collect()
+    }
+
+    /// Returns a row's primary key
+    fn primary_key(&self, row: &[Value]) -> Vec<Value> {
+        self.values(row, &self.table.primary_key)
+    }
+
+    /// The key prefix shared by the table's rows
+    fn prefix(&self) -> Vec<u8> {
+        let mut key = Vec::new();
+        write_bytes(&mut key, b"row");
+        write_bytes(&mut key, self.table.name.as_bytes());
+        key
+    }
+
+    /// The key a row is stored under, given its primary key, which is
+    /// normalized according to the collations of its columns
+    fn key(&self, primary_key: &[Value]) -> Vec<u8> {
+        let primary_key: Vec<Value> = self
+            .table
+            .primary_key
+            .iter()
+            .zip(primary_key)
+            .map(|(name, value)| match self.table.column(name) {
+                Some(column) => column.collation.normalize(value),
+                None => value.clone(),
+            })
+            .collect();
+        let mut key = self.prefix();
+        key.extend(encode_values(&primary_key));
+        key
+    }
+
+    /// The error for a row whose primary key is already taken
+    fn duplicate(&self, row: &[Value]) -> EasyDbError {
+        EasyDbError::Value(format!(
+            "Duplicate key ({}) = ({}) violates unique constraint {}_pkey of table {}",
+            self.table.primary_key.join(", "),
+            display(&self.primary_key(row)),
+            self.table.name,
+            self.table.name
+        ))
+    }
+}
+
+/// The rows of a table referencing a row through one of its foreign keys
+struct Referencing {
+    rows: Rows,
+    foreign_key: ForeignKey,
+    children: Vec<Vec<Value>>,
+}
+
+/// Formats a tuple of values for error messages
+fn display(values: &[Value]) -> String {
+    let values: Vec<String> = values.iter().map(|v| v.to_string()).collect();
+    values.join(", ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::super::parser::ast::{Parser, Statement};
+    use super::super::super::schema::MemoryCatalog;
+    use super::*;
+    use crate::storage::Memory;
+
+    fn rows(sql: &str) -> Rows {
+        let Statement::CreateTable {
+            name,
+            columns,
+            constraints,
+        } = Parser::new(sql).parse().unwrap()
+        else {
+            panic!("expected CREATE TABLE");
+        };
+        let table = Table::from_ast(&name, &columns, &constraints).unwrap();
+        Rows::new(&MemoryCatalog::new(), &table).unwrap()
+    }
+
+    fn row(id: &str, a: i64, b: Option<&str>) -> Vec<Value> {
+        vec![
+            Value::String(id.into()),
+            Value::Integer(a),
+            b.map_or(Value::Null, |b| Value::String(b.into())),
+        ]
+    }
+
+    /// Checks that looking up every value of the indexed columns gives the
+    /// rows of the table holding it
+    fn check_indexes(rows: &Rows, engine: &mut dyn Engine) -> EasyDbResult<()> {
+        let all = rows.scan(engine)?;
+        for (column, position) in [("a", 1), ("b", 2)] {
+            for value in all.iter().map(|row| row[position].clone()) {
+                let expect: Vec<Vec<Value>> = all
+                    .iter()
+                    .filter(|row| value != Value::Null && row[position] == value)
+                    .cloned()
+                    .collect();
+                assert_eq!(
+                    rows.lookup(engine, column, &value)?,
+                    expect,
+                    "{} = {}",
+                    column,
+                    value
+                );
+            }
+        }
+        Ok(())
+    }
+
+    const TABLE: &str =
+        "CREATE TABLE t (id TEXT PRIMARY KEY COLLATE NOCASE, a INT INDEX, b TEXT UNIQUE)";
+
+    #[test]
+    fn insert_and_read() -> EasyDbResult<()> {
+        let rows = rows(TABLE);
+        let catalog = MemoryCatalog::new();
+        let mut engine = Memory::new();
+        rows.insert(&catalog, &mut engine, row("b", 1, Some("x")))?;
+        rows.insert(&catalog, &mut engine, row("a", 1, None))?;
+        rows.insert(&catalog, &mut engine, row("c", 2, None))?;
+        assert_eq!(
+            rows.scan(&mut engine)?,
+            vec![row("a", 1, None), row("b", 1, Some("x")), row("c", 2, None)]
+        );
+        assert_eq!(
+            rows.get(&mut engine, &[Value::String("B".into())])?,
+            Some(row("b", 1, Some("x")))
+        );
+        assert_eq!(rows.get(&mut engine, &[Value::String("d".into())])?, None);
+        check_indexes(&rows, &mut engine)
+    }
+
+    #[test]
+    fn constraint_violations_write_nothing() -> EasyDbResult<()> {
+        let rows = rows(TABLE);
+        let catalog = MemoryCatalog::new();
+        let mut engine = Memory::new();
+        rows.insert(&catalog, &mut engine, row("a", 1, Some("x")))?;
+        let err = rows
+            .insert(&catalog, &mut engine, row("A", 2, None))
+            .unwrap_err();
+        assert!(err.to_string().contains("t_pkey"), "{}", err);
+        let err = rows
+            .insert(&catalog, &mut engine, row("b", 2, Some("x")))
+            .unwrap_err();
+        assert!(err.to_string().contains("t_b_key"), "{}", err);
+        assert!(rows
+            .insert(
+                &catalog,
+                &mut engine,
+                vec![Value::Null, Value::Integer(2), Value::Null]
+            )
+            .is_err());
+        assert_eq!(rows.scan(&mut engine)?, vec![row("a", 1, Some("x"))]);
+        assert_eq!(rows.lookup(&mut engine, "a", &Value::Integer(2))?.len(), 0);
+        check_indexes(&rows, &mut engine)
+    }
+
+    #[test]
+    fn update_and_delete() -> EasyDbResult<()> {
+        let rows = rows(TABLE);
+        let catalog = MemoryCatalog::new();
+        let mut engine = Memory::new();
+        for (id, a, b) in [("a", 1, Some("x")), ("b", 1, Some("y")), ("c", 2, None)] {
+            rows.insert(&catalog, &mut engine, row(id, a, b))?;
+        }
+        let key = |id: &str| vec![Value::String(id.into())];
+
+        rows.update(&catalog, &mut engine, &key("a"), row("a", 2, Some("z")))?;
+        check_indexes(&rows, &mut engine)?;
+        // The updated row may take the values it frees, and its primary key
+        // may change
+        rows.update(&catalog, &mut engine, &key("b"), row("d", 3, Some("x")))?;
+        assert_eq!(rows.get(&mut engine, &key("b"))?, None);
+        check_indexes(&rows, &mut engine)?;
+        // A violation leaves the row and its index entries as they were
+        assert!(rows
+            .update(&catalog, &mut engine, &key("c"), row("c", 2, Some("z")))
+            .is_err());
+        assert!(rows
+            .update(&catalog, &mut engine, &key("c"), row("a", 2, None))
+            .is_err());
+        assert!(rows
+            .update(&catalog, &mut engine, &key("e"), row("e", 2, None))
+            .is_err());
+        assert_eq!(rows.get(&mut engine, &key("c"))?, Some(row("c", 2, None)));
+        check_indexes(&rows, &mut engine)?;
+
+        assert_eq!(
+            rows.delete(&catalog, &mut engine, &key("A"))?,
+            Some(row("a", 2, Some("z")))
+        );
+        assert_eq!(rows.delete(&catalog, &mut engine, &key("a"))?, None);
+        check_indexes(&rows, &mut engine)?;
+        assert_eq!(
+            rows.lookup(&mut engine, "b", &Value::String("z".into()))?
+                .len(),
+            0
+        );
+        rows.insert(&catalog, &mut engine, row("e", 1, Some("z")))?;
+        assert_eq!(
+            rows.scan(&mut engine)?,
+            vec![
+                row("c", 2, None),
+                row("d", 3, Some("x")),
+                row("e", 1, Some("z"))
+            ]
+        );
+        check_indexes(&rows, &mut engine)
+    }
+
+    #[test]
+    fn enums() -> EasyDbResult<()> {
+        let mut catalog = MemoryCatalog::new();
+        let labels = vec!["sad".to_string(), "ok".into(), "happy".into()];
+        for name in ["mood", "other"] {
+            catalog.create_enum(EnumType {
+                name: name.into(),
+                labels: labels.clone(),
+            })?;
+        }
+        let (mood, other) = (
+            catalog.must_read_enum("mood")?,
+            catalog.must_read_enum("other")?,
+        );
+        let Statement::CreateTable {
+            name,
+            columns,
+            constraints,
+        } = Parser::new("CREATE TABLE t (m mood PRIMARY KEY, n mood INDEX)").parse()?
+        else {
+            panic!("expected CREATE TABLE");
+        };
+        let table = Table::from_ast(&name, &columns, &constraints)?;
+        assert!(Rows::new(&MemoryCatalog::new(), &table).is_err());
+        let rows = Rows::new(&catalog, &table)?;
+        let mut engine = Memory::new();
+        for (m, n) in [(2, 0), (0, 0), (1, 2)] {
+            rows.insert(
+                &catalog,
+                &mut engine,
+                vec![mood.value(m).unwrap(), mood.value(n).unwrap()],
+            )?;
+        }
+
+        // Rows are stored with ordinals, and ordered by them
+        let stored: Vec<Vec<Value>> = engine
+            .scan_prefix(&rows.prefix())
+            .map(|entry| decode_row(&entry?.1))
+            .collect::<EasyDbResult<_>>()?;
+        assert_eq!(stored[0], vec![Value::Integer(0), Value::Integer(0)]);
+        let all = rows.scan(&mut engine)?;
+        assert_eq!(
+            all.iter().map(|row| row[0].to_string()).collect::<Vec<_>>(),
+            vec!["sad", "ok", "happy"]
+        );
+        assert_eq!(
+            rows.lookup(&mut engine, "n", &mood.value(0).unwrap())?
+                .len(),
+            2
+        );
+
+        // Only labels of the column's type can be stored
+        for value in [
+            Value::String("ok".into()),
+            Value::Integer(1),
+            other.value(1).unwrap(),
+        ] {
+            assert!(rows
+                .insert(
+                    &catalog,
+                    &mut engine,
+                    vec![mood.value(1).unwrap(), value.clone()]
+                )
+                .is_err());
+            assert!(rows
+                .update(
+                    &catalog,
+                    &mut engine,
+                    &[mood.value(1).unwrap()],
+                    vec![mood.value(1).unwrap(), value]
+                )
+                .is_err());
+        }
+        assert_eq!(rows.scan(&mut engine)?, all);
+        Ok(())
+    }
+
+    /// Creates tables in a catalog from CREATE TABLE statements
+    fn catalog(sqls: &[&str]) -> EasyDbResult<MemoryCatalog> {
+        let mut catalog = MemoryCatalog::new();
+        for sql in sqls {
+            let Statement::CreateTable {
+                name,
+                columns,
+                constraints,
+            } = Parser::new(sql).parse()?
+            else {
+                panic!("expected CREATE TABLE");
+            };
+            catalog.create_table(Table::from_ast(&name, &columns, &constraints)?)?;
+        }
+        Ok(catalog)
+    }
+
+    fn ints(values: &[Option<i64>]) -> Vec<Value> {
+        values
+            .iter()
+            .map(|v| v.map_or(Value::Null, Value::Integer))
+            .collect()
+    }
+
+    #[test]
+    fn foreign_keys() -> EasyDbResult<()> {
+        let catalog = catalog(&[
+            "CREATE TABLE p (id INT PRIMARY KEY, code INT UNIQUE)",
+            "CREATE TABLE r (id INT PRIMARY KEY, p_id INT REFERENCES p)",
+            "CREATE TABLE c (id INT PRIMARY KEY, p_id INT REFERENCES p ON DELETE CASCADE)",
+            "CREATE TABLE n (id INT PRIMARY KEY, p_code INT, \
+                FOREIGN KEY (p_code) REFERENCES p (code) ON DELETE SET NULL)",
+        ])?;
+        let table = |name: &str| Rows::new(&catalog, &catalog.must_read_table(name).unwrap());
+        let (p, r, c, n) = (table("p")?, table("r")?, table("c")?, table("n")?);
+        let mut engine = Memory::new();
+        for id in [1, 2, 3] {
+            p.insert(&catalog, &mut engine, ints(&[Some(id), Some(id * 10)]))?;
+        }
+
+        // A foreign key must match a row of the referenced table, by its
+        // primary key or another unique key, unless it is NULL
+        let err = r
+            .insert(&catalog, &mut engine, ints(&[Some(1), Some(4)]))
+            .unwrap_err();
+        assert!(
+            err.to_string()
+                .contains("Key (p_id) = (4) of table r references no row of table p"),
+            "{}",
+            err
+        );
+        assert!(n
+            .insert(&catalog, &mut engine, ints(&[Some(1), Some(1)]))
+            .is_err());
+        r.insert(&catalog, &mut engine, ints(&[Some(1), Some(1)]))?;
+        r.insert(&catalog, &mut engine, ints(&[Some(2), None]))?;
+        c.insert(&catalog, &mut engine, ints(&[Some(1), Some(2)]))?;
+        c.insert(&catalog, &mut engine, ints(&[Some(2), Some(2)]))?;
+        n.insert(&catalog, &mut engine, ints(&[Some(1), Some(20)]))?;
+        n.insert(&catalog, &mut engine, ints(&[Some(2), Some(30)]))?;
+        assert!(r
+            .update(
+                &catalog,
+                &mut engine,
+                &ints(&[Some(1)]),
+                ints(&[Some(1), Some(5)])
+            )
+            .is_err());
+
+        // RESTRICT blocks deleting a referenced row or changing its key
+        let err = p
+            .delete(&catalog, &mut engine, &ints(&[Some(1)]))
+            .unwrap_err();
+        assert!(
+            err.to_string().contains("foreign key (p_id) of table r"),
+            "{}",
+            err
+        );
+        assert!(p
+            .update(
+                &catalog,
+                &mut engine,
+                &ints(&[Some(1)]),
+                ints(&[Some(4), Some(10)])
+            )
+            .is_err());
+        p.update(
+            &catalog,
+            &mut engine,
+            &ints(&[Some(1)]),
+            ints(&[Some(1), Some(11)]),
+        )?;
+        assert_eq!(p.scan(&mut engine)?.len(), 3);
+
+        // CASCADE deletes the referencing rows, and SET NULL clears their
+        // foreign keys
+        p.delete(&catalog, &mut engine, &ints(&[Some(2)]))?;
+        assert_eq!(c.scan(&mut engine)?, Vec::<Vec<Value>>::new());
+        assert_eq!(
+            n.scan(&mut engine)?,
+            vec![ints(&[Some(1), None]), ints(&[Some(2), Some(30)])]
+        );
+        p.delete(&catalog, &mut engine, &ints(&[Some(3)]))?;
+        assert_eq!(
+            n.scan(&mut engine)?,
+            vec![ints(&[Some(1), None]), ints(&[Some(2), None])]
+        );
+        r.delete(&catalog, &mut engine, &ints(&[Some(1)]))?;
+        p.delete(&catalog, &mut engine, &ints(&[Some(1)]))?;
+        assert_eq!(p.scan(&mut engine)?, Vec::<Vec<Value>>::new());
+        Ok(())
+    }
+
+    #[test]
+    fn self_referencing_foreign_keys() -> EasyDbResult<()> {
+        let catalog = catalog(&[
+            "CREATE TABLE t (id INT PRIMARY KEY, parent INT REFERENCES t ON DELETE CASCADE)",
+        ])?;
+        let t = Rows::new(&catalog, &catalog.must_read_table("t")?)?;
+        let mut engine = Memory::new();
+        // A row may reference itself
+        t.insert(&catalog, &mut engine, ints(&[Some(1), Some(1)]))?;
+        t.insert(&catalog, &mut engine, ints(&[Some(2), Some(1)]))?;
+        t.insert(&catalog, &mut engine, ints(&[Some(3), Some(2)]))?;
+        t.insert(&catalog, &mut engine, ints(&[Some(4), None]))?;
+        assert!(t
+            .insert(&catalog, &mut engine, ints(&[Some(5), Some(6)]))
+            .is_err());
+
+        // Deleting cascades down the tree, and stops at the cycle
+        t.delete(&catalog, &mut engine, &ints(&[Some(1)]))?;
+        assert_eq!(t.scan(&mut engine)?, vec![ints(&[Some(4), None])]);
+        Ok(())
+    }
+}
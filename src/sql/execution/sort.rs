@@ -0,0 +1,123 @@
+use super::super::parser::ast::{Expression, Order};
+use super::super::types::{coerce, Collation};
+use super::expr::{collate, column_collation, evaluate_cached, Environment, RegexCache};
+use crate::error::EasyDbResult;
+
+use std::cmp::Ordering;
+
+/// Sorts rows as ORDER BY does, by the values of each expression in turn.
+/// Strings read directly from a column are compared by the column's
+/// collation, and others byte by byte. Values that can't be coerced to a
+/// common type are ordered by type, which puts NULLs first when ascending
+/// and last when descending. The sort is stable.
+pub fn order_by<E: Environment>(
+    rows: Vec<E>,
+    order: &[(Expression, Order)],
+) -> EasyDbResult<Vec<E>> {
+    let regexes = RegexCache::default();
+    let mut keyed = rows
+        .into_iter()
+        .map(|row| {
+            let keys = order
+                .iter()
+                .map(|(expr, _)| evaluate_cached(expr, Some(&row), &regexes))
+                .collect::<EasyDbResult<Vec<_>>>()?;
+            Ok((keys, row))
+        })
+        .collect::<EasyDbResult<Vec<_>>>()?;
+    let collations: Vec<Collation> = match keyed.first() {
+        Some((_, row)) => order
+            .iter()
+            .map(|(expr, _)| column_collation(expr, Some(row)).unwrap_or_default())
+            .collect(),
+        None => return Ok(Vec::new()),
+    };
+
+    keyed.sort_by(|(lhs, _), (rhs, _)| {
+        for (((lhs, rhs), (_, direction)), collation) in
+            lhs.iter().zip(rhs).zip(order).zip(&collations)
+        {
+            let ordering = match coerce::unify(lhs, rhs) {
+                Ok(Some((lhs, rhs))) => collate(&lhs, &rhs, *collation),
+                _ => lhs.cmp(rhs),
+            };
+            let ordering = match direction {
+                Order::Ascending => ordering,
+                Order::Descending => ordering.reverse(),
+            };
+            if ordering != Ordering::Equal {
+                return ordering;
+            }
+        }
+        Ordering::Equal
+    });
+    Ok(keyed.into_iter().map(|(_, row)| row).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::super::parser::ast::{Parser, Statement};
+    use super::super::super::schema::{MemoryCatalog, Table};
+    use super::super::super::types::Value;
+    use super::super::expr::TableRow;
+    use super::*;
+
+    fn table() -> Table {
+        let Statement::CreateTable {
+            name,
+            columns,
+            constraints,
+        } = Parser::new("CREATE TABLE t (id INT PRIMARY KEY, a TEXT COLLATE NOCASE, b TEXT)")
+            .parse()
+            .unwrap()
+        else {
+            panic!("expected CREATE TABLE");
+        };
+        Table::from_ast(&name, &columns, &constraints).unwrap()
+    }
+
+    /// Sorts the rows by an ORDER BY clause, returning their ids
+    fn sort(table: &Table, rows: &[Vec<Value>], clause: &str) -> EasyDbResult<Vec<i64>> {
+        let Statement::Select { order, .. } =
+            Parser::new(&format!("SELECT * FROM t ORDER BY {}", clause)).parse()?
+        else {
+            panic!("expected a SELECT");
+        };
+        let catalog = MemoryCatalog::new();
+        let rows = rows
+            .iter()
+            .map(|row| TableRow {
+                catalog: &catalog,
+                table,
+                row,
+            })
+            .collect();
+        Ok(order_by(rows, &order)?
+            .into_iter()
+            .map(|row| match row.row[0] {
+                Value::Integer(id) => id,
+                _ => panic!("expected an integer id"),
+            })
+            .collect())
+    }
+
+    #[test]
+    fn collations() -> EasyDbResult<()> {
+        let table = table();
+        let text = |s: &str| Value::String(s.into());
+        let rows = vec![
+            vec![Value::Integer(1), text("b"), text("b")],
+            vec![Value::Integer(2), text("A"), text("A")],
+            vec![Value::Integer(3), Value::Null, Value::Null],
+            vec![Value::Integer(4), text("a"), text("a")],
+            vec![Value::Integer(5), text("B"), text("B")],
+        ];
+        assert_eq!(sort(&table, &rows, "a")?, vec![3, 2, 4, 1, 5]);
+        assert_eq!(sort(&table, &rows, "t.a DESC")?, vec![1, 5, 2, 4, 3]);
+        assert_eq!(sort(&table, &rows, "b")?, vec![3, 2, 5, 4, 1]);
+        assert_eq!(sort(&table, &rows, "a, b DESC")?, vec![3, 4, 2, 1, 5]);
+        assert_eq!(sort(&table, &rows, "id * -1")?, vec![5, 4, 3, 2, 1]);
+        assert!(sort(&table, &rows, "c").is_err());
+        Ok(())
+    }
+}
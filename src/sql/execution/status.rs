@@ -0,0 +1,94 @@
+use super::super::schema::information_schema::SystemTable;
+use super::super::types::Value;
+use crate::storage::{CacheStatus, Status};
+
+/// Builds the result of SHOW STATUS from an engine's status, as a row per
+/// statistic with its name and value. The cache statistics are NULL for
+/// engines without a page cache.
+pub fn show_status(status: &Status) -> SystemTable {
+    let cache = |value: fn(&CacheStatus) -> Value| status.cache.as_ref().map_or(Value::Null, value);
+    let rows = vec![
+        ("engine", Value::String(status.name.clone())),
+        ("keys", Value::Integer(status.keys as i64)),
+        ("size", Value::Integer(status.size as i64)),
+        ("disk_size", Value::Integer(status.disk_size as i64)),
+        (
+            "garbage_disk_size",
+            Value::Integer(status.garbage_disk_size as i64),
+        ),
+        ("garbage_ratio", Value::Float(status.garbage_ratio())),
+        ("cache_pages", cache(|c| Value::Integer(c.pages as i64))),
+        (
+            "cache_capacity",
+            cache(|c| Value::Integer(c.capacity as i64)),
+        ),
+        ("cache_hits", cache(|c| Value::Integer(c.hits as i64))),
+        ("cache_misses", cache(|c| Value::Integer(c.misses as i64))),
+        ("cache_hit_rate", cache(|c| Value::Float(c.hit_rate()))),
+    ];
+    SystemTable {
+        columns: vec!["name".into(), "value".into()],
+        rows: rows
+            .into_iter()
+            .map(|(name, value)| vec![Value::String(name.into()), value])
+            .collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::{Engine, Memory, Paged};
+
+    #[test]
+    fn show_status_rows() -> crate::error::EasyDbResult<()> {
+        let mut engine = Memory::new();
+        engine.set(b"a", vec![1, 2])?;
+        engine.set(b"bc", vec![])?;
+        let table = show_status(&engine.status()?);
+        assert_eq!(table.columns, vec!["name".to_string(), "value".to_string()]);
+        let row = |name: &str, value: Value| vec![Value::String(name.into()), value];
+        assert_eq!(
+            table.rows,
+            vec![
+                row("engine", Value::String("memory".into())),
+                row("keys", Value::Integer(2)),
+                row("size", Value::Integer(5)),
+                row("disk_size", Value::Integer(0)),
+                row("garbage_disk_size", Value::Integer(0)),
+                row("garbage_ratio", Value::Float(0.0)),
+                row("cache_pages", Value::Null),
+                row("cache_capacity", Value::Null),
+                row("cache_hits", Value::Null),
+                row("cache_misses", Value::Null),
+                row("cache_hit_rate", Value::Null),
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn show_status_cache_rows() -> crate::error::EasyDbResult<()> {
+        let dir = tempfile::tempdir()?;
+        let mut engine = Paged::open(dir.path().join("pages"), 8, None)?;
+        engine.set(b"a", vec![1, 2])?;
+        engine.get(b"a")?;
+        engine.get(b"a")?;
+        let table = show_status(&engine.status()?);
+        let value = |name: &str| {
+            table
+                .rows
+                .iter()
+                .find(|row| row[0] == Value::String(name.into()))
+                .map(|row| row[1].clone())
+        };
+        assert_eq!(value("engine"), Some(Value::String("paged".into())));
+        assert_eq!(value("cache_pages"), Some(Value::Integer(1)));
+        assert_eq!(value("cache_capacity"), Some(Value::Integer(8)));
+        // Opening the file reads page 0, and every later use finds it cached
+        assert_eq!(value("cache_hits"), Some(Value::Integer(3)));
+        assert_eq!(value("cache_misses"), Some(Value::Integer(1)));
+        assert_eq!(value("cache_hit_rate"), Some(Value::Float(0.75)));
+        Ok(())
+    }
+}
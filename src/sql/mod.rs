@@ -1,2 +1,5 @@
+pub mod execution;
+pub mod optimizer;
 pub mod parser;
+pub mod schema;
 pub mod types;
@@ -0,0 +1,131 @@
+use super::super::execution::expr::evaluate;
+use super::super::parser::ast::{Expression, Literal, Operation, Statement};
+use super::super::parser::visitor::{walk_expression_mut, walk_statement_mut, VisitorMut};
+use super::super::types::Value;
+
+/// Evaluates constant sub-expressions ahead of execution, e.g. rewriting
+/// 2 + 3 to 5, and simplifies AND and OR with a constant TRUE or FALSE
+/// operand. WHERE and HAVING clauses which fold to TRUE are removed.
+///
+/// Expressions that fail to evaluate, such as 1 / 0, are left as they are
+/// for execution to report, as are results with no literal syntax.
+pub fn fold_constants(statement: &mut Statement) {
+    ConstantFolder.visit_statement_mut(statement)
+}
+
+struct ConstantFolder;
+
+impl VisitorMut for ConstantFolder {
+    fn visit_statement_mut(&mut self, statement: &mut Statement) {
+        walk_statement_mut(self, statement);
+        if let Statement::Select {
+            r#where, having, ..
+        } = statement
+        {
+            for clause in [r#where, having] {
+                if matches!(clause, Some(Expression::Literal(Literal::Boolean(true)))) {
+                    *clause = None;
+                }
+            }
+        }
+    }
+
+    fn visit_expression_mut(&mut self, expr: &mut Expression) {
+        // Fold bottom-up, so that operands are already literals where possible
+        walk_expression_mut(self, expr);
+        if let Some(folded) = fold(expr) {
+            *expr = folded;
+        }
+    }
+}
+
+/// Folds an operation whose operands have already been folded
+fn fold(expr: &Expression) -> Option<Expression> {
+    use Literal::Boolean;
+    let operation = match expr {
+        Expression::Operation(operation) => operation,
+        _ => return None,
+    };
+    match operation {
+        Operation::And(lhs, rhs) => match (lhs.as_ref(), rhs.as_ref()) {
+            (Expression::Literal(_), Expression::Literal(_)) => {}
+            (Expression::Literal(Boolean(false)), _) | (_, Expression::Literal(Boolean(false))) => {
+                return Some(Boolean(false).into())
+            }
+            (Expression::Literal(Boolean(true)), expr)
+            | (expr, Expression::Literal(Boolean(true))) => return Some(expr.clone()),
+            _ => {}
+        },
+        Operation::Or(lhs, rhs) => match (lhs.as_ref(), rhs.as_ref()) {
+            (Expression::Literal(_), Expression::Literal(_)) => {}
+            (Expression::Literal(Boolean(true)), _) | (_, Expression::Literal(Boolean(true))) => {
+                return Some(Boolean(true).into())
+            }
+            (Expression::Literal(Boolean(false)), expr)
+            | (expr, Expression::Literal(Boolean(false))) => return Some(expr.clone()),
+            _ => {}
+        },
+        _ => {}
+    }
+    // Expressions referencing columns or unknown functions fail to evaluate
+    // without an environment, and are left alone
+    let literal = match evaluate(expr, None).ok()? {
+        Value::Null => Literal::Null,
+        Value::Boolean(b) => Literal::Boolean(b),
+        Value::Integer(i) => Literal::Integer(i),
+        Value::Float(f) if f.is_finite() => Literal::Float(f),
+        Value::String(s) => Literal::String(s),
+        Value::Bytes(b) => Literal::Bytes(b),
+        Value::Interval(i) => Literal::Interval(i),
+        Value::Float(_) | Value::Decimal(_) | Value::Json(_) | Value::Enum(_) => return None,
+    };
+    Some(literal.into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::super::parser::ast::Parser;
+    use super::*;
+
+    /// Parses a statement, folds its constants and returns it as SQL
+    fn folded(sql: &str) -> String {
+        let mut statement = Parser::new(sql).parse().unwrap();
+        fold_constants(&mut statement);
+        statement.to_string()
+    }
+
+    #[test]
+    fn folding() {
+        let cases = [
+            ("SELECT 2 + 3 * 4", "SELECT 14"),
+            ("SELECT a + (1 + 1) FROM t", "SELECT a + 2 FROM t"),
+            ("SELECT 'a' LIKE 'a%'", "SELECT TRUE"),
+            ("SELECT a FROM t WHERE 1 = 1", "SELECT a FROM t"),
+            (
+                "SELECT a FROM t WHERE a = 1 AND TRUE",
+                "SELECT a FROM t WHERE a = 1",
+            ),
+            (
+                "SELECT a FROM t WHERE a = 1 AND 1 = 2",
+                "SELECT a FROM t WHERE FALSE",
+            ),
+            ("SELECT a FROM t WHERE a = 1 OR 2 > 1", "SELECT a FROM t"),
+            (
+                "SELECT a FROM t WHERE FALSE OR a = 1",
+                "SELECT a FROM t WHERE a = 1",
+            ),
+            ("SELECT NULL + 1", "SELECT NULL"),
+            // Failures are left for execution to report
+            ("SELECT 1 / 0", "SELECT 1 / 0"),
+            ("SELECT f(1 + 1)", "SELECT f(2)"),
+            // Decimals have no literal syntax
+            (
+                "SELECT CAST(1 AS DECIMAL(3, 1))",
+                "SELECT CAST(1 AS DECIMAL(3, 1))",
+            ),
+        ];
+        for (sql, expect) in cases {
+            assert_eq!(folded(sql), expect, "{}", sql);
+        }
+    }
+}
@@ -0,0 +1,85 @@
+use super::super::parser::ast::{FromItem, Parser, Statement};
+use super::super::parser::visitor::{walk_from_item_mut, VisitorMut};
+use super::super::schema::Catalog;
+use crate::error::{EasyDbError, EasyDbResult};
+
+/// Replaces references to views in FROM clauses with their queries, as
+/// subqueries aliased to the view name unless aliased otherwise. Views
+/// reading from other views are expanded too, and a view that ends up
+/// reading from itself is an error.
+pub fn expand_views(statement: &mut Statement, catalog: &dyn Catalog) -> EasyDbResult<()> {
+    let mut expander = ViewExpander {
+        catalog,
+        expanding: Vec::new(),
+        error: None,
+    };
+    expander.visit_statement_mut(statement);
+    match expander.error {
+        Some(err) => Err(err),
+        None => Ok(()),
+    }
+}
+
+struct ViewExpander<'a> {
+    catalog: &'a dyn Catalog,
+    /// The views currently being expanded, outermost first
+    expanding: Vec<String>,
+    /// The first error, since visitors can't return one
+    error: Option<EasyDbError>,
+}
+
+impl ViewExpander<'_> {
+    /// Returns the query of a view referenced by a FROM item, if it is one
+    fn expand(&self, name: &str) -> EasyDbResult<Option<Statement>> {
+        let view = match self.catalog.read_view(name)? {
+            Some(view) => view,
+            None => return Ok(None),
+        };
+        if let Some(start) = self.expanding.iter().position(|v| v == name) {
+            let mut cycle = self.expanding[start..].to_vec();
+            cycle.push(name.to_string());
+            return Err(EasyDbError::Value(format!(
+                "View {} references itself through {}",
+                name,
+                cycle.join(" -> ")
+            )));
+        }
+        let query = Parser::new(&view.query).parse().map_err(|err| {
+            EasyDbError::Internal(format!("Invalid query for view {}: {}", name, err))
+        })?;
+        Ok(Some(query))
+    }
+}
+
+impl VisitorMut for ViewExpander<'_> {
+    fn visit_from_item_mut(&mut self, item: &mut FromItem) {
+        if self.error.is_some() {
+            return;
+        }
+        let name = match item {
+            FromItem::Table {
+                schema: None,
+                name,
+                alias,
+            } => match self.expand(name) {
+                Ok(Some(query)) => {
+                    let view = name.clone();
+                    *item = FromItem::Subquery {
+                        query: Box::new(query),
+                        alias: Some(alias.take().unwrap_or_else(|| view.clone())),
+                    };
+                    view
+                }
+                Ok(None) => return,
+                Err(err) => {
+                    self.error = Some(err);
+                    return;
+                }
+            },
+            item => return walk_from_item_mut(self, item),
+        };
+        self.expanding.push(name);
+        walk_from_item_mut(self, item);
+        self.expanding.pop();
+    }
+}
@@ -1,9 +1,13 @@
-use super::super::types::DataType;
+use super::super::schema::ReferentialAction;
+use super::super::types::{Collation, DataType, Interval, MAX_DECIMAL_PRECISION};
 use crate::error::{EasyDbError, EasyDbResult};
 
-use super::lexer::{Keyword, Lexer, Token};
+use super::lexer::{
+    describe_position, is_ident_continue, is_ident_start, is_lowercase, Keyword, Lexer,
+    LexerOptions, Token,
+};
 use std::collections::BTreeMap;
-use std::mem::replace;
+use std::fmt::{self, Display};
 
 /// Statements
 #[derive(Clone, Debug, PartialEq)]
@@ -15,52 +19,185 @@ pub enum Statement {
     // },
     // Commit,
     // Rollback,
-    // Explain(Box<Statement>),
-    CreateTable { name: String, columns: Vec<Column> },
+    Explain {
+        statement: Box<Statement>,
+        format: ExplainFormat,
+    },
+    CreateTable {
+        name: String,
+        columns: Vec<Column>,
+        constraints: Vec<Constraint>,
+    },
     DropTable(String),
+    /// CREATE TYPE name AS ENUM ('label', ...)
+    CreateEnum {
+        name: String,
+        labels: Vec<String>,
+    },
+    AlterTable {
+        name: String,
+        action: AlterTableAction,
+    },
+    /// CREATE VIEW name AS query
+    CreateView {
+        name: String,
+        query: Box<Statement>,
+    },
+    DropView(String),
+    /// Removes all rows from a table, keeping its schema
+    Truncate(String),
+    /// Bulk-loads rows into a table from a file
+    Copy {
+        table: String,
+        columns: Option<Vec<String>>,
+        path: String,
+        format: CopyFormat,
+        header: bool,
+    },
+    /// SHOW STATUS, reporting the storage engine's statistics
+    ShowStatus,
     // Delete {
     //     table: String,
     //     r#where: Option<Expression>,
     // },
-    // Insert {
-    //     table: String,
-    //     columns: Option<Vec<String>>,
-    //     values: Vec<Vec<Expression>>,
-    // },
+    Insert {
+        table: String,
+        columns: Option<Vec<String>>,
+        source: InsertSource,
+        on_conflict: Option<OnConflict>,
+    },
     // Update {
     //     table: String,
     //     set: BTreeMap<String, Expression>,
     //     r#where: Option<Expression>,
     // },
+    /// An empty select list means `*`
+    Select {
+        select: Vec<(Expression, Option<String>)>,
+        from: Vec<FromItem>,
+        r#where: Option<Expression>,
+        group_by: Vec<Expression>,
+        having: Option<Expression>,
+        order: Vec<(Expression, Order)>,
+        offset: Option<Expression>,
+        limit: Option<Expression>,
+    },
+    /// A compound query combining the rows of two queries. ORDER BY, LIMIT
+    /// and OFFSET after the last operand apply to the whole result.
+    SetOperation {
+        operator: SetOperator,
+        all: bool,
+        left: Box<Statement>,
+        right: Box<Statement>,
+        order: Vec<(Expression, Order)>,
+        offset: Option<Expression>,
+        limit: Option<Expression>,
+    },
+}
 
-    // Select {
-    //     select: Vec<(Expression, Option<String>)>,
-    //     from: Vec<FromItem>,
-    //     r#where: Option<Expression>,
-    //     group_by: Vec<Expression>,
-    //     having: Option<Expression>,
-    //     order: Vec<(Expression, Order)>,
-    //     offset: Option<Expression>,
-    //     limit: Option<Expression>,
-    // },
+/// Set operators combining queries
+#[derive(Clone, Debug, PartialEq)]
+pub enum SetOperator {
+    Union,
+    Intersect,
+    Except,
+}
+
+impl SetOperator {
+    /// INTERSECT binds tighter than UNION and EXCEPT, as in standard SQL
+    fn prec(&self) -> u8 {
+        match self {
+            Self::Union | Self::Except => 1,
+            Self::Intersect => 2,
+        }
+    }
+}
+
+/// Output formats for EXPLAIN, given as `EXPLAIN (FORMAT name) ...`
+#[derive(Clone, Debug, PartialEq)]
+pub enum ExplainFormat {
+    Text,
+    Json,
+    /// A Graphviz digraph of the plan tree
+    Dot,
+}
+
+/// File formats for COPY, given as `WITH (FORMAT name)`
+#[derive(Clone, Debug, PartialEq)]
+pub enum CopyFormat {
+    Text,
+    Csv,
+}
+
+/// Schema changes made by ALTER TABLE
+#[derive(Clone, Debug, PartialEq)]
+pub enum AlterTableAction {
+    /// RENAME TO new_name
+    RenameTable(String),
+    /// RENAME COLUMN from TO to
+    RenameColumn { from: String, to: String },
+    /// ADD [COLUMN] column_definition
+    AddColumn(Column),
+    /// DROP [COLUMN] name
+    DropColumn(String),
+}
+
+/// The rows inserted by an INSERT statement
+#[derive(Clone, Debug, PartialEq)]
+pub enum InsertSource {
+    Values(Vec<Vec<Expression>>),
+    /// A query whose result rows are inserted, e.g. `INSERT INTO t SELECT ...`
+    Select(Box<Statement>),
+}
+
+/// An ON CONFLICT clause, turning an INSERT into an upsert
+#[derive(Clone, Debug, PartialEq)]
+pub struct OnConflict {
+    /// The columns whose uniqueness conflict is handled, empty for any conflict
+    pub target: Vec<String>,
+    pub action: ConflictAction,
+}
+
+/// What to do with a row that conflicts with an existing one
+#[derive(Clone, Debug, PartialEq)]
+pub enum ConflictAction {
+    Nothing,
+    Update {
+        set: BTreeMap<String, Expression>,
+        r#where: Option<Expression>,
+    },
 }
 
 /// A FROM item
-// #[derive(Clone, Debug, PartialEq)]
-// pub enum FromItem {
-//     Table {
-//         name: String,
-//         alias: Option<String>,
-//     },
-//     Join {
-//         left: Box<FromItem>,
-//         right: Box<FromItem>,
-//         r#type: JoinType,
-//         predicate: Option<Expression>,
-//     },
-// }
+#[derive(Clone, Debug, PartialEq)]
+pub enum FromItem {
+    Table {
+        /// The schema the table is qualified with, e.g. information_schema
+        schema: Option<String>,
+        name: String,
+        alias: Option<String>,
+    },
+    /// A table-valued function producing rows, e.g. `generate_rows(1000, 'spec')`
+    Function {
+        name: String,
+        args: Vec<Expression>,
+        alias: Option<String>,
+    },
+    /// A parenthesized query, e.g. `(SELECT a FROM t) AS s`, also used for
+    /// expanded views
+    Subquery {
+        query: Box<Statement>,
+        alias: Option<String>,
+    },
+    // Join {
+    //     left: Box<FromItem>,
+    //     right: Box<FromItem>,
+    //     r#type: JoinType,
+    //     predicate: Option<Expression>,
+    // },
+}
 
-/// A JOIN type
+// A JOIN type
 // #[derive(Clone, Debug, PartialEq)]
 // pub enum JoinType {
 //     Cross,
@@ -76,21 +213,680 @@ pub struct Column {
     pub datatype: DataType,
     pub primary_key: bool,
     pub nullable: Option<bool>,
-    // TODO: implement expressions
-    // pub default: Option<Expression>,
+    pub default: Option<Expression>,
     pub unique: bool,
     pub index: bool,
+    /// Whether values are generated from the table's sequence, from
+    /// AUTOINCREMENT or a SERIAL type
+    pub autoincrement: bool,
     pub references: Option<String>,
+    /// The ON DELETE action of the REFERENCES constraint, if any
+    pub on_delete: ReferentialAction,
+    /// The collation of a string column, if declared
+    pub collation: Option<Collation>,
+}
+
+/// A table-level constraint, declared alongside the columns of CREATE TABLE
+#[derive(Clone, Debug, PartialEq)]
+pub enum Constraint {
+    PrimaryKey(Vec<String>),
+    Unique(Vec<String>),
+    /// An empty `references` list refers to the primary key of `table`
+    ForeignKey {
+        columns: Vec<String>,
+        table: String,
+        references: Vec<String>,
+        on_delete: ReferentialAction,
+    },
+}
+
+/// Sort orders
+#[derive(Clone, Debug, PartialEq)]
+pub enum Order {
+    Ascending,
+    Descending,
+}
+
+/// Expressions
+#[derive(Clone, Debug, PartialEq)]
+pub enum Expression {
+    /// A column reference, optionally qualified with a table name
+    Field(Option<String>, String),
+    Literal(Literal),
+    Function(String, Vec<Expression>),
+    /// A function evaluated over a window of rows, i.e. func(...) OVER (...)
+    Window(String, Vec<Expression>, WindowSpec),
+    Operation(Operation),
+    /// A scalar subquery, i.e. (SELECT ...)
+    Subquery(Box<Statement>),
+}
+
+/// The window a window function is evaluated over
+#[derive(Clone, Debug, PartialEq)]
+pub struct WindowSpec {
+    pub partition_by: Vec<Expression>,
+    pub order_by: Vec<(Expression, Order)>,
+}
+
+impl From<Literal> for Expression {
+    fn from(literal: Literal) -> Self {
+        Self::Literal(literal)
+    }
+}
+
+impl From<Operation> for Expression {
+    fn from(op: Operation) -> Self {
+        Self::Operation(op)
+    }
+}
+
+/// Literals
+#[derive(Clone, Debug, PartialEq)]
+pub enum Literal {
+    Null,
+    Boolean(bool),
+    Integer(i64),
+    Float(f64),
+    String(String),
+    Bytes(Vec<u8>),
+    Interval(Interval),
+}
+
+/// Operations (done by operators)
+#[derive(Clone, Debug, PartialEq)]
+pub enum Operation {
+    /// Converts a value to the given type, from CAST(expr AS type) or expr::type
+    Cast(Box<Expression>, DataType),
+
+    // Logical operators
+    And(Box<Expression>, Box<Expression>),
+    Not(Box<Expression>),
+    Or(Box<Expression>, Box<Expression>),
+
+    // Comparison operators
+    Equal(Box<Expression>, Box<Expression>),
+    GreaterThan(Box<Expression>, Box<Expression>),
+    GreaterThanOrEqual(Box<Expression>, Box<Expression>),
+    IsNull(Box<Expression>),
+    /// Inequality where NULL is a comparable value: NULL IS DISTINCT FROM
+    /// NULL is false, and NULL IS DISTINCT FROM 1 is true
+    IsDistinctFrom(Box<Expression>, Box<Expression>),
+    LessThan(Box<Expression>, Box<Expression>),
+    LessThanOrEqual(Box<Expression>, Box<Expression>),
+    NotEqual(Box<Expression>, Box<Expression>),
+
+    // Mathematical operators
+    Add(Box<Expression>, Box<Expression>),
+    /// Unary plus, which asserts that its operand is a number
+    Assert(Box<Expression>),
+    Divide(Box<Expression>, Box<Expression>),
+    Exponentiate(Box<Expression>, Box<Expression>),
+    Modulo(Box<Expression>, Box<Expression>),
+    Multiply(Box<Expression>, Box<Expression>),
+    Negate(Box<Expression>),
+    Subtract(Box<Expression>, Box<Expression>),
+
+    // String operators
+    /// expr LIKE pattern [ESCAPE 'c'], where % in the pattern matches any
+    /// sequence of characters and _ any single character. The escape
+    /// character, if any, makes the character after it match literally.
+    Like(Box<Expression>, Box<Expression>, Option<char>),
+    /// Like LIKE, but ignoring case
+    ILike(Box<Expression>, Box<Expression>, Option<char>),
+    /// expr ~ pattern or expr REGEXP pattern: whether the regular expression
+    /// matches anywhere in the string
+    Regex(Box<Expression>, Box<Expression>),
+
+    // JSON operators
+    JsonGet(Box<Expression>, Box<Expression>),
+    JsonGetText(Box<Expression>, Box<Expression>),
+}
+
+/// Formats an identifier, double-quoting it unless it would lex back
+/// unchanged as an unquoted identifier
+struct Ident<'a>(&'a str);
+
+impl Display for Ident<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut chars = self.0.chars();
+        let plain = chars.next().is_some_and(is_ident_start)
+            && chars.all(is_ident_continue)
+            && is_lowercase(self.0)
+            && Keyword::from_str(self.0).is_none();
+        match plain {
+            true => f.write_str(self.0),
+            false => write!(f, "\"{}\"", self.0.replace('"', "\"\"")),
+        }
+    }
+}
+
+/// Writes a comma-separated list of identifiers
+fn write_idents(f: &mut fmt::Formatter, idents: &[String]) -> fmt::Result {
+    write_list(f, &idents.iter().map(|i| Ident(i)).collect::<Vec<_>>())
+}
+
+/// Writes a comma-separated list of items
+fn write_list<T: Display>(f: &mut fmt::Formatter, items: &[T]) -> fmt::Result {
+    for (i, item) in items.iter().enumerate() {
+        if i > 0 {
+            f.write_str(", ")?;
+        }
+        write!(f, "{}", item)?;
+    }
+    Ok(())
+}
+
+/// Writes a comma-separated list of ORDER BY items
+fn write_order(f: &mut fmt::Formatter, order: &[(Expression, Order)]) -> fmt::Result {
+    for (i, (expr, order)) in order.iter().enumerate() {
+        if i > 0 {
+            f.write_str(", ")?;
+        }
+        match order {
+            Order::Ascending => write!(f, "{} ASC", expr)?,
+            Order::Descending => write!(f, "{} DESC", expr)?,
+        }
+    }
+    Ok(())
+}
+
+/// Statements are formatted as SQL which parses back into the same statement
+impl Display for Statement {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Explain { statement, format } => match format {
+                ExplainFormat::Text => write!(f, "EXPLAIN {}", statement),
+                ExplainFormat::Json => write!(f, "EXPLAIN (FORMAT JSON) {}", statement),
+                ExplainFormat::Dot => write!(f, "EXPLAIN (FORMAT DOT) {}", statement),
+            },
+            Self::CreateTable {
+                name,
+                columns,
+                constraints,
+            } => {
+                write!(f, "CREATE TABLE {} (", Ident(name))?;
+                write_list(f, columns)?;
+                if !columns.is_empty() && !constraints.is_empty() {
+                    f.write_str(", ")?;
+                }
+                write_list(f, constraints)?;
+                f.write_str(")")
+            }
+            Self::DropTable(name) => write!(f, "DROP TABLE {}", Ident(name)),
+            Self::CreateEnum { name, labels } => {
+                write!(f, "CREATE TYPE {} AS ENUM (", Ident(name))?;
+                let labels: Vec<_> = labels.iter().map(|l| Literal::String(l.clone())).collect();
+                write_list(f, &labels)?;
+                f.write_str(")")
+            }
+            Self::AlterTable { name, action } => {
+                write!(f, "ALTER TABLE {} ", Ident(name))?;
+                match action {
+                    AlterTableAction::RenameTable(to) => write!(f, "RENAME TO {}", Ident(to)),
+                    AlterTableAction::RenameColumn { from, to } => {
+                        write!(f, "RENAME COLUMN {} TO {}", Ident(from), Ident(to))
+                    }
+                    AlterTableAction::AddColumn(column) => write!(f, "ADD COLUMN {}", column),
+                    AlterTableAction::DropColumn(column) => {
+                        write!(f, "DROP COLUMN {}", Ident(column))
+                    }
+                }
+            }
+            Self::CreateView { name, query } => {
+                write!(f, "CREATE VIEW {} AS {}", Ident(name), query)
+            }
+            Self::DropView(name) => write!(f, "DROP VIEW {}", Ident(name)),
+            Self::Truncate(name) => write!(f, "TRUNCATE TABLE {}", Ident(name)),
+            Self::ShowStatus => write!(f, "SHOW STATUS"),
+            Self::Copy {
+                table,
+                columns,
+                path,
+                format,
+                header,
+            } => {
+                write!(f, "COPY {}", Ident(table))?;
+                if let Some(columns) = columns {
+                    f.write_str(" (")?;
+                    write_idents(f, columns)?;
+                    f.write_str(")")?;
+                }
+                write!(f, " FROM {}", Literal::String(path.clone()))?;
+                f.write_str(match format {
+                    CopyFormat::Text => " WITH (FORMAT TEXT",
+                    CopyFormat::Csv => " WITH (FORMAT CSV",
+                })?;
+                if *header {
+                    f.write_str(", HEADER")?;
+                }
+                f.write_str(")")
+            }
+            Self::Insert {
+                table,
+                columns,
+                source,
+                on_conflict,
+            } => {
+                write!(f, "INSERT INTO {}", Ident(table))?;
+                if let Some(columns) = columns {
+                    f.write_str(" (")?;
+                    write_idents(f, columns)?;
+                    f.write_str(")")?;
+                }
+                match source {
+                    InsertSource::Values(rows) => {
+                        f.write_str(" VALUES ")?;
+                        for (i, row) in rows.iter().enumerate() {
+                            if i > 0 {
+                                f.write_str(", ")?;
+                            }
+                            f.write_str("(")?;
+                            write_list(f, row)?;
+                            f.write_str(")")?;
+                        }
+                    }
+                    InsertSource::Select(query) => write!(f, " {}", query)?,
+                }
+                match on_conflict {
+                    Some(on_conflict) => write!(f, " {}", on_conflict),
+                    None => Ok(()),
+                }
+            }
+            Self::Select {
+                select,
+                from,
+                r#where,
+                group_by,
+                having,
+                order,
+                offset,
+                limit,
+            } => {
+                f.write_str("SELECT ")?;
+                if select.is_empty() {
+                    f.write_str("*")?;
+                }
+                for (i, (expr, alias)) in select.iter().enumerate() {
+                    if i > 0 {
+                        f.write_str(", ")?;
+                    }
+                    write!(f, "{}", expr)?;
+                    if let Some(alias) = alias {
+                        write!(f, " AS {}", Ident(alias))?;
+                    }
+                }
+                if !from.is_empty() {
+                    f.write_str(" FROM ")?;
+                    write_list(f, from)?;
+                }
+                if let Some(expr) = r#where {
+                    write!(f, " WHERE {}", expr)?;
+                }
+                if !group_by.is_empty() {
+                    f.write_str(" GROUP BY ")?;
+                    write_list(f, group_by)?;
+                }
+                if let Some(expr) = having {
+                    write!(f, " HAVING {}", expr)?;
+                }
+                write_query_tail(f, order, offset, limit)
+            }
+            Self::SetOperation {
+                operator,
+                all,
+                left,
+                right,
+                order,
+                offset,
+                limit,
+            } => {
+                // Operands are parenthesized where the parser's precedence and
+                // left associativity would otherwise regroup them, and where
+                // their own ORDER BY, LIMIT or OFFSET would otherwise be read
+                // as applying to the whole compound query.
+                let operand_prec = |statement: &Statement| match statement {
+                    Self::Select {
+                        order,
+                        offset,
+                        limit,
+                        ..
+                    }
+                    | Self::SetOperation {
+                        order,
+                        offset,
+                        limit,
+                        ..
+                    } if !order.is_empty() || offset.is_some() || limit.is_some() => Some(0),
+                    Self::SetOperation { operator, .. } => Some(operator.prec()),
+                    _ => None,
+                };
+                match operand_prec(left) {
+                    Some(prec) if prec < operator.prec() => write!(f, "({})", left)?,
+                    _ => write!(f, "{}", left)?,
+                }
+                f.write_str(match operator {
+                    SetOperator::Union => " UNION ",
+                    SetOperator::Intersect => " INTERSECT ",
+                    SetOperator::Except => " EXCEPT ",
+                })?;
+                if *all {
+                    f.write_str("ALL ")?;
+                }
+                match operand_prec(right) {
+                    Some(prec) if prec <= operator.prec() => write!(f, "({})", right)?,
+                    _ => write!(f, "{}", right)?,
+                }
+                write_query_tail(f, order, offset, limit)
+            }
+        }
+    }
+}
+
+/// Writes the ORDER BY, LIMIT and OFFSET clauses ending a query
+fn write_query_tail(
+    f: &mut fmt::Formatter,
+    order: &[(Expression, Order)],
+    offset: &Option<Expression>,
+    limit: &Option<Expression>,
+) -> fmt::Result {
+    if !order.is_empty() {
+        f.write_str(" ORDER BY ")?;
+        write_order(f, order)?;
+    }
+    if let Some(expr) = limit {
+        write!(f, " LIMIT {}", expr)?;
+    }
+    if let Some(expr) = offset {
+        write!(f, " OFFSET {}", expr)?;
+    }
+    Ok(())
+}
+
+impl Display for OnConflict {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("ON CONFLICT ")?;
+        if !self.target.is_empty() {
+            f.write_str("(")?;
+            write_idents(f, &self.target)?;
+            f.write_str(") ")?;
+        }
+        match &self.action {
+            ConflictAction::Nothing => f.write_str("DO NOTHING"),
+            ConflictAction::Update { set, r#where } => {
+                f.write_str("DO UPDATE SET ")?;
+                for (i, (column, expr)) in set.iter().enumerate() {
+                    if i > 0 {
+                        f.write_str(", ")?;
+                    }
+                    write!(f, "{} = {}", Ident(column), expr)?;
+                }
+                match r#where {
+                    Some(expr) => write!(f, " WHERE {}", expr),
+                    None => Ok(()),
+                }
+            }
+        }
+    }
+}
+
+impl Display for FromItem {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Table {
+                schema,
+                name,
+                alias,
+            } => {
+                if let Some(schema) = schema {
+                    write!(f, "{}.", Ident(schema))?;
+                }
+                write!(f, "{}", Ident(name))?;
+                match alias {
+                    Some(alias) => write!(f, " AS {}", Ident(alias)),
+                    None => Ok(()),
+                }
+            }
+            Self::Function { name, args, alias } => {
+                write!(f, "{}(", Ident(name))?;
+                write_list(f, args)?;
+                f.write_str(")")?;
+                match alias {
+                    Some(alias) => write!(f, " AS {}", Ident(alias)),
+                    None => Ok(()),
+                }
+            }
+            Self::Subquery { query, alias } => {
+                write!(f, "({})", query)?;
+                match alias {
+                    Some(alias) => write!(f, " AS {}", Ident(alias)),
+                    None => Ok(()),
+                }
+            }
+        }
+    }
+}
+
+impl Display for Column {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} {}", Ident(&self.name), self.datatype)?;
+        if self.primary_key {
+            f.write_str(" PRIMARY KEY")?;
+        }
+        match self.nullable {
+            Some(true) => f.write_str(" NULL")?,
+            Some(false) => f.write_str(" NOT NULL")?,
+            None => {}
+        }
+        if let Some(default) = &self.default {
+            write!(f, " DEFAULT {}", default)?;
+        }
+        if self.unique {
+            f.write_str(" UNIQUE")?;
+        }
+        if self.index {
+            f.write_str(" INDEX")?;
+        }
+        if self.autoincrement {
+            f.write_str(" AUTOINCREMENT")?;
+        }
+        if let Some(table) = &self.references {
+            write!(f, " REFERENCES {}", Ident(table))?;
+            if self.on_delete != ReferentialAction::default() {
+                write!(f, " ON DELETE {}", self.on_delete)?;
+            }
+        }
+        if let Some(collation) = &self.collation {
+            write!(f, " COLLATE {}", collation)?;
+        }
+        Ok(())
+    }
+}
+
+impl Display for Constraint {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::PrimaryKey(columns) => {
+                f.write_str("PRIMARY KEY (")?;
+                write_idents(f, columns)?;
+            }
+            Self::Unique(columns) => {
+                f.write_str("UNIQUE (")?;
+                write_idents(f, columns)?;
+            }
+            Self::ForeignKey {
+                columns,
+                table,
+                references,
+                on_delete,
+            } => {
+                f.write_str("FOREIGN KEY (")?;
+                write_idents(f, columns)?;
+                write!(f, ") REFERENCES {}", Ident(table))?;
+                if !references.is_empty() {
+                    f.write_str(" (")?;
+                    write_idents(f, references)?;
+                    f.write_str(")")?;
+                }
+                if *on_delete != ReferentialAction::default() {
+                    write!(f, " ON DELETE {}", on_delete)?;
+                }
+                return Ok(());
+            }
+        }
+        f.write_str(")")
+    }
+}
+
+impl Display for Expression {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Field(None, name) => write!(f, "{}", Ident(name)),
+            Self::Field(Some(table), name) => write!(f, "{}.{}", Ident(table), Ident(name)),
+            Self::Literal(literal) => write!(f, "{}", literal),
+            Self::Function(name, args) => {
+                write!(f, "{}(", Ident(name))?;
+                write_list(f, args)?;
+                f.write_str(")")
+            }
+            Self::Window(name, args, window) => {
+                write!(f, "{}(", Ident(name))?;
+                write_list(f, args)?;
+                write!(f, ") OVER ({})", window)
+            }
+            Self::Operation(operation) => write!(f, "{}", operation),
+            Self::Subquery(query) => write!(f, "({})", query),
+        }
+    }
+}
+
+impl Display for WindowSpec {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if !self.partition_by.is_empty() {
+            f.write_str("PARTITION BY ")?;
+            write_list(f, &self.partition_by)?;
+            if !self.order_by.is_empty() {
+                f.write_str(" ")?;
+            }
+        }
+        if !self.order_by.is_empty() {
+            f.write_str("ORDER BY ")?;
+            write_order(f, &self.order_by)?;
+        }
+        Ok(())
+    }
+}
+
+impl Display for Literal {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Null => f.write_str("NULL"),
+            Self::Boolean(true) => f.write_str("TRUE"),
+            Self::Boolean(false) => f.write_str("FALSE"),
+            Self::Integer(i) => write!(f, "{}", i),
+            // Debug formatting keeps the decimal point, so the value lexes
+            // back as a float rather than an integer.
+            Self::Float(n) => write!(f, "{:?}", n),
+            Self::String(s) => write!(f, "'{}'", s.replace('\'', "''")),
+            Self::Interval(interval) => write!(f, "INTERVAL '{}'", interval),
+            Self::Bytes(bytes) => {
+                f.write_str("x'")?;
+                bytes.iter().try_for_each(|b| write!(f, "{:02X}", b))?;
+                f.write_str("'")
+            }
+        }
+    }
+}
+
+/// Wraps operands that are themselves operations in parentheses, so the
+/// output never depends on operator precedence.
+struct Operand<'a>(&'a Expression);
+
+impl Display for Operand<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.0 {
+            Expression::Operation(_) => write!(f, "({})", self.0),
+            expr => write!(f, "{}", expr),
+        }
+    }
+}
+
+impl Operation {
+    /// Formats a [NOT] [I]LIKE operation
+    fn fmt_like(&self, f: &mut fmt::Formatter, not: bool) -> fmt::Result {
+        let (lhs, op, rhs, escape) = match self {
+            Self::Like(lhs, rhs, escape) => (lhs, "LIKE", rhs, escape),
+            Self::ILike(lhs, rhs, escape) => (lhs, "ILIKE", rhs, escape),
+            _ => return write!(f, "{}", self),
+        };
+        let not = if not { "NOT " } else { "" };
+        write!(f, "{} {}{} {}", Operand(lhs), not, op, Operand(rhs))?;
+        if let Some(escape) = escape {
+            write!(f, " ESCAPE {}", Literal::String(escape.to_string()))?;
+        }
+        Ok(())
+    }
+}
+
+impl Display for Operation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let (lhs, op, rhs) = match self {
+            Self::Cast(expr, datatype) => return write!(f, "CAST({} AS {})", expr, datatype),
+            Self::Not(expr) => match expr.as_ref() {
+                Expression::Operation(like @ (Self::Like(..) | Self::ILike(..))) => {
+                    return like.fmt_like(f, true)
+                }
+                expr => return write!(f, "NOT {}", Operand(expr)),
+            },
+            Self::Assert(expr) => return write!(f, "+{}", Operand(expr)),
+            // A minus sign directly before a number would lex as a signed literal
+            Self::Negate(expr) => match expr.as_ref() {
+                Expression::Literal(Literal::Integer(_))
+                | Expression::Literal(Literal::Float(_)) => return write!(f, "-({})", expr),
+                expr => return write!(f, "-{}", Operand(expr)),
+            },
+            Self::IsNull(expr) => return write!(f, "{} IS NULL", Operand(expr)),
+            Self::Like(..) | Self::ILike(..) => return self.fmt_like(f, false),
+            Self::And(lhs, rhs) => (lhs, "AND", rhs),
+            Self::Or(lhs, rhs) => (lhs, "OR", rhs),
+            Self::Equal(lhs, rhs) => (lhs, "=", rhs),
+            Self::GreaterThan(lhs, rhs) => (lhs, ">", rhs),
+            Self::GreaterThanOrEqual(lhs, rhs) => (lhs, ">=", rhs),
+            Self::LessThan(lhs, rhs) => (lhs, "<", rhs),
+            Self::LessThanOrEqual(lhs, rhs) => (lhs, "<=", rhs),
+            Self::NotEqual(lhs, rhs) => (lhs, "!=", rhs),
+            Self::Add(lhs, rhs) => (lhs, "+", rhs),
+            Self::Divide(lhs, rhs) => (lhs, "/", rhs),
+            Self::Exponentiate(lhs, rhs) => (lhs, "^", rhs),
+            Self::Modulo(lhs, rhs) => (lhs, "%", rhs),
+            Self::Multiply(lhs, rhs) => (lhs, "*", rhs),
+            Self::Subtract(lhs, rhs) => (lhs, "-", rhs),
+            Self::Regex(lhs, rhs) => (lhs, "~", rhs),
+            Self::IsDistinctFrom(lhs, rhs) => (lhs, "IS DISTINCT FROM", rhs),
+            Self::JsonGet(lhs, rhs) => (lhs, "->", rhs),
+            Self::JsonGetText(lhs, rhs) => (lhs, "->>", rhs),
+        };
+        write!(f, "{} {} {}", Operand(lhs), op, Operand(rhs))
+    }
 }
 
 pub struct Parser<'a> {
+    input: &'a str,
     lexer: std::iter::Peekable<Lexer<'a>>,
+    /// Byte offset of the last token handed out by next() or peek(), which
+    /// parse errors point at
+    pos: usize,
 }
 
 impl<'a> Parser<'a> {
-    pub fn new(query: &str) -> Parser {
+    pub fn new(query: &'a str) -> Parser<'a> {
+        Self::with_options(query, LexerOptions::default())
+    }
+
+    pub fn with_options(query: &'a str, options: LexerOptions) -> Parser<'a> {
         Parser {
-            lexer: Lexer::new(query).peekable(),
+            input: query,
+            lexer: Lexer::with_options(query, options).peekable(),
+            pos: 0,
         }
     }
 
@@ -101,58 +897,164 @@ impl<'a> Parser<'a> {
         Ok(statement)
     }
 
+    /// Parses a script of semicolon-separated statements, stopping at the
+    /// first error
+    pub fn parse_all(&mut self) -> EasyDbResult<Vec<Statement>> {
+        let mut statements = Vec::new();
+        loop {
+            while self.next_if_token(Token::Semicolon).is_some() {}
+            if self.peek()?.is_none() {
+                break;
+            }
+            statements.push(self.parse_statement()?);
+            if self.next_if_token(Token::Semicolon).is_none() {
+                self.next_expect(None)?;
+            }
+        }
+        Ok(statements)
+    }
+
+    /// Parses a script of semicolon-separated statements like parse_all(),
+    /// but recovers from errors by skipping to the next semicolon, so that
+    /// every error in the script is reported rather than just the first.
+    pub fn parse_all_with_recovery(&mut self) -> (Vec<Statement>, Vec<EasyDbError>) {
+        let mut statements = Vec::new();
+        let mut errors = Vec::new();
+        loop {
+            while self.next_if_token(Token::Semicolon).is_some() {}
+            let statement = match self.peek() {
+                Ok(None) => break,
+                Ok(Some(_)) => self.parse_statement().and_then(|statement| {
+                    if self.next_if_token(Token::Semicolon).is_none() {
+                        self.next_expect(None)?;
+                    }
+                    Ok(statement)
+                }),
+                Err(err) => Err(err),
+            };
+            match statement {
+                Ok(statement) => statements.push(statement),
+                Err(err) => {
+                    errors.push(err);
+                    self.synchronize();
+                }
+            }
+        }
+        (statements, errors)
+    }
+
+    /// Skips past the next semicolon, to resume parsing after an error
+    fn synchronize(&mut self) {
+        if matches!(self.lexer.peek(), Some(Err(_))) {
+            // A lexer error stays peeked without moving pos, so it must be
+            // consumed here, or it would be reported again forever.
+            self.lexer.next();
+        } else {
+            // The token the error occurred at may itself have been a
+            // consumed semicolon, in which case the next statement starts
+            // right away.
+            let peeked = matches!(self.lexer.peek(), Some(Ok((_, span))) if span.start == self.pos);
+            if !peeked && self.input[self.pos..].starts_with(';') {
+                return;
+            }
+        }
+        for token in self.lexer.by_ref() {
+            if let Ok((Token::Semicolon, _)) = token {
+                break;
+            }
+        }
+    }
+
+    /// Builds a parse error pointing at the current token
+    fn error(&self, message: String) -> EasyDbError {
+        EasyDbError::Parse(format!(
+            "{} at {}",
+            message,
+            describe_position(self.input, self.pos)
+        ))
+    }
+
     /// Get the next lexer token, or throws an error if none is found.
-    fn next(&mut self) -> EasyDbResult<Token> {
-        self.lexer
-            .next()
-            .unwrap_or_else(|| Err(EasyDbError::Parse("Unexpected end of input".into())))
+    fn next(&mut self) -> EasyDbResult<Token<'a>> {
+        match self.lexer.next() {
+            Some(Ok((token, span))) => {
+                self.pos = span.start;
+                Ok(token)
+            }
+            Some(Err(err)) => Err(err),
+            None => {
+                self.pos = self.input.len();
+                Err(self.error("Unexpected end of input".into()))
+            }
+        }
     }
 
     /// Grabs the next lexer token if it satisfies the predicate function
-    fn next_if<F: Fn(&Token) -> bool>(&mut self, predicate: F) -> Option<Token> {
+    fn next_if<F: Fn(&Token) -> bool>(&mut self, predicate: F) -> Option<Token<'a>> {
         self.peek().unwrap_or(None).filter(|t| predicate(t))?;
         self.next().ok()
     }
 
     /// Grabs the next lexer token if it is a given token
-    fn next_if_token(&mut self, token: Token) -> Option<Token> {
+    fn next_if_token(&mut self, token: Token<'a>) -> Option<Token<'a>> {
         self.next_if(|t| t == &token)
     }
 
     /// Grabs the next lexer token if it is a keyword
-    fn next_if_keyword(&mut self) -> Option<Token> {
+    fn next_if_keyword(&mut self) -> Option<Token<'a>> {
         self.next_if(|t| matches!(t, Token::Keyword(_)))
     }
 
     /// Grabs the next lexer token, and returns it if it was expected or
     /// otherwise throws an error.
-    fn next_expect(&mut self, expect: Option<Token>) -> EasyDbResult<Option<Token>> {
+    fn next_expect(&mut self, expect: Option<Token<'a>>) -> EasyDbResult<Option<Token<'a>>> {
         if let Some(t) = expect {
             let token = self.next()?;
             if token == t {
                 Ok(Some(token))
             } else {
-                Err(EasyDbError::Parse(format!(
-                    "Expected token {}, found {}",
-                    t, token
-                )))
+                Err(self.error(format!("Expected token {}, found {}", t, token)))
             }
         } else if let Some(token) = self.peek()? {
-            Err(EasyDbError::Parse(format!("Unexpected token {}", token)))
+            Err(self.error(format!("Unexpected token {}", token)))
         } else {
             Ok(None)
         }
     }
 
-    fn peek(&mut self) -> EasyDbResult<Option<Token>> {
-        self.lexer.peek().cloned().transpose()
+    /// Peeks at the token after the next one, without moving pos
+    fn peek_second(&mut self) -> EasyDbResult<Option<Token<'a>>> {
+        let mut lexer = self.lexer.clone();
+        lexer.next();
+        Ok(lexer.next().transpose()?.map(|(token, _)| token))
+    }
+
+    fn peek(&mut self) -> EasyDbResult<Option<Token<'a>>> {
+        match self.lexer.peek().cloned().transpose()? {
+            Some((token, span)) => {
+                self.pos = span.start;
+                Ok(Some(token))
+            }
+            None => {
+                self.pos = self.input.len();
+                Ok(None)
+            }
+        }
     }
 
     fn parse_statement(&mut self) -> EasyDbResult<Statement> {
         match self.peek()? {
+            Some(Token::Keyword(Keyword::Explain)) => self.parse_statement_explain(),
             Some(Token::Keyword(Keyword::Create)) => self.parse_ddl(),
-            Some(token) => Err(EasyDbError::Parse(format!("Unexpected token {}", token))),
-            None => Err(EasyDbError::Parse("Unexpected end of input".into())),
+            Some(Token::Keyword(Keyword::Drop)) => self.parse_ddl(),
+            Some(Token::Keyword(Keyword::Alter)) => self.parse_ddl(),
+            Some(Token::Keyword(Keyword::Select)) | Some(Token::OpenParen) => self.parse_query(),
+            Some(Token::Keyword(Keyword::Insert)) => self.parse_statement_insert(),
+            Some(Token::Keyword(Keyword::Truncate)) => self.parse_statement_truncate(),
+            Some(Token::Keyword(Keyword::Copy)) => self.parse_statement_copy(),
+            Some(Token::Ident(word)) if word == "show" => self.parse_statement_show(),
+            Some(token) => Err(self.error(format!("Unexpected token {}", token))),
+            None => Err(self.error("Unexpected end of input".into())),
         }
     }
 
@@ -160,27 +1062,47 @@ impl<'a> Parser<'a> {
         match self.next()? {
             Token::Keyword(Keyword::Create) => match self.next()? {
                 Token::Keyword(Keyword::Table) => self.parse_ddl_create_table(),
-                token => Err(EasyDbError::Parse(format!("Unexpected token {}", token))),
+                Token::Ident(word) if word == "type" => self.parse_ddl_create_enum(),
+                Token::Ident(word) if word == "view" => {
+                    let name = self.next_ident()?;
+                    self.next_expect(Some(Keyword::As.into()))?;
+                    let query = Box::new(self.parse_query()?);
+                    Ok(Statement::CreateView { name, query })
+                }
+                token => Err(self.error(format!("Unexpected token {}", token))),
             },
             Token::Keyword(Keyword::Drop) => match self.next()? {
                 Token::Keyword(Keyword::Table) => self.parse_ddl_drop_table(),
-                token => Err(EasyDbError::Parse(format!("Unexpected token {}", token))),
+                Token::Ident(word) if word == "view" => Ok(Statement::DropView(self.next_ident()?)),
+                token => Err(self.error(format!("Unexpected token {}", token))),
             },
-            token => Err(EasyDbError::Parse(format!("Unexpected token {}", token))),
+            Token::Keyword(Keyword::Alter) => match self.next()? {
+                Token::Keyword(Keyword::Table) => self.parse_ddl_alter_table(),
+                token => Err(self.error(format!("Unexpected token {}", token))),
+            },
+            token => Err(self.error(format!("Unexpected token {}", token))),
         }
     }
 
     /// Grabs the next identifier, or errors if not found
     fn next_ident(&mut self) -> EasyDbResult<String> {
         match self.next()? {
-            Token::Ident(ident) => Ok(ident),
-            token => Err(EasyDbError::Parse(format!(
-                "Expected identifier, got {}",
-                token
-            ))),
+            Token::Ident(ident) => Ok(ident.into_owned()),
+            Token::Keyword(keyword) => Err(self.reserved_keyword_error(keyword)),
+            token => Err(self.error(format!("Expected identifier, got {}", token))),
         }
     }
 
+    /// Builds the error for a reserved keyword used where an identifier was
+    /// expected
+    fn reserved_keyword_error(&self, keyword: Keyword) -> EasyDbError {
+        self.error(format!(
+            "{} is a reserved keyword, use double quotes to use it as an identifier: \"{}\"",
+            keyword,
+            keyword.to_str().to_lowercase()
+        ))
+    }
+
     /// Parses a CREATE TABLE DDL statement. The CREATE TABLE prefix has
     /// already been consumed.
     fn parse_ddl_create_table(&mut self) -> EasyDbResult<Statement> {
@@ -188,50 +1110,170 @@ impl<'a> Parser<'a> {
         self.next_expect(Some(Token::OpenParen))?;
 
         let mut columns = Vec::new();
+        let mut constraints = Vec::new();
 
         loop {
-            columns.push(self.parse_ddl_column()?);
+            match self.peek()? {
+                Some(Token::Keyword(Keyword::Primary))
+                | Some(Token::Keyword(Keyword::Unique))
+                | Some(Token::Keyword(Keyword::Foreign)) => {
+                    constraints.push(self.parse_ddl_constraint()?)
+                }
+                _ => columns.push(self.parse_ddl_column()?),
+            }
             if self.next_if_token(Token::Comma).is_none() {
                 break;
             }
         }
 
         self.next_expect(Some(Token::CloseParen))?;
-        Ok(Statement::CreateTable { name, columns })
+        Ok(Statement::CreateTable {
+            name,
+            columns,
+            constraints,
+        })
     }
 
-    fn parse_ddl_column(&mut self) -> EasyDbResult<Column> {
-        let mut column = Column {
-            name: self.next_ident()?,
-            datatype: match self.next()? {
-                Token::Keyword(Keyword::Bool) => DataType::Boolean,
-                Token::Keyword(Keyword::Boolean) => DataType::Boolean,
-                Token::Keyword(Keyword::Char) => DataType::String,
-                Token::Keyword(Keyword::Double) => DataType::Float,
-                Token::Keyword(Keyword::Float) => DataType::Float,
-                Token::Keyword(Keyword::Int) => DataType::Integer,
-                Token::Keyword(Keyword::Integer) => DataType::Integer,
-                Token::Keyword(Keyword::String) => DataType::String,
-                Token::Keyword(Keyword::Text) => DataType::String,
-                Token::Keyword(Keyword::Varchar) => DataType::String,
-                token => return Err(EasyDbError::Parse(format!("Unexpected token {}", token))),
-            },
-            primary_key: false,
-            nullable: None,
-            // default: None,
-            unique: false,
-            index: false,
-            references: None,
-        };
-
-        while let Some(Token::Keyword(keyword)) = self.next_if_keyword() {
-            match keyword {
-                Keyword::Primary => {
-                    self.next_expect(Some(Keyword::Key.into()))?;
-                    column.primary_key = true;
+    /// Parses a CREATE TYPE ... AS ENUM DDL statement. The CREATE TYPE
+    /// prefix has already been consumed.
+    fn parse_ddl_create_enum(&mut self) -> EasyDbResult<Statement> {
+        let name = self.next_ident()?;
+        self.next_expect(Some(Keyword::As.into()))?;
+        match self.next_ident()?.as_str() {
+            "enum" => {}
+            word => return Err(self.error(format!("Expected ENUM, found {}", word))),
+        }
+        self.next_expect(Some(Token::OpenParen))?;
+        let mut labels: Vec<String> = Vec::new();
+        loop {
+            match self.next()? {
+                Token::String(label) if labels.iter().any(|l| *l == label) => {
+                    return Err(self.error(format!("Duplicate enum label '{}'", label)))
                 }
-                Keyword::Null => {
-                    if let Some(false) = column.nullable {
+                Token::String(label) => labels.push(label.into_owned()),
+                token => return Err(self.error(format!("Expected enum label, found {}", token))),
+            }
+            if self.next_if_token(Token::Comma).is_none() {
+                break;
+            }
+        }
+        self.next_expect(Some(Token::CloseParen))?;
+        Ok(Statement::CreateEnum { name, labels })
+    }
+
+    /// Parses a table-level constraint in a CREATE TABLE column list
+    fn parse_ddl_constraint(&mut self) -> EasyDbResult<Constraint> {
+        match self.next()? {
+            Token::Keyword(Keyword::Primary) => {
+                self.next_expect(Some(Keyword::Key.into()))?;
+                Ok(Constraint::PrimaryKey(self.parse_ident_list()?))
+            }
+            Token::Keyword(Keyword::Unique) => Ok(Constraint::Unique(self.parse_ident_list()?)),
+            Token::Keyword(Keyword::Foreign) => {
+                self.next_expect(Some(Keyword::Key.into()))?;
+                let columns = self.parse_ident_list()?;
+                self.next_expect(Some(Keyword::References.into()))?;
+                let table = self.next_ident()?;
+                let references = match self.peek()? {
+                    Some(Token::OpenParen) => self.parse_ident_list()?,
+                    _ => Vec::new(),
+                };
+                if !references.is_empty() && references.len() != columns.len() {
+                    return Err(self.error(format!(
+                        "Foreign key has {} columns but references {}",
+                        columns.len(),
+                        references.len()
+                    )));
+                }
+                Ok(Constraint::ForeignKey {
+                    columns,
+                    table,
+                    references,
+                    on_delete: self.parse_on_delete()?,
+                })
+            }
+            token => Err(self.error(format!("Unexpected token {}", token))),
+        }
+    }
+
+    /// Parses the optional ON DELETE clause of a foreign key
+    fn parse_on_delete(&mut self) -> EasyDbResult<ReferentialAction> {
+        if self.next_if_token(Keyword::On.into()).is_none() {
+            return Ok(ReferentialAction::default());
+        }
+        self.next_expect(Some(Keyword::Delete.into()))?;
+        Ok(match self.next()? {
+            Token::Keyword(Keyword::Set) => {
+                self.next_expect(Some(Keyword::Null.into()))?;
+                ReferentialAction::SetNull
+            }
+            Token::Ident(word) if word == "cascade" => ReferentialAction::Cascade,
+            Token::Ident(word) if word == "restrict" => ReferentialAction::Restrict,
+            Token::Ident(word) if word == "no" => match self.next()? {
+                Token::Ident(word) if word == "action" => ReferentialAction::Restrict,
+                token => return Err(self.error(format!("Expected ACTION, found {}", token))),
+            },
+            token => {
+                return Err(self.error(format!(
+                    "Expected CASCADE, RESTRICT, NO ACTION or SET NULL, found {}",
+                    token
+                )))
+            }
+        })
+    }
+
+    /// Parses a parenthesized, comma-separated list of identifiers
+    fn parse_ident_list(&mut self) -> EasyDbResult<Vec<String>> {
+        self.next_expect(Some(Token::OpenParen))?;
+        let mut idents = Vec::new();
+        loop {
+            idents.push(self.next_ident()?);
+            if self.next_if_token(Token::Comma).is_none() {
+                break;
+            }
+        }
+        self.next_expect(Some(Token::CloseParen))?;
+        Ok(idents)
+    }
+
+    fn parse_ddl_column(&mut self) -> EasyDbResult<Column> {
+        let name = self.next_ident()?;
+        // SERIAL types are shorthands for AUTOINCREMENT integer columns
+        let serial = match self.peek()? {
+            Some(Token::Ident(name)) if name == "smallserial" => Some(DataType::SmallInt),
+            Some(Token::Ident(name)) if name == "serial" => Some(DataType::Int),
+            Some(Token::Ident(name)) if name == "bigserial" => Some(DataType::BigInt),
+            _ => None,
+        };
+        let (datatype, autoincrement) = match serial {
+            Some(datatype) => {
+                self.next()?;
+                (datatype, true)
+            }
+            None => (self.parse_datatype()?, false),
+        };
+        let mut column = Column {
+            name,
+            datatype,
+            primary_key: false,
+            nullable: None,
+            default: None,
+            unique: false,
+            index: false,
+            autoincrement,
+            references: None,
+            on_delete: ReferentialAction::default(),
+            collation: None,
+        };
+
+        while let Some(Token::Keyword(keyword)) = self.next_if_keyword() {
+            match keyword {
+                Keyword::Primary => {
+                    self.next_expect(Some(Keyword::Key.into()))?;
+                    column.primary_key = true;
+                }
+                Keyword::Null => {
+                    if let Some(false) = column.nullable {
                         return Err(EasyDbError::Value(format!(
                             "Column {} can't be both not nullable and nullable",
                             column.name
@@ -239,10 +1281,38 @@ impl<'a> Parser<'a> {
                     }
                     column.nullable = Some(true)
                 }
-                // Keyword::Default => column.default = Some(self.parse_expression(0)?),
+                Keyword::Default => column.default = Some(self.parse_expression(0)?),
                 Keyword::Unique => column.unique = true,
                 Keyword::Index => column.index = true,
-                Keyword::References => column.references = Some(self.next_ident()?),
+                Keyword::Autoincrement => {
+                    if !matches!(
+                        column.datatype,
+                        DataType::SmallInt | DataType::Int | DataType::BigInt
+                    ) {
+                        return Err(self.error(format!(
+                            "Column {} of type {} can't be AUTOINCREMENT",
+                            column.name, column.datatype
+                        )));
+                    }
+                    column.autoincrement = true
+                }
+                Keyword::References => {
+                    column.references = Some(self.next_ident()?);
+                    column.on_delete = self.parse_on_delete()?;
+                }
+                Keyword::Collate => {
+                    if !matches!(column.datatype, DataType::String(_)) {
+                        return Err(self.error(format!(
+                            "Column {} of type {} can't have a collation",
+                            column.name, column.datatype
+                        )));
+                    }
+                    let name = self.next_ident()?;
+                    column.collation = Some(
+                        Collation::from_name(&name)
+                            .ok_or_else(|| self.error(format!("Unknown collation {}", name)))?,
+                    );
+                }
                 Keyword::Not => {
                     self.next_expect(Some(Keyword::Null.into()))?;
                     if let Some(true) = column.nullable {
@@ -253,21 +1323,1386 @@ impl<'a> Parser<'a> {
                     }
                     column.nullable = Some(false)
                 }
-                keyword => {
-                    return Err(EasyDbError::Parse(format!(
-                        "Unexpected keyword {}",
-                        keyword
-                    )))
-                }
+                keyword => return Err(self.error(format!("Unexpected keyword {}", keyword))),
             }
         }
 
         Ok(column)
     }
 
+    /// Parses a data type name
+    fn parse_datatype(&mut self) -> EasyDbResult<DataType> {
+        Ok(match self.next()? {
+            Token::Keyword(Keyword::Bool) => DataType::Boolean,
+            Token::Keyword(Keyword::Boolean) => DataType::Boolean,
+            Token::Keyword(Keyword::Double) => DataType::Float,
+            Token::Keyword(Keyword::Float) => DataType::Float,
+            Token::Ident(name) if name == "json" => DataType::Json,
+            Token::Ident(name) => DataType::Enum(name.into_owned()),
+            Token::Keyword(Keyword::Interval) => DataType::Interval,
+            Token::Keyword(Keyword::Blob) => DataType::Bytes,
+            Token::Keyword(Keyword::Bytea) => DataType::Bytes,
+            Token::Keyword(Keyword::Smallint) => DataType::SmallInt,
+            Token::Keyword(Keyword::Int) => DataType::Int,
+            Token::Keyword(Keyword::Integer) => DataType::Int,
+            Token::Keyword(Keyword::Bigint) => DataType::BigInt,
+            Token::Keyword(Keyword::Char)
+            | Token::Keyword(Keyword::String)
+            | Token::Keyword(Keyword::Text)
+            | Token::Keyword(Keyword::Varchar) => {
+                let length = match self.parse_datatype_args()?.as_slice() {
+                    [] => None,
+                    [0] => return Err(self.error("String length must be positive".into())),
+                    [length] => Some(*length),
+                    _ => return Err(self.error("String types take a single length".into())),
+                };
+                DataType::String(length)
+            }
+            Token::Keyword(Keyword::Decimal) | Token::Keyword(Keyword::Numeric) => {
+                let (precision, scale) = match self.parse_datatype_args()?.as_slice() {
+                    [] => (MAX_DECIMAL_PRECISION, 0),
+                    [precision] => (*precision, 0),
+                    [precision, scale] => (*precision, *scale),
+                    _ => return Err(self.error("DECIMAL takes a precision and scale".into())),
+                };
+                if precision == 0 || precision > MAX_DECIMAL_PRECISION {
+                    return Err(self.error(format!(
+                        "DECIMAL precision must be between 1 and {}",
+                        MAX_DECIMAL_PRECISION
+                    )));
+                }
+                if scale > precision {
+                    return Err(self.error(format!(
+                        "DECIMAL scale {} exceeds precision {}",
+                        scale, precision
+                    )));
+                }
+                DataType::Decimal(precision, scale)
+            }
+            token => return Err(self.error(format!("Unexpected token {}", token))),
+        })
+    }
+
+    /// Parses an optional parenthesized list of datatype arguments, such as
+    /// the length in VARCHAR(255) or the precision and scale in DECIMAL(10, 2)
+    fn parse_datatype_args(&mut self) -> EasyDbResult<Vec<u32>> {
+        let mut args = Vec::new();
+        if self.next_if_token(Token::OpenParen).is_none() {
+            return Ok(args);
+        }
+        loop {
+            match self.next()? {
+                Token::Number(n) => args.push(
+                    n.parse()
+                        .map_err(|_| self.error(format!("Invalid datatype argument {}", n)))?,
+                ),
+                token => return Err(self.error(format!("Unexpected token {}", token))),
+            }
+            if self.next_if_token(Token::Comma).is_none() {
+                break;
+            }
+        }
+        self.next_expect(Some(Token::CloseParen))?;
+        Ok(args)
+    }
+
     /// Parses a DROP TABLE DDL statement. The DROP TABLE prefix has
     /// already been consumed.
     fn parse_ddl_drop_table(&mut self) -> EasyDbResult<Statement> {
         Ok(Statement::DropTable(self.next_ident()?))
     }
+
+    /// Parses an ALTER TABLE DDL statement. The ALTER TABLE prefix has
+    /// already been consumed.
+    fn parse_ddl_alter_table(&mut self) -> EasyDbResult<Statement> {
+        let name = self.next_ident()?;
+        let action = match self.next()? {
+            Token::Keyword(Keyword::Rename) => match self.next()? {
+                Token::Keyword(Keyword::To) => AlterTableAction::RenameTable(self.next_ident()?),
+                Token::Keyword(Keyword::Column) => {
+                    let from = self.next_ident()?;
+                    self.next_expect(Some(Keyword::To.into()))?;
+                    AlterTableAction::RenameColumn {
+                        from,
+                        to: self.next_ident()?,
+                    }
+                }
+                token => return Err(self.error(format!("Unexpected token {}", token))),
+            },
+            Token::Keyword(Keyword::Add) => {
+                self.next_if_token(Keyword::Column.into());
+                AlterTableAction::AddColumn(self.parse_ddl_column()?)
+            }
+            Token::Keyword(Keyword::Drop) => {
+                self.next_if_token(Keyword::Column.into());
+                AlterTableAction::DropColumn(self.next_ident()?)
+            }
+            token => return Err(self.error(format!("Unexpected token {}", token))),
+        };
+        Ok(Statement::AlterTable { name, action })
+    }
+
+    /// Parses a query: a SELECT statement, possibly combined with further
+    /// SELECTs through set operators, followed by ORDER BY, LIMIT and OFFSET
+    /// clauses which apply to the whole query
+    fn parse_query(&mut self) -> EasyDbResult<Statement> {
+        let mut query = self.parse_query_compound(0)?;
+        self.parse_clause_query_tail(&mut query)?;
+        Ok(query)
+    }
+
+    /// Parses a SELECT statement, possibly combined with further SELECTs
+    /// through set operators of at least the given precedence, without the
+    /// clauses ending the query
+    fn parse_query_compound(&mut self, min_prec: u8) -> EasyDbResult<Statement> {
+        let mut lhs = self.parse_query_operand()?;
+        loop {
+            let operator = match self.peek()? {
+                Some(Token::Keyword(Keyword::Union)) => SetOperator::Union,
+                Some(Token::Keyword(Keyword::Intersect)) => SetOperator::Intersect,
+                Some(Token::Keyword(Keyword::Except)) => SetOperator::Except,
+                _ => break,
+            };
+            if operator.prec() < min_prec {
+                break;
+            }
+            self.next()?;
+            let all = self.next_if_token(Keyword::All.into()).is_some();
+            let rhs = self.parse_query_compound(operator.prec() + 1)?;
+            lhs = Statement::SetOperation {
+                operator,
+                all,
+                left: Box::new(lhs),
+                right: Box::new(rhs),
+                order: Vec::new(),
+                offset: None,
+                limit: None,
+            };
+        }
+        Ok(lhs)
+    }
+
+    /// Parses an operand of a set operation: a SELECT without ORDER BY,
+    /// LIMIT or OFFSET, or a parenthesized query
+    fn parse_query_operand(&mut self) -> EasyDbResult<Statement> {
+        if self.next_if_token(Token::OpenParen).is_some() {
+            let query = self.parse_query()?;
+            self.next_expect(Some(Token::CloseParen))?;
+            Ok(query)
+        } else {
+            self.parse_statement_select()
+        }
+    }
+
+    /// Parses a SELECT statement, up to but not including the clauses
+    /// ending the query, which parse_query() handles
+    fn parse_statement_select(&mut self) -> EasyDbResult<Statement> {
+        Ok(Statement::Select {
+            select: self.parse_clause_select()?,
+            from: self.parse_clause_from()?,
+            r#where: self.parse_clause_where()?,
+            group_by: self.parse_clause_group_by()?,
+            having: self.parse_clause_having()?,
+            order: Vec::new(),
+            offset: None,
+            limit: None,
+        })
+    }
+
+    /// Parses the ORDER BY, LIMIT and OFFSET clauses ending a query, each of
+    /// which may be missing, into the query. A parenthesized query may
+    /// already have some of them, which can't be given twice.
+    fn parse_clause_query_tail(&mut self, query: &mut Statement) -> EasyDbResult<()> {
+        let (Statement::Select {
+            order,
+            offset,
+            limit,
+            ..
+        }
+        | Statement::SetOperation {
+            order,
+            offset,
+            limit,
+            ..
+        }) = query
+        else {
+            return Err(EasyDbError::Internal(format!("Unexpected query {}", query)));
+        };
+        let duplicate = |parser: &Self| {
+            parser.error("A query can't have more than one ORDER BY, LIMIT or OFFSET clause".into())
+        };
+        if matches!(self.peek()?, Some(Token::Keyword(Keyword::Order))) && !order.is_empty() {
+            return Err(duplicate(self));
+        }
+        order.extend(self.parse_clause_order()?);
+        if self.next_if_token(Keyword::Limit.into()).is_some() {
+            if limit.is_some() {
+                return Err(duplicate(self));
+            }
+            *limit = Some(self.parse_expression(0)?);
+        }
+        if self.next_if_token(Keyword::Offset.into()).is_some() {
+            if offset.is_some() {
+                return Err(duplicate(self));
+            }
+            *offset = Some(self.parse_expression(0)?);
+        }
+        Ok(())
+    }
+
+    /// Parses an INSERT statement, whose rows come either from a VALUES
+    /// list or from a SELECT query
+    fn parse_statement_insert(&mut self) -> EasyDbResult<Statement> {
+        self.next_expect(Some(Keyword::Insert.into()))?;
+        self.next_expect(Some(Keyword::Into.into()))?;
+        let table = self.next_ident()?;
+
+        // A parenthesis starts either the column list or a parenthesized
+        // query, as in INSERT INTO t (SELECT ...)
+        let columns = match (self.peek()?, self.peek_second()?) {
+            (Some(Token::OpenParen), Some(Token::Keyword(Keyword::Select))) => None,
+            (Some(Token::OpenParen), _) => Some(self.parse_ident_list()?),
+            _ => None,
+        };
+
+        let source = match self.peek()? {
+            Some(Token::Keyword(Keyword::Select)) | Some(Token::OpenParen) => {
+                InsertSource::Select(Box::new(self.parse_query()?))
+            }
+            _ => InsertSource::Values(self.parse_clause_values()?),
+        };
+
+        let on_conflict = match self.next_if_token(Keyword::On.into()) {
+            Some(_) => Some(self.parse_clause_on_conflict()?),
+            None => None,
+        };
+
+        Ok(Statement::Insert {
+            table,
+            columns,
+            source,
+            on_conflict,
+        })
+    }
+
+    /// Parses an ON CONFLICT clause. The ON keyword has already been consumed.
+    fn parse_clause_on_conflict(&mut self) -> EasyDbResult<OnConflict> {
+        self.next_expect(Some(Keyword::Conflict.into()))?;
+        let target = match self.peek()? {
+            Some(Token::OpenParen) => self.parse_ident_list()?,
+            _ => Vec::new(),
+        };
+        self.next_expect(Some(Keyword::Do.into()))?;
+
+        let action = match self.next()? {
+            Token::Keyword(Keyword::Nothing) => ConflictAction::Nothing,
+            Token::Keyword(Keyword::Update) => {
+                if target.is_empty() {
+                    return Err(
+                        self.error("ON CONFLICT DO UPDATE requires a conflict target".into())
+                    );
+                }
+                self.next_expect(Some(Keyword::Set.into()))?;
+                let mut set = BTreeMap::new();
+                loop {
+                    let column = self.next_ident()?;
+                    self.next_expect(Some(Token::Equal))?;
+                    let expr = self.parse_expression(0)?;
+                    if set.contains_key(&column) {
+                        return Err(self.error(format!("Column {} set multiple times", column)));
+                    }
+                    set.insert(column, expr);
+                    if self.next_if_token(Token::Comma).is_none() {
+                        break;
+                    }
+                }
+                ConflictAction::Update {
+                    set,
+                    r#where: self.parse_clause_where()?,
+                }
+            }
+            token => return Err(self.error(format!("Unexpected token {}", token))),
+        };
+
+        Ok(OnConflict { target, action })
+    }
+
+    /// Parses an EXPLAIN statement, with an optional parenthesized option list
+    fn parse_statement_explain(&mut self) -> EasyDbResult<Statement> {
+        self.next_expect(Some(Keyword::Explain.into()))?;
+
+        let mut format = ExplainFormat::Text;
+        if self.next_if_token(Token::OpenParen).is_some() {
+            loop {
+                match self.next_ident()?.as_str() {
+                    "format" => {
+                        format = match self.next()? {
+                            Token::Keyword(Keyword::Text) => ExplainFormat::Text,
+                            Token::Ident(name) if name == "json" => ExplainFormat::Json,
+                            Token::Ident(name) if name == "dot" => ExplainFormat::Dot,
+                            token => {
+                                return Err(self.error(format!("Unknown EXPLAIN format {}", token)))
+                            }
+                        }
+                    }
+                    option => return Err(self.error(format!("Unknown EXPLAIN option {}", option))),
+                }
+                if self.next_if_token(Token::Comma).is_none() {
+                    break;
+                }
+            }
+            self.next_expect(Some(Token::CloseParen))?;
+        }
+
+        let statement = match self.peek()? {
+            Some(Token::Keyword(Keyword::Explain)) => {
+                return Err(self.error("Cannot EXPLAIN an EXPLAIN statement".into()))
+            }
+            _ => self.parse_statement()?,
+        };
+        match statement {
+            Statement::CreateTable { .. }
+            | Statement::CreateEnum { .. }
+            | Statement::DropTable(_)
+            | Statement::AlterTable { .. }
+            | Statement::CreateView { .. }
+            | Statement::DropView(_) => {
+                Err(self.error("EXPLAIN is not supported for DDL statements".into()))
+            }
+            statement => Ok(Statement::Explain {
+                statement: Box::new(statement),
+                format,
+            }),
+        }
+    }
+
+    /// Parses a TRUNCATE [TABLE] statement
+    fn parse_statement_truncate(&mut self) -> EasyDbResult<Statement> {
+        self.next_expect(Some(Keyword::Truncate.into()))?;
+        self.next_if_token(Keyword::Table.into());
+        Ok(Statement::Truncate(self.next_ident()?))
+    }
+
+    /// Parses a SHOW STATUS statement. SHOW and STATUS aren't keywords, so
+    /// that they can still be used as names.
+    fn parse_statement_show(&mut self) -> EasyDbResult<Statement> {
+        self.next()?;
+        match self.next()? {
+            Token::Ident(word) if word == "status" => Ok(Statement::ShowStatus),
+            token => Err(self.error(format!("Unexpected token {}", token))),
+        }
+    }
+
+    /// Parses a COPY table [(columns)] FROM 'path' [WITH (options)] statement.
+    /// The supported options are FORMAT TEXT | CSV and HEADER [TRUE | FALSE].
+    fn parse_statement_copy(&mut self) -> EasyDbResult<Statement> {
+        self.next_expect(Some(Keyword::Copy.into()))?;
+        let table = self.next_ident()?;
+        let columns = match self.peek()? {
+            Some(Token::OpenParen) => Some(self.parse_ident_list()?),
+            _ => None,
+        };
+        self.next_expect(Some(Keyword::From.into()))?;
+        let path = match self.next()? {
+            Token::String(path) => path.into_owned(),
+            token => return Err(self.error(format!("Expected file path, found {}", token))),
+        };
+
+        let mut format = CopyFormat::Text;
+        let mut header = false;
+        if self.next_if_token(Keyword::With.into()).is_some() {
+            self.next_expect(Some(Token::OpenParen))?;
+            loop {
+                match self.next_ident()?.as_str() {
+                    "format" => {
+                        format = match self.next()? {
+                            Token::Keyword(Keyword::Text) => CopyFormat::Text,
+                            Token::Ident(name) if name == "csv" => CopyFormat::Csv,
+                            token => {
+                                return Err(self.error(format!("Unknown COPY format {}", token)))
+                            }
+                        }
+                    }
+                    "header" => {
+                        header = self.next_if_token(Keyword::False.into()).is_none();
+                        self.next_if_token(Keyword::True.into());
+                    }
+                    option => return Err(self.error(format!("Unknown COPY option {}", option))),
+                }
+                if self.next_if_token(Token::Comma).is_none() {
+                    break;
+                }
+            }
+            self.next_expect(Some(Token::CloseParen))?;
+        }
+
+        Ok(Statement::Copy {
+            table,
+            columns,
+            path,
+            format,
+            header,
+        })
+    }
+
+    /// Parses a VALUES clause
+    fn parse_clause_values(&mut self) -> EasyDbResult<Vec<Vec<Expression>>> {
+        self.next_expect(Some(Keyword::Values.into()))?;
+        let mut values = Vec::new();
+        loop {
+            self.next_expect(Some(Token::OpenParen))?;
+            let mut row = Vec::new();
+            loop {
+                row.push(self.parse_expression(0)?);
+                if self.next_if_token(Token::Comma).is_none() {
+                    break;
+                }
+            }
+            self.next_expect(Some(Token::CloseParen))?;
+            values.push(row);
+            if self.next_if_token(Token::Comma).is_none() {
+                break;
+            }
+        }
+        Ok(values)
+    }
+
+    /// Parses a SELECT clause, if present
+    fn parse_clause_select(&mut self) -> EasyDbResult<Vec<(Expression, Option<String>)>> {
+        self.next_expect(Some(Keyword::Select.into()))?;
+        let mut select = Vec::new();
+        if self.next_if_token(Token::Asterisk).is_some() {
+            return Ok(select);
+        }
+        loop {
+            let expr = self.parse_expression(0)?;
+            let alias = match self.next_if_token(Keyword::As.into()) {
+                Some(_) => Some(self.next_ident()?),
+                None => None,
+            };
+            select.push((expr, alias));
+            if self.next_if_token(Token::Comma).is_none() {
+                break;
+            }
+        }
+        Ok(select)
+    }
+
+    /// Parses a FROM clause, if present
+    fn parse_clause_from(&mut self) -> EasyDbResult<Vec<FromItem>> {
+        let mut from = Vec::new();
+        if self.next_if_token(Keyword::From.into()).is_none() {
+            return Ok(from);
+        }
+        loop {
+            from.push(self.parse_clause_from_table()?);
+            if self.next_if_token(Token::Comma).is_none() {
+                break;
+            }
+        }
+        Ok(from)
+    }
+
+    /// Parses a possibly schema-qualified table name, table function call or
+    /// parenthesized query in a FROM clause, with an optional alias given
+    /// either as `table AS alias` or as `table alias`
+    fn parse_clause_from_table(&mut self) -> EasyDbResult<FromItem> {
+        if self.next_if_token(Token::OpenParen).is_some() {
+            let query = Box::new(self.parse_query()?);
+            self.next_expect(Some(Token::CloseParen))?;
+            let alias = self.parse_clause_from_alias()?;
+            return Ok(FromItem::Subquery { query, alias });
+        }
+        let name = self.next_ident()?;
+        if self.next_if_token(Token::Period).is_some() {
+            return Ok(FromItem::Table {
+                schema: Some(name),
+                name: self.next_ident()?,
+                alias: self.parse_clause_from_alias()?,
+            });
+        }
+        let args = match self.next_if_token(Token::OpenParen) {
+            Some(_) => Some(self.parse_function_args()?),
+            None => None,
+        };
+        let alias = self.parse_clause_from_alias()?;
+        Ok(match args {
+            Some(args) => FromItem::Function { name, args, alias },
+            None => FromItem::Table {
+                schema: None,
+                name,
+                alias,
+            },
+        })
+    }
+
+    /// Parses the optional alias of a FROM item
+    fn parse_clause_from_alias(&mut self) -> EasyDbResult<Option<String>> {
+        Ok(if self.next_if_token(Keyword::As.into()).is_some() {
+            Some(self.next_ident()?)
+        } else if let Some(Token::Ident(_)) = self.peek()? {
+            Some(self.next_ident()?)
+        } else {
+            None
+        })
+    }
+
+    /// Parses a WHERE clause, if present
+    fn parse_clause_where(&mut self) -> EasyDbResult<Option<Expression>> {
+        if self.next_if_token(Keyword::Where.into()).is_none() {
+            return Ok(None);
+        }
+        Ok(Some(self.parse_expression(0)?))
+    }
+
+    /// Parses a GROUP BY clause, if present
+    fn parse_clause_group_by(&mut self) -> EasyDbResult<Vec<Expression>> {
+        let mut exprs = Vec::new();
+        if self.next_if_token(Keyword::Group.into()).is_none() {
+            return Ok(exprs);
+        }
+        self.next_expect(Some(Keyword::By.into()))?;
+        loop {
+            exprs.push(self.parse_expression(0)?);
+            if self.next_if_token(Token::Comma).is_none() {
+                break;
+            }
+        }
+        Ok(exprs)
+    }
+
+    /// Parses a HAVING clause, if present
+    fn parse_clause_having(&mut self) -> EasyDbResult<Option<Expression>> {
+        if self.next_if_token(Keyword::Having.into()).is_none() {
+            return Ok(None);
+        }
+        Ok(Some(self.parse_expression(0)?))
+    }
+
+    /// Parses an ORDER BY clause, if present
+    fn parse_clause_order(&mut self) -> EasyDbResult<Vec<(Expression, Order)>> {
+        let mut orders = Vec::new();
+        if self.next_if_token(Keyword::Order.into()).is_none() {
+            return Ok(orders);
+        }
+        self.next_expect(Some(Keyword::By.into()))?;
+        loop {
+            orders.push((
+                self.parse_expression(0)?,
+                if self.next_if_token(Keyword::Asc.into()).is_some() {
+                    Order::Ascending
+                } else if self.next_if_token(Keyword::Desc.into()).is_some() {
+                    Order::Descending
+                } else {
+                    Order::Ascending
+                },
+            ));
+            if self.next_if_token(Token::Comma).is_none() {
+                break;
+            }
+        }
+        Ok(orders)
+    }
+
+    /// Parses an expression consisting of at least one atom operated on by any
+    /// number of operators, using the precedence climbing algorithm.
+    fn parse_expression(&mut self, min_prec: u8) -> EasyDbResult<Expression> {
+        // Prefix operators can only appear where an atom is expected, so they
+        // are accepted regardless of the surrounding precedence.
+        let mut lhs = match self.next_if_operator::<PrefixOperator>(0)? {
+            Some(PrefixOperator::Minus) if matches!(self.peek()?, Some(Token::Number(_))) => {
+                match self.next()? {
+                    Token::Number(n) => self.parse_number(&format!("-{}", n))?,
+                    token => {
+                        return Err(EasyDbError::Internal(format!("Unexpected token {}", token)))
+                    }
+                }
+            }
+            Some(prefix) => prefix.build(self.parse_expression(prefix.prec() + prefix.assoc())?),
+            None => self.parse_expression_atom()?,
+        };
+        loop {
+            if let Some(postfix) = self.next_if_operator::<PostfixOperator>(min_prec)? {
+                lhs = postfix.build(lhs);
+            } else if let Some(like) = self.next_if_not_like(min_prec)? {
+                lhs = Operation::Not(Box::new(like.build(lhs))).into();
+            } else if CAST_PREC >= min_prec && self.next_if_token(Token::DoubleColon).is_some() {
+                lhs = Operation::Cast(Box::new(lhs), self.parse_datatype()?).into();
+            } else if let Some(infix) = self.next_if_operator::<InfixOperator>(min_prec)? {
+                lhs = infix.build(lhs, self.parse_expression(infix.prec() + infix.assoc())?);
+            } else {
+                break;
+            }
+        }
+        Ok(lhs)
+    }
+
+    /// Parses an expression atom
+    fn parse_expression_atom(&mut self) -> EasyDbResult<Expression> {
+        Ok(match self.next()? {
+            Token::Number(n) => self.parse_number(n)?,
+            Token::String(s) => Literal::String(s.into_owned()).into(),
+            Token::HexString(digits) => Literal::Bytes(
+                (0..digits.len())
+                    .step_by(2)
+                    .map(|i| u8::from_str_radix(&digits[i..i + 2], 16))
+                    .collect::<Result<_, _>>()
+                    .map_err(|_| self.error(format!("Invalid hex string {}", digits)))?,
+            )
+            .into(),
+            Token::Keyword(Keyword::Interval) => match self.next()? {
+                Token::String(s) => Literal::Interval(
+                    s.parse()
+                        .map_err(|err: EasyDbError| self.error(err.to_string()))?,
+                )
+                .into(),
+                token => {
+                    return Err(self.error(format!("Expected interval string, found {}", token)))
+                }
+            },
+            Token::Keyword(Keyword::True) => Literal::Boolean(true).into(),
+            Token::Keyword(Keyword::False) => Literal::Boolean(false).into(),
+            Token::Keyword(Keyword::Null) => Literal::Null.into(),
+            Token::OpenParen if matches!(self.peek()?, Some(Token::Keyword(Keyword::Select))) => {
+                let query = self.parse_query()?;
+                self.next_expect(Some(Token::CloseParen))?;
+                Expression::Subquery(Box::new(query))
+            }
+            Token::OpenParen => {
+                let expr = self.parse_expression(0)?;
+                self.next_expect(Some(Token::CloseParen))?;
+                expr
+            }
+            Token::Keyword(Keyword::Cast) => {
+                self.next_expect(Some(Token::OpenParen))?;
+                let expr = self.parse_expression(0)?;
+                self.next_expect(Some(Keyword::As.into()))?;
+                let datatype = self.parse_datatype()?;
+                self.next_expect(Some(Token::CloseParen))?;
+                Operation::Cast(Box::new(expr), datatype).into()
+            }
+            Token::Ident(name) => {
+                let name = name.into_owned();
+                if self.next_if_token(Token::OpenParen).is_some() {
+                    let args = self.parse_function_args()?;
+                    match self.next_if_token(Keyword::Over.into()) {
+                        Some(_) => Expression::Window(name, args, self.parse_window_spec()?),
+                        None => Expression::Function(name, args),
+                    }
+                } else if self.next_if_token(Token::Period).is_some() {
+                    Expression::Field(Some(name), self.next_ident()?)
+                } else {
+                    Expression::Field(None, name)
+                }
+            }
+            token => return Err(self.error(format!("Expected expression atom, found {}", token))),
+        })
+    }
+
+    /// Parses function call arguments up to and including the closing
+    /// parenthesis. The opening parenthesis has already been consumed.
+    fn parse_function_args(&mut self) -> EasyDbResult<Vec<Expression>> {
+        let mut args = Vec::new();
+        while self.next_if_token(Token::CloseParen).is_none() {
+            if !args.is_empty() {
+                self.next_expect(Some(Token::Comma))?;
+            }
+            args.push(self.parse_expression(0)?);
+        }
+        Ok(args)
+    }
+
+    /// Parses a parenthesized window specification following OVER
+    fn parse_window_spec(&mut self) -> EasyDbResult<WindowSpec> {
+        self.next_expect(Some(Token::OpenParen))?;
+        let mut partition_by = Vec::new();
+        if self.next_if_token(Keyword::Partition.into()).is_some() {
+            self.next_expect(Some(Keyword::By.into()))?;
+            loop {
+                partition_by.push(self.parse_expression(0)?);
+                if self.next_if_token(Token::Comma).is_none() {
+                    break;
+                }
+            }
+        }
+        let order_by = self.parse_clause_order()?;
+        self.next_expect(Some(Token::CloseParen))?;
+        Ok(WindowSpec {
+            partition_by,
+            order_by,
+        })
+    }
+
+    /// Parses a numeric literal, which may carry a leading minus sign
+    fn parse_number(&self, n: &str) -> EasyDbResult<Expression> {
+        if n.trim_start_matches('-')
+            .chars()
+            .all(|c| c.is_ascii_digit())
+        {
+            Ok(Literal::Integer(
+                n.parse()
+                    .map_err(|_| self.error(format!("Invalid integer literal {}", n)))?,
+            )
+            .into())
+        } else {
+            Ok(Literal::Float(
+                n.parse()
+                    .map_err(|_| self.error(format!("Invalid float literal {}", n)))?,
+            )
+            .into())
+        }
+    }
+
+    /// Grabs the next operator if it satisfies the type and precedence
+    /// Grabs NOT [I]LIKE if it is next and satisfies the minimum precedence,
+    /// returning the [I]LIKE for the caller to negate. NOT is only taken
+    /// when LIKE or ILIKE follows it, since an expression can also be
+    /// followed by e.g. a column's NOT NULL.
+    fn next_if_not_like(&mut self, min_prec: u8) -> EasyDbResult<Option<PostfixOperator>> {
+        if self.peek()? != Some(Keyword::Not.into()) {
+            return Ok(None);
+        }
+        match self.peek_second()? {
+            Some(token @ Token::Keyword(Keyword::Like | Keyword::ILike))
+                if <PostfixOperator as Operator>::from(&token)
+                    .is_some_and(|op| op.prec() >= min_prec) =>
+            {
+                self.next()?;
+                self.next_if_operator(min_prec)
+            }
+            _ => Ok(None),
+        }
+    }
+
+    fn next_if_operator<O: Operator>(&mut self, min_prec: u8) -> EasyDbResult<Option<O>> {
+        if let Some(operator) = self
+            .peek()
+            .unwrap_or(None)
+            .and_then(|token| O::from(&token))
+            .filter(|op| op.prec() >= min_prec)
+        {
+            self.next()?;
+            Ok(Some(operator.augment(self)?))
+        } else {
+            Ok(None)
+        }
+    }
+}
+
+/// An operator trait, to help with parsing of operators
+trait Operator: Sized {
+    /// Looks up the corresponding operator for a token, if one exists
+    fn from(token: &Token) -> Option<Self>;
+    /// Augments an operator by allowing it to parse any modifiers.
+    fn augment(self, parser: &mut Parser) -> EasyDbResult<Self>;
+    /// Returns the operator's associativity
+    fn assoc(&self) -> u8;
+    /// Returns the operator's precedence
+    fn prec(&self) -> u8;
+}
+
+const LEFT_ASSOCIATIVE: u8 = 1;
+const RIGHT_ASSOCIATIVE: u8 = 0;
+
+/// The precedence of the postfix `::` cast, which binds tighter than any
+/// other operator
+const CAST_PREC: u8 = 10;
+
+/// Prefix operators
+enum PrefixOperator {
+    Minus,
+    Not,
+    Plus,
+}
+
+impl PrefixOperator {
+    fn build(&self, rhs: Expression) -> Expression {
+        let rhs = Box::new(rhs);
+        match self {
+            Self::Minus => Operation::Negate(rhs),
+            Self::Not => Operation::Not(rhs),
+            Self::Plus => Operation::Assert(rhs),
+        }
+        .into()
+    }
+}
+
+impl Operator for PrefixOperator {
+    fn from(token: &Token) -> Option<Self> {
+        match token {
+            Token::Keyword(Keyword::Not) => Some(Self::Not),
+            Token::Minus => Some(Self::Minus),
+            Token::Plus => Some(Self::Plus),
+            _ => None,
+        }
+    }
+
+    fn augment(self, _parser: &mut Parser) -> EasyDbResult<Self> {
+        Ok(self)
+    }
+
+    fn assoc(&self) -> u8 {
+        RIGHT_ASSOCIATIVE
+    }
+
+    fn prec(&self) -> u8 {
+        match self {
+            Self::Not => 3,
+            Self::Minus | Self::Plus => 9,
+        }
+    }
+}
+
+/// Infix operators
+enum InfixOperator {
+    Add,
+    And,
+    Divide,
+    Equal,
+    Exponentiate,
+    GreaterThan,
+    GreaterThanOrEqual,
+    JsonGet,
+    JsonGetText,
+    LessThan,
+    LessThanOrEqual,
+    Modulo,
+    Multiply,
+    NotEqual,
+    Or,
+    Regex,
+    Subtract,
+}
+
+impl InfixOperator {
+    fn build(&self, lhs: Expression, rhs: Expression) -> Expression {
+        let (lhs, rhs) = (Box::new(lhs), Box::new(rhs));
+        match self {
+            Self::Add => Operation::Add(lhs, rhs),
+            Self::And => Operation::And(lhs, rhs),
+            Self::Divide => Operation::Divide(lhs, rhs),
+            Self::Equal => Operation::Equal(lhs, rhs),
+            Self::Exponentiate => Operation::Exponentiate(lhs, rhs),
+            Self::GreaterThan => Operation::GreaterThan(lhs, rhs),
+            Self::GreaterThanOrEqual => Operation::GreaterThanOrEqual(lhs, rhs),
+            Self::JsonGet => Operation::JsonGet(lhs, rhs),
+            Self::JsonGetText => Operation::JsonGetText(lhs, rhs),
+            Self::LessThan => Operation::LessThan(lhs, rhs),
+            Self::LessThanOrEqual => Operation::LessThanOrEqual(lhs, rhs),
+            Self::Modulo => Operation::Modulo(lhs, rhs),
+            Self::Multiply => Operation::Multiply(lhs, rhs),
+            Self::NotEqual => Operation::NotEqual(lhs, rhs),
+            Self::Or => Operation::Or(lhs, rhs),
+            Self::Regex => Operation::Regex(lhs, rhs),
+            Self::Subtract => Operation::Subtract(lhs, rhs),
+        }
+        .into()
+    }
+}
+
+impl Operator for InfixOperator {
+    fn from(token: &Token) -> Option<Self> {
+        Some(match token {
+            Token::Arrow => Self::JsonGet,
+            Token::Asterisk => Self::Multiply,
+            Token::Caret => Self::Exponentiate,
+            Token::DoubleArrow => Self::JsonGetText,
+            Token::Equal => Self::Equal,
+            Token::GreaterThan => Self::GreaterThan,
+            Token::GreaterThanOrEqual => Self::GreaterThanOrEqual,
+            Token::Keyword(Keyword::And) => Self::And,
+            Token::Keyword(Keyword::Or) => Self::Or,
+            Token::Keyword(Keyword::Regexp) => Self::Regex,
+            Token::LessOrGreaterThan => Self::NotEqual,
+            Token::LessThan => Self::LessThan,
+            Token::LessThanOrEqual => Self::LessThanOrEqual,
+            Token::Minus => Self::Subtract,
+            Token::NotEqual => Self::NotEqual,
+            Token::Percent => Self::Modulo,
+            Token::Plus => Self::Add,
+            Token::Slash => Self::Divide,
+            Token::Tilde => Self::Regex,
+            _ => return None,
+        })
+    }
+
+    fn augment(self, _parser: &mut Parser) -> EasyDbResult<Self> {
+        Ok(self)
+    }
+
+    fn assoc(&self) -> u8 {
+        match self {
+            Self::Exponentiate => RIGHT_ASSOCIATIVE,
+            _ => LEFT_ASSOCIATIVE,
+        }
+    }
+
+    fn prec(&self) -> u8 {
+        match self {
+            Self::Or => 1,
+            Self::And => 2,
+            Self::Equal | Self::NotEqual | Self::Regex => 3,
+            Self::GreaterThan
+            | Self::GreaterThanOrEqual
+            | Self::LessThan
+            | Self::LessThanOrEqual => 4,
+            Self::Add | Self::Subtract => 5,
+            Self::Multiply | Self::Divide | Self::Modulo => 6,
+            Self::Exponentiate => 7,
+            Self::JsonGet | Self::JsonGetText => 8,
+        }
+    }
+}
+
+/// Postfix operators
+enum PostfixOperator {
+    IsNull {
+        not: bool,
+    },
+    /// IS [NOT] DISTINCT FROM, which takes its right-hand operand along
+    IsDistinctFrom {
+        not: bool,
+        rhs: Expression,
+    },
+    /// [I]LIKE, which takes its pattern and escape character along. The
+    /// pattern is NULL until augment() parses it.
+    Like {
+        case_insensitive: bool,
+        pattern: Expression,
+        escape: Option<char>,
+    },
+}
+
+impl PostfixOperator {
+    fn build(self, lhs: Expression) -> Expression {
+        let lhs = Box::new(lhs);
+        let (operation, not) = match self {
+            Self::IsNull { not } => (Operation::IsNull(lhs), not),
+            Self::IsDistinctFrom { not, rhs } => {
+                (Operation::IsDistinctFrom(lhs, Box::new(rhs)), not)
+            }
+            Self::Like {
+                case_insensitive,
+                pattern,
+                escape,
+            } => match case_insensitive {
+                true => (Operation::ILike(lhs, Box::new(pattern), escape), false),
+                false => (Operation::Like(lhs, Box::new(pattern), escape), false),
+            },
+        };
+        match not {
+            true => Operation::Not(Box::new(operation.into())).into(),
+            false => operation.into(),
+        }
+    }
+}
+
+impl Operator for PostfixOperator {
+    fn from(token: &Token) -> Option<Self> {
+        match token {
+            Token::Keyword(Keyword::Is) => Some(Self::IsNull { not: false }),
+            Token::Keyword(keyword @ (Keyword::Like | Keyword::ILike)) => Some(Self::Like {
+                case_insensitive: *keyword == Keyword::ILike,
+                pattern: Literal::Null.into(),
+                escape: None,
+            }),
+            _ => None,
+        }
+    }
+
+    fn augment(self, parser: &mut Parser) -> EasyDbResult<Self> {
+        Ok(match self {
+            Self::IsNull { .. } | Self::IsDistinctFrom { .. } => {
+                let not = parser.next_if_token(Keyword::Not.into()).is_some();
+                if parser.next_if_token(Keyword::Distinct.into()).is_some() {
+                    parser.next_expect(Some(Keyword::From.into()))?;
+                    let rhs = parser.parse_expression(self.prec() + LEFT_ASSOCIATIVE)?;
+                    Self::IsDistinctFrom { not, rhs }
+                } else {
+                    parser.next_expect(Some(Keyword::Null.into()))?;
+                    Self::IsNull { not }
+                }
+            }
+            Self::Like {
+                case_insensitive, ..
+            } => {
+                let pattern = parser.parse_expression(self.prec() + LEFT_ASSOCIATIVE)?;
+                let escape = match parser.next_if(|t| matches!(t, Token::Ident(i) if i == "escape"))
+                {
+                    None => None,
+                    Some(_) => match parser.next()? {
+                        Token::String(s) if s.chars().count() == 1 => s.chars().next(),
+                        token => {
+                            return Err(parser.error(format!(
+                                "Expected a single-character escape string, found {}",
+                                token
+                            )))
+                        }
+                    },
+                };
+                Self::Like {
+                    case_insensitive,
+                    pattern,
+                    escape,
+                }
+            }
+        })
+    }
+
+    fn assoc(&self) -> u8 {
+        LEFT_ASSOCIATIVE
+    }
+
+    fn prec(&self) -> u8 {
+        match self {
+            Self::IsNull { .. } | Self::IsDistinctFrom { .. } | Self::Like { .. } => 3,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Parses a script with recovery, returning the statements as SQL and
+    /// the number of errors
+    fn parse_with_recovery(input: &str) -> (Vec<String>, usize) {
+        let (statements, errors) = Parser::new(input).parse_all_with_recovery();
+        let statements = statements.iter().map(|s| s.to_string()).collect();
+        (statements, errors.len())
+    }
+
+    /// Parses a statement and returns it as SQL
+    fn roundtrip(input: &str) -> EasyDbResult<String> {
+        Ok(Parser::new(input).parse()?.to_string())
+    }
+
+    #[test]
+    fn set_operation_order_and_limit_apply_to_whole_query() {
+        let query =
+            Parser::new("SELECT a FROM t UNION SELECT b FROM u ORDER BY 1 LIMIT 1 OFFSET 2")
+                .parse()
+                .unwrap();
+        let Statement::SetOperation {
+            right,
+            order,
+            offset,
+            limit,
+            ..
+        } = &query
+        else {
+            panic!("expected a set operation, got {:?}", query);
+        };
+        assert_eq!(order.len(), 1);
+        assert_eq!(limit, &Some(Literal::Integer(1).into()));
+        assert_eq!(offset, &Some(Literal::Integer(2).into()));
+        assert!(matches!(
+            right.as_ref(),
+            Statement::Select { order, limit: None, offset: None, .. } if order.is_empty()
+        ));
+        assert_eq!(
+            query.to_string(),
+            "SELECT a FROM t UNION SELECT b FROM u ORDER BY 1 ASC LIMIT 1 OFFSET 2"
+        );
+
+        // An operand's own clauses need parentheses, and survive a round trip
+        for sql in [
+            "(SELECT a FROM t LIMIT 1) UNION SELECT b FROM u",
+            "SELECT a FROM t UNION (SELECT b FROM u ORDER BY b ASC LIMIT 1) ORDER BY 1 ASC",
+            "SELECT a FROM t ORDER BY a ASC LIMIT 1",
+        ] {
+            assert_eq!(roundtrip(sql).unwrap(), sql);
+        }
+        assert!(roundtrip("(SELECT a FROM t LIMIT 1) LIMIT 2").is_err());
+        assert_eq!(
+            roundtrip("(SELECT a FROM t ORDER BY a) LIMIT 2").unwrap(),
+            "SELECT a FROM t ORDER BY a ASC LIMIT 2"
+        );
+    }
+
+    #[test]
+    fn subqueries_are_not_reserved_keyword_errors() {
+        assert_eq!(roundtrip("SELECT (SELECT 1)").unwrap(), "SELECT (SELECT 1)");
+        assert_eq!(
+            roundtrip("SELECT 1 + (SELECT a FROM t) FROM u").unwrap(),
+            "SELECT 1 + (SELECT a FROM t) FROM u"
+        );
+        assert_eq!(
+            roundtrip("INSERT INTO t (SELECT 1)").unwrap(),
+            "INSERT INTO t SELECT 1"
+        );
+        assert_eq!(
+            roundtrip("INSERT INTO t (a) SELECT 1").unwrap(),
+            "INSERT INTO t (a) SELECT 1"
+        );
+    }
+
+    #[test]
+    fn reserved_keyword_as_identifier() {
+        let err = roundtrip("CREATE TABLE select (a INTEGER PRIMARY KEY)").unwrap_err();
+        assert!(
+            err.to_string().contains("SELECT is a reserved keyword"),
+            "{}",
+            err
+        );
+        let err = roundtrip("SELECT FROM").unwrap_err();
+        assert!(!err.to_string().contains("reserved keyword"), "{}", err);
+        assert_eq!(
+            roundtrip("SELECT \"select\" FROM t").unwrap(),
+            "SELECT \"select\" FROM t"
+        );
+    }
+
+    #[test]
+    fn like() {
+        for sql in [
+            "SELECT a LIKE 'x%'",
+            "SELECT a ILIKE 'x%'",
+            "SELECT a LIKE 'x!%' ESCAPE '!'",
+            "SELECT a NOT LIKE 'x%'",
+            "SELECT a NOT ILIKE 'x!%' ESCAPE '!'",
+            "SELECT (a NOT LIKE 'x%') AND b",
+        ] {
+            assert_eq!(roundtrip(sql).unwrap(), sql);
+        }
+        let like = Operation::Like(
+            Box::new(Expression::Field(None, "a".into())),
+            Box::new(Literal::String("x%".into()).into()),
+            None,
+        );
+        let Statement::Select { select, .. } =
+            Parser::new("SELECT a NOT LIKE 'x%'").parse().unwrap()
+        else {
+            panic!("expected a SELECT");
+        };
+        assert_eq!(select[0].0, Operation::Not(Box::new(like.into())).into());
+        assert_eq!(
+            roundtrip("SELECT NOT (a LIKE 'x%')").unwrap(),
+            "SELECT a NOT LIKE 'x%'"
+        );
+        assert_eq!(
+            roundtrip("SELECT a NOT LIKE 'x' OR b").unwrap(),
+            "SELECT (a NOT LIKE 'x') OR b"
+        );
+
+        // NOT only belongs to the LIKE when LIKE or ILIKE follows it
+        assert_eq!(
+            roundtrip("CREATE TABLE t (a TEXT DEFAULT 'x' NOT NULL)").unwrap(),
+            "CREATE TABLE t (a STRING NOT NULL DEFAULT 'x')"
+        );
+        assert!(roundtrip("SELECT a NOT 'x%'").is_err());
+        assert!(roundtrip("SELECT a LIKE 'x' ESCAPE 'ab'").is_err());
+    }
+
+    #[test]
+    fn explain() {
+        for sql in [
+            "EXPLAIN SELECT a FROM t",
+            "EXPLAIN (FORMAT JSON) SELECT a FROM t",
+            "EXPLAIN (FORMAT DOT) INSERT INTO t VALUES (1)",
+        ] {
+            assert_eq!(roundtrip(sql).unwrap(), sql);
+        }
+        assert_eq!(
+            roundtrip("EXPLAIN (FORMAT TEXT) SELECT 1").unwrap(),
+            "EXPLAIN SELECT 1"
+        );
+        let Statement::Explain { statement, format } =
+            Parser::new("EXPLAIN (FORMAT JSON) SELECT 1")
+                .parse()
+                .unwrap()
+        else {
+            panic!("expected EXPLAIN");
+        };
+        assert_eq!(format, ExplainFormat::Json);
+        assert!(matches!(*statement, Statement::Select { .. }));
+        for sql in [
+            "EXPLAIN EXPLAIN SELECT 1",
+            "EXPLAIN CREATE TABLE t (a INTEGER PRIMARY KEY)",
+            "EXPLAIN DROP VIEW v",
+            "EXPLAIN (FORMAT XML) SELECT 1",
+            "EXPLAIN (ANALYZE) SELECT 1",
+            "EXPLAIN",
+        ] {
+            assert!(roundtrip(sql).is_err(), "{}", sql);
+        }
+    }
+
+    #[test]
+    fn truncate() {
+        assert_eq!(roundtrip("TRUNCATE TABLE t").unwrap(), "TRUNCATE TABLE t");
+        assert_eq!(roundtrip("TRUNCATE t").unwrap(), "TRUNCATE TABLE t");
+        assert_eq!(
+            Parser::new("TRUNCATE \"table\"").parse().unwrap(),
+            Statement::Truncate("table".into())
+        );
+        assert!(roundtrip("TRUNCATE").is_err());
+        assert!(roundtrip("TRUNCATE TABLE").is_err());
+        assert!(roundtrip("TRUNCATE TABLE t u").is_err());
+    }
+
+    #[test]
+    fn copy() {
+        for sql in [
+            "COPY t FROM '/tmp/t.txt' WITH (FORMAT TEXT)",
+            "COPY t (a, b) FROM '/tmp/t.csv' WITH (FORMAT CSV, HEADER)",
+        ] {
+            assert_eq!(roundtrip(sql).unwrap(), sql);
+        }
+        assert_eq!(
+            roundtrip("COPY t FROM 'it''s.csv' WITH (HEADER FALSE, FORMAT CSV)").unwrap(),
+            "COPY t FROM 'it''s.csv' WITH (FORMAT CSV)"
+        );
+        assert_eq!(
+            Parser::new("COPY t FROM 'f' WITH (HEADER TRUE)")
+                .parse()
+                .unwrap(),
+            Statement::Copy {
+                table: "t".into(),
+                columns: None,
+                path: "f".into(),
+                format: CopyFormat::Text,
+                header: true,
+            }
+        );
+        for sql in [
+            "COPY t FROM f",
+            "COPY t 'f'",
+            "COPY t FROM 'f' WITH (FORMAT XML)",
+            "COPY t FROM 'f' WITH (DELIMITER ';')",
+            "COPY t FROM 'f' WITH ()",
+        ] {
+            assert!(roundtrip(sql).is_err(), "{}", sql);
+        }
+    }
+
+    #[test]
+    fn regex() {
+        assert_eq!(roundtrip("SELECT a ~ '^x'").unwrap(), "SELECT a ~ '^x'");
+        assert_eq!(
+            roundtrip("SELECT a REGEXP '^x'").unwrap(),
+            "SELECT a ~ '^x'"
+        );
+        assert_eq!(
+            roundtrip("SELECT a ~ 'x' AND b + 1 ~ 'z'").unwrap(),
+            "SELECT (a ~ 'x') AND ((b + 1) ~ 'z')"
+        );
+        let Statement::Select { select, .. } = Parser::new("SELECT a ~ 'x'").parse().unwrap()
+        else {
+            panic!("expected a SELECT");
+        };
+        assert_eq!(
+            select[0].0,
+            Operation::Regex(
+                Box::new(Expression::Field(None, "a".into())),
+                Box::new(Literal::String("x".into()).into()),
+            )
+            .into()
+        );
+        assert!(roundtrip("SELECT a ~").is_err());
+    }
+
+    #[test]
+    fn views() {
+        for sql in [
+            "CREATE VIEW v AS SELECT a, b + 1 AS c FROM t WHERE a > 1",
+            "CREATE VIEW v AS SELECT a FROM t UNION SELECT b FROM u",
+            "DROP VIEW v",
+            "SELECT c FROM v",
+        ] {
+            assert_eq!(roundtrip(sql).unwrap(), sql);
+        }
+        let Statement::CreateView { name, query } =
+            Parser::new("CREATE VIEW v AS SELECT 1").parse().unwrap()
+        else {
+            panic!("expected CREATE VIEW");
+        };
+        assert_eq!(name, "v");
+        assert!(matches!(*query, Statement::Select { .. }));
+        for sql in [
+            "CREATE VIEW v SELECT 1",
+            "CREATE VIEW AS SELECT 1",
+            "CREATE VIEW v AS INSERT INTO t VALUES (1)",
+            "DROP VIEW",
+        ] {
+            assert!(roundtrip(sql).is_err(), "{}", sql);
+        }
+    }
+
+    #[test]
+    fn on_delete() {
+        for sql in [
+            "CREATE TABLE c (id INTEGER PRIMARY KEY, p INTEGER REFERENCES p ON DELETE CASCADE)",
+            "CREATE TABLE c (id INTEGER PRIMARY KEY, p INTEGER REFERENCES p ON DELETE SET NULL)",
+            "CREATE TABLE c (id INTEGER PRIMARY KEY, a INTEGER, b INTEGER, \
+             FOREIGN KEY (a, b) REFERENCES p (x, y) ON DELETE CASCADE)",
+            "CREATE TABLE c (id INTEGER PRIMARY KEY, p INTEGER REFERENCES p)",
+        ] {
+            assert_eq!(roundtrip(sql).unwrap(), sql);
+        }
+        // RESTRICT and NO ACTION are the default, which isn't written out
+        for action in ["RESTRICT", "NO ACTION"] {
+            assert_eq!(
+                roundtrip(&format!(
+                    "CREATE TABLE c (id INTEGER PRIMARY KEY, p INTEGER REFERENCES p ON DELETE {})",
+                    action
+                ))
+                .unwrap(),
+                "CREATE TABLE c (id INTEGER PRIMARY KEY, p INTEGER REFERENCES p)"
+            );
+        }
+        let Statement::CreateTable { constraints, .. } = Parser::new(
+            "CREATE TABLE c (a INTEGER, FOREIGN KEY (a) REFERENCES p (x) ON DELETE SET NULL)",
+        )
+        .parse()
+        .unwrap() else {
+            panic!("expected CREATE TABLE");
+        };
+        assert!(matches!(
+            constraints[0],
+            Constraint::ForeignKey {
+                on_delete: ReferentialAction::SetNull,
+                ..
+            }
+        ));
+        for sql in [
+            "CREATE TABLE c (p INTEGER REFERENCES p ON DELETE)",
+            "CREATE TABLE c (p INTEGER REFERENCES p ON UPDATE CASCADE)",
+            "CREATE TABLE c (p INTEGER REFERENCES p ON DELETE SET DEFAULT)",
+            "CREATE TABLE c (p INTEGER REFERENCES p ON DELETE NO)",
+        ] {
+            assert!(roundtrip(sql).is_err(), "{}", sql);
+        }
+    }
+
+    #[test]
+    fn recovery_reports_every_error() {
+        assert_eq!(
+            parse_with_recovery("SELECT 1; SELECT FROM; SELECT 2; SELECT +; SELECT 3"),
+            (
+                vec![
+                    "SELECT 1".to_string(),
+                    "SELECT 2".to_string(),
+                    "SELECT 3".to_string()
+                ],
+                2
+            )
+        );
+    }
+
+    #[test]
+    fn recovery_consumes_lexer_errors_after_semicolon() {
+        assert_eq!(
+            parse_with_recovery("SELECT 1; 'abc"),
+            (vec!["SELECT 1".to_string()], 1)
+        );
+        assert_eq!(
+            parse_with_recovery("SELECT 1; @"),
+            (vec!["SELECT 1".to_string()], 1)
+        );
+        assert_eq!(
+            parse_with_recovery("SELECT 1; @ SELECT 2; SELECT 3"),
+            (vec!["SELECT 1".to_string(), "SELECT 3".to_string()], 1)
+        );
+    }
 }
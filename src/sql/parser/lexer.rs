@@ -1,14 +1,18 @@
 use crate::error::{EasyDbError, EasyDbResult};
 
+use std::borrow::Cow;
 use std::iter::Peekable;
-use std::str::Chars;
+use std::str::CharIndices;
 
-// A lexer token
+// A lexer token. Payloads borrow from the input wherever the token text can
+// be used as-is.
 #[derive(Clone, Debug, PartialEq)]
-pub enum Token {
-    Number(String),
-    String(String),
-    Ident(String),
+pub enum Token<'a> {
+    Number(&'a str),
+    String(Cow<'a, str>),
+    /// The hex digits of a binary string literal, x'DEADBEEF'
+    HexString(&'a str),
+    Ident(Cow<'a, str>),
     Keyword(Keyword),
     Period,
     Equal,
@@ -26,18 +30,24 @@ pub enum Token {
     CloseParen,
     Comma,
     Semicolon,
+    Colon,
+    DoubleColon,
     GreaterThanOrEqual,
     LessThanOrEqual,
     LessOrGreaterThan,
     NotEqual,
+    Arrow,
+    DoubleArrow,
+    Tilde,
 }
 
-impl std::fmt::Display for Token {
+impl std::fmt::Display for Token<'_> {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
         f.write_str(match self {
             Token::Number(n) => n,
-            Token::String(s) => s,
-            Token::Ident(s) => s,
+            Token::String(s) => s.as_ref(),
+            Token::HexString(s) => s,
+            Token::Ident(s) => s.as_ref(),
             Token::Keyword(k) => k.to_str(),
             Token::Period => ".",
             Token::Equal => "=",
@@ -54,11 +64,16 @@ impl std::fmt::Display for Token {
             Token::Percent => "%",
             Token::Exclamation => "!",
             Token::NotEqual => "!=",
+            Token::Arrow => "->",
+            Token::DoubleArrow => "->>",
+            Token::Tilde => "~",
             Token::Question => "?",
             Token::OpenParen => "(",
             Token::CloseParen => ")",
             Token::Comma => ",",
             Token::Semicolon => ";",
+            Token::Colon => ":",
+            Token::DoubleColon => "::",
         })
     }
 }
@@ -70,7 +85,7 @@ let my_str = "hello"; // str
 
 let my_string = String::from(my_str); // string
 */
-impl From<Keyword> for Token {
+impl From<Keyword> for Token<'_> {
     fn from(keyword: Keyword) -> Self {
         Self::Keyword(keyword)
     }
@@ -101,15 +116,148 @@ pub enum Keyword {
     Unique,
     Index,
     References,
+    Foreign,
+    Select,
+    From,
+    Where,
+    Group,
+    By,
+    Having,
+    Order,
+    Asc,
+    Desc,
+    Limit,
+    Offset,
+    Insert,
+    Into,
+    Values,
+    Or,
+    Like,
+    Is,
+    Truncate,
+    Union,
+    Intersect,
+    Except,
+    All,
+    As,
+    True,
+    False,
+    Cast,
+    Explain,
+    Copy,
+    With,
+    On,
+    Conflict,
+    Do,
+    Nothing,
+    Update,
+    Set,
+    Over,
+    Partition,
+    Decimal,
+    Numeric,
+    Alter,
+    Rename,
+    To,
+    Column,
+    Blob,
+    Bytea,
+    Collate,
+    Distinct,
+    Interval,
+    Smallint,
+    Bigint,
+    ILike,
+    Regexp,
+    Delete,
+    Add,
+    Autoincrement,
 }
 
-impl Keyword {}
-
 impl Keyword {
     #[allow(clippy::should_implement_trait)]
     pub fn from_str(ident: &str) -> Option<Self> {
-        Some(match ident.to_uppercase().as_ref() {
+        // Keywords are ASCII, and a full Unicode uppercasing would turn e.g.
+        // a long s into one ('ſelect' into 'SELECT')
+        Some(match ident.to_ascii_uppercase().as_ref() {
             "AND" => Self::And,
+            "CREATE" => Self::Create,
+            "DROP" => Self::Drop,
+            "TABLE" => Self::Table,
+            "BOOL" => Self::Bool,
+            "BOOLEAN" => Self::Boolean,
+            "CHAR" => Self::Char,
+            "DOUBLE" => Self::Double,
+            "FLOAT" => Self::Float,
+            "INT" => Self::Int,
+            "INTEGER" => Self::Integer,
+            "STRING" => Self::String,
+            "TEXT" => Self::Text,
+            "VARCHAR" => Self::Varchar,
+            "PRIMARY" => Self::Primary,
+            "KEY" => Self::Key,
+            "NULL" => Self::Null,
+            "NOT" => Self::Not,
+            "DEFAULT" => Self::Default,
+            "UNIQUE" => Self::Unique,
+            "INDEX" => Self::Index,
+            "REFERENCES" => Self::References,
+            "FOREIGN" => Self::Foreign,
+            "SELECT" => Self::Select,
+            "FROM" => Self::From,
+            "WHERE" => Self::Where,
+            "GROUP" => Self::Group,
+            "BY" => Self::By,
+            "HAVING" => Self::Having,
+            "ORDER" => Self::Order,
+            "ASC" => Self::Asc,
+            "DESC" => Self::Desc,
+            "LIMIT" => Self::Limit,
+            "OFFSET" => Self::Offset,
+            "INSERT" => Self::Insert,
+            "INTO" => Self::Into,
+            "VALUES" => Self::Values,
+            "OR" => Self::Or,
+            "LIKE" => Self::Like,
+            "IS" => Self::Is,
+            "TRUNCATE" => Self::Truncate,
+            "UNION" => Self::Union,
+            "INTERSECT" => Self::Intersect,
+            "EXCEPT" => Self::Except,
+            "ALL" => Self::All,
+            "AS" => Self::As,
+            "TRUE" => Self::True,
+            "FALSE" => Self::False,
+            "CAST" => Self::Cast,
+            "EXPLAIN" => Self::Explain,
+            "COPY" => Self::Copy,
+            "WITH" => Self::With,
+            "ON" => Self::On,
+            "CONFLICT" => Self::Conflict,
+            "DO" => Self::Do,
+            "NOTHING" => Self::Nothing,
+            "UPDATE" => Self::Update,
+            "SET" => Self::Set,
+            "OVER" => Self::Over,
+            "PARTITION" => Self::Partition,
+            "DECIMAL" => Self::Decimal,
+            "NUMERIC" => Self::Numeric,
+            "ALTER" => Self::Alter,
+            "RENAME" => Self::Rename,
+            "TO" => Self::To,
+            "COLUMN" => Self::Column,
+            "BLOB" => Self::Blob,
+            "BYTEA" => Self::Bytea,
+            "COLLATE" => Self::Collate,
+            "DISTINCT" => Self::Distinct,
+            "INTERVAL" => Self::Interval,
+            "SMALLINT" => Self::Smallint,
+            "BIGINT" => Self::Bigint,
+            "ILIKE" => Self::ILike,
+            "REGEXP" => Self::Regexp,
+            "DELETE" => Self::Delete,
+            "ADD" => Self::Add,
+            "AUTOINCREMENT" => Self::Autoincrement,
             _ => return None,
         })
     }
@@ -117,6 +265,83 @@ impl Keyword {
     pub fn to_str(&self) -> &str {
         match self {
             Self::And => "AND",
+            Self::Create => "CREATE",
+            Self::Drop => "DROP",
+            Self::Table => "TABLE",
+            Self::Bool => "BOOL",
+            Self::Boolean => "BOOLEAN",
+            Self::Char => "CHAR",
+            Self::Double => "DOUBLE",
+            Self::Float => "FLOAT",
+            Self::Int => "INT",
+            Self::Integer => "INTEGER",
+            Self::String => "STRING",
+            Self::Text => "TEXT",
+            Self::Varchar => "VARCHAR",
+            Self::Primary => "PRIMARY",
+            Self::Key => "KEY",
+            Self::Null => "NULL",
+            Self::Not => "NOT",
+            Self::Default => "DEFAULT",
+            Self::Unique => "UNIQUE",
+            Self::Index => "INDEX",
+            Self::References => "REFERENCES",
+            Self::Foreign => "FOREIGN",
+            Self::Select => "SELECT",
+            Self::From => "FROM",
+            Self::Where => "WHERE",
+            Self::Group => "GROUP",
+            Self::By => "BY",
+            Self::Having => "HAVING",
+            Self::Order => "ORDER",
+            Self::Asc => "ASC",
+            Self::Desc => "DESC",
+            Self::Limit => "LIMIT",
+            Self::Offset => "OFFSET",
+            Self::Insert => "INSERT",
+            Self::Into => "INTO",
+            Self::Values => "VALUES",
+            Self::Or => "OR",
+            Self::Like => "LIKE",
+            Self::Is => "IS",
+            Self::Truncate => "TRUNCATE",
+            Self::Union => "UNION",
+            Self::Intersect => "INTERSECT",
+            Self::Except => "EXCEPT",
+            Self::All => "ALL",
+            Self::As => "AS",
+            Self::True => "TRUE",
+            Self::False => "FALSE",
+            Self::Cast => "CAST",
+            Self::Explain => "EXPLAIN",
+            Self::Copy => "COPY",
+            Self::With => "WITH",
+            Self::On => "ON",
+            Self::Conflict => "CONFLICT",
+            Self::Do => "DO",
+            Self::Nothing => "NOTHING",
+            Self::Update => "UPDATE",
+            Self::Set => "SET",
+            Self::Over => "OVER",
+            Self::Partition => "PARTITION",
+            Self::Decimal => "DECIMAL",
+            Self::Numeric => "NUMERIC",
+            Self::Alter => "ALTER",
+            Self::Rename => "RENAME",
+            Self::To => "TO",
+            Self::Column => "COLUMN",
+            Self::Blob => "BLOB",
+            Self::Bytea => "BYTEA",
+            Self::Collate => "COLLATE",
+            Self::Distinct => "DISTINCT",
+            Self::Interval => "INTERVAL",
+            Self::Smallint => "SMALLINT",
+            Self::Bigint => "BIGINT",
+            Self::ILike => "ILIKE",
+            Self::Regexp => "REGEXP",
+            Self::Delete => "DELETE",
+            Self::Add => "ADD",
+            Self::Autoincrement => "AUTOINCREMENT",
         }
     }
 }
@@ -127,21 +352,85 @@ impl std::fmt::Display for Keyword {
     }
 }
 
-/// just an iterator
+/// just an iterator, walking the input by byte offset so that tokens can
+/// be sliced straight out of it
+#[derive(Clone)]
 pub struct Lexer<'a> {
-    iter: Peekable<Chars<'a>>,
+    input: &'a str,
+    iter: Peekable<CharIndices<'a>>,
+    options: LexerOptions,
+}
+
+/// Options controlling how the lexer scans its input
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct LexerOptions {
+    /// Interprets backslash escapes (\n, \r, \t, \', \\) in string literals.
+    /// A doubled single quote is always accepted, as in standard SQL.
+    pub backslash_escapes: bool,
+}
+
+/// The byte range a token occupies in the input
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+}
+
+/// Whether the character can start an unquoted identifier
+pub fn is_ident_start(c: char) -> bool {
+    c.is_alphabetic() || c == '_'
 }
 
+/// Whether the character can appear in an unquoted identifier after the first
+pub fn is_ident_continue(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+/// Whether the string is unchanged by lowercasing, as unquoted identifiers are
+pub fn is_lowercase(s: &str) -> bool {
+    s.chars().flat_map(char::to_lowercase).eq(s.chars())
+}
+
+/// Describes where a byte offset lies in the input: its line and column
+/// (both 1-based), followed by the source line with a caret under it
+pub fn describe_position(input: &str, offset: usize) -> String {
+    let before = &input[..offset];
+    let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+    let line = before.matches('\n').count() + 1;
+    let column = before[line_start..].chars().count() + 1;
+    let text = input[line_start..].lines().next().unwrap_or("");
+    format!(
+        "line {}, column {}:\n{}\n{}^",
+        line,
+        column,
+        text,
+        " ".repeat(column - 1)
+    )
+}
+
+/// Yields tokens along with the span of input they were scanned from
 impl<'a> Iterator for Lexer<'a> {
-    type Item = EasyDbResult<Token>;
+    type Item = EasyDbResult<(Token<'a>, Span)>;
 
-    fn next(&mut self) -> Option<EasyDbResult<Token>> {
+    fn next(&mut self) -> Option<EasyDbResult<(Token<'a>, Span)>> {
+        self.skip_whitespace();
+        let start = self.pos();
         match self.scan() {
-            Ok(Some(token)) => Some(Ok(token)),
-            Ok(None) => self
-                .iter
-                .peek()
-                .map(|c| Err(EasyDbError::Parse(format!("Unexpected character {}", c)))),
+            Ok(Some(token)) => Some(Ok((
+                token,
+                Span {
+                    start,
+                    end: self.pos(),
+                },
+            ))),
+            Ok(None) => {
+                // Skip the character, so lexing can resume after the error
+                let (_, c) = self.iter.next()?;
+                Some(Err(
+                    self.error_at(start, format!("Unexpected character {}", c))
+                ))
+            }
+            Err(EasyDbError::Parse(message)) => Some(Err(self.error_at(start, message))),
             Err(err) => Some(Err(err)),
         }
     }
@@ -149,19 +438,37 @@ impl<'a> Iterator for Lexer<'a> {
 
 impl<'a> Lexer<'a> {
     pub fn new(input: &'a str) -> Lexer<'a> {
+        Self::with_options(input, LexerOptions::default())
+    }
+
+    pub fn with_options(input: &'a str, options: LexerOptions) -> Lexer<'a> {
         Lexer {
-            iter: input.chars().peekable(),
+            input,
+            iter: input.char_indices().peekable(),
+            options,
         }
     }
 
-    /// Scans the input for the next token if any, ignoring leading whitespace
-    fn scan(&mut self) -> EasyDbResult<Option<Token>> {
-        self.skip_whitespace();
-        match self.iter.peek() {
-            // Some('\'') => self.scan_string(),
-            // Some('"') => self.scan_ident_quoted(),
-            Some(c) if c.is_digit(10) => Ok(self.scan_number()),
-            // Some(c) if c.is_alphabetic() => Ok(self.scan_ident()),
+    /// Builds a parse error pointing at the given byte offset
+    fn error_at(&self, offset: usize, message: String) -> EasyDbError {
+        EasyDbError::Parse(format!(
+            "{} at {}",
+            message,
+            describe_position(self.input, offset)
+        ))
+    }
+
+    /// Scans the input for the next token if any. Leading whitespace has
+    /// already been skipped.
+    fn scan(&mut self) -> EasyDbResult<Option<Token<'a>>> {
+        match self.iter.peek().map(|&(_, c)| c) {
+            Some('\'') => self.scan_string(),
+            Some('x' | 'X') if self.input[self.pos() + 1..].starts_with('\'') => {
+                self.scan_hex_string()
+            }
+            Some('"') => self.scan_ident_quoted(),
+            Some(c) if c.is_ascii_digit() => Ok(self.scan_number()),
+            Some(c) if is_ident_start(c) => Ok(self.scan_ident()),
             Some(_) => Ok(self.scan_symbol()),
             None => Ok(None),
         }
@@ -171,53 +478,193 @@ impl<'a> Lexer<'a> {
         self.next_while(|c| c.is_whitespace());
     }
 
+    /// The byte offset of the next character, or the input length at the end
+    fn pos(&mut self) -> usize {
+        self.iter.peek().map_or(self.input.len(), |&(i, _)| i)
+    }
+
     fn next_if<F: Fn(char) -> bool>(&mut self, predicate: F) -> Option<char> {
-        self.iter.peek().filter(|&c| predicate(*c))?;
-        self.iter.next()
+        self.iter.next_if(|&(_, c)| predicate(c)).map(|(_, c)| c)
+    }
+
+    fn next_while<F: Fn(char) -> bool>(&mut self, predicate: F) -> Option<&'a str> {
+        let start = self.pos();
+        while self.next_if(&predicate).is_some() {}
+        Some(&self.input[start..self.pos()]).filter(|v| !v.is_empty())
     }
 
-    fn next_while<F: Fn(char) -> bool>(&mut self, predicate: F) -> Option<String> {
-        let mut value = String::new();
+    fn scan_number(&mut self) -> Option<Token<'a>> {
+        let start = self.pos();
+        self.next_while(|c| c.is_ascii_digit())?;
 
-        while let Some(c) = self.next_if(&predicate) {
-            value.push(c)
+        if self.next_if(|c| c == '.').is_some() {
+            self.next_while(|c| c.is_ascii_digit());
         }
 
-        Some(value).filter(|v| !v.is_empty())
-    }
+        if self.next_if(|c| c == 'e' || c == 'E').is_some() {
+            self.next_if(|c| c == '+' || c == '-');
+            self.next_while(|c| c.is_ascii_digit());
+        }
 
-    fn scan_number(&mut self) -> Option<Token> {
-        let mut num = self.next_while(|c| c.is_digit(10))?;
+        Some(Token::Number(&self.input[start..self.pos()]))
+    }
 
-        if let Some(sep) = self.next_if(|c| c == '.') {
-            num.push(sep);
+    /// Scans a quoted string literal. A doubled quote stands for a literal
+    /// quote, as do backslash escapes if enabled; the literal is only copied
+    /// out of the input when it contains either.
+    fn scan_string(&mut self) -> EasyDbResult<Option<Token<'a>>> {
+        if self.next_if(|c| c == '\'').is_none() {
+            return Ok(None);
+        }
 
-            while let Some(dec) = self.next_if(|c| c.is_digit(10)) {
-                num.push(dec)
+        let start = self.pos();
+        let mut unescaped: Option<String> = None;
+        loop {
+            match self.iter.next() {
+                Some((i, '\'')) => {
+                    if self.next_if(|c| c == '\'').is_none() {
+                        return Ok(Some(Token::String(match unescaped {
+                            Some(s) => Cow::Owned(s),
+                            None => Cow::Borrowed(&self.input[start..i]),
+                        })));
+                    }
+                    unescaped
+                        .get_or_insert_with(|| self.input[start..i].to_string())
+                        .push('\'')
+                }
+                Some((i, '\\')) if self.options.backslash_escapes => {
+                    let c = match self.iter.next() {
+                        Some((_, 'n')) => '\n',
+                        Some((_, 'r')) => '\r',
+                        Some((_, 't')) => '\t',
+                        Some((_, '\'')) => '\'',
+                        Some((_, '\\')) => '\\',
+                        Some((_, c)) => {
+                            return Err(EasyDbError::Parse(format!(
+                                "Unknown escape sequence \\{}",
+                                c
+                            )))
+                        }
+                        None => {
+                            return Err(EasyDbError::Parse(
+                                "Unexpected end of string literal".into(),
+                            ))
+                        }
+                    };
+                    unescaped
+                        .get_or_insert_with(|| self.input[start..i].to_string())
+                        .push(c)
+                }
+                Some((_, c)) => {
+                    if let Some(s) = unescaped.as_mut() {
+                        s.push(c)
+                    }
+                }
+                None => {
+                    return Err(EasyDbError::Parse(
+                        "Unexpected end of string literal".into(),
+                    ))
+                }
             }
         }
+    }
 
-        if let Some(exp) = self.next_if(|c| c == 'e' || c == 'E') {
-            num.push(exp);
-            if let Some(sign) = self.next_if(|c| c == '+' || c == '-') {
-                num.push(sign)
-            }
-            while let Some(c) = self.next_if(|c| c.is_digit(10)) {
-                num.push(c)
+    /// Scans a binary string literal of an even number of hex digits
+    fn scan_hex_string(&mut self) -> EasyDbResult<Option<Token<'a>>> {
+        if self.next_if(|c| c == 'x' || c == 'X').is_none() || self.next_if(|c| c == '\'').is_none()
+        {
+            return Ok(None);
+        }
+
+        // Scan up to the closing quote before validating, so that lexing
+        // resumes after the literal even if it is invalid
+        let start = self.pos();
+        self.next_while(|c| c != '\'');
+        let digits = &self.input[start..self.pos()];
+        if self.next_if(|c| c == '\'').is_none() {
+            return Err(EasyDbError::Parse("Unexpected end of hex string".into()));
+        }
+        if let Some(c) = digits.chars().find(|c| !c.is_ascii_hexdigit()) {
+            return Err(EasyDbError::Parse(format!("Invalid hex digit {}", c)));
+        }
+        if !digits.len().is_multiple_of(2) {
+            return Err(EasyDbError::Parse(
+                "Hex string has an odd number of digits".into(),
+            ));
+        }
+        Ok(Some(Token::HexString(digits)))
+    }
+
+    /// Scans a double-quoted identifier, which keeps its case and may be a
+    /// keyword. A doubled quote inside it stands for a literal quote, and is
+    /// the only case where the identifier can't be borrowed from the input.
+    fn scan_ident_quoted(&mut self) -> EasyDbResult<Option<Token<'a>>> {
+        if self.next_if(|c| c == '"').is_none() {
+            return Ok(None);
+        }
+
+        let start = self.pos();
+        let mut unescaped: Option<String> = None;
+        loop {
+            match self.iter.next() {
+                Some((i, '"')) => {
+                    if self.next_if(|c| c == '"').is_none() {
+                        let ident = match unescaped {
+                            Some(ident) => Cow::Owned(ident),
+                            None => Cow::Borrowed(&self.input[start..i]),
+                        };
+                        if ident.is_empty() {
+                            return Err(EasyDbError::Parse("Empty quoted identifier".into()));
+                        }
+                        return Ok(Some(Token::Ident(ident)));
+                    }
+                    unescaped
+                        .get_or_insert_with(|| self.input[start..i].to_string())
+                        .push('"')
+                }
+                Some((_, c)) => {
+                    if let Some(ident) = unescaped.as_mut() {
+                        ident.push(c)
+                    }
+                }
+                None => {
+                    return Err(EasyDbError::Parse(
+                        "Unexpected end of quoted identifier".into(),
+                    ))
+                }
             }
         }
+    }
 
-        Some(Token::Number(num))
+    /// Scans an identifier or keyword. Unquoted identifiers are case-insensitive,
+    /// so they're normalized to lowercase, which only allocates when the input
+    /// isn't lowercase already.
+    fn scan_ident(&mut self) -> Option<Token<'a>> {
+        let start = self.pos();
+        self.next_if(is_ident_start)?;
+        self.next_while(is_ident_continue);
+        let name = &self.input[start..self.pos()];
+
+        Keyword::from_str(name).map(Token::Keyword).or_else(|| {
+            Some(Token::Ident(if is_lowercase(name) {
+                Cow::Borrowed(name)
+            } else {
+                Cow::Owned(name.to_lowercase())
+            }))
+        })
     }
 
     /// Grabs the next single-character token if the tokenizer function returns one
-    fn next_if_token<F: Fn(char) -> Option<Token>>(&mut self, tokenizer: F) -> Option<Token> {
-        let token = self.iter.peek().and_then(|&c| tokenizer(c))?;
+    fn next_if_token<F: Fn(char) -> Option<Token<'a>>>(
+        &mut self,
+        tokenizer: F,
+    ) -> Option<Token<'a>> {
+        let token = self.iter.peek().and_then(|&(_, c)| tokenizer(c))?;
         self.iter.next();
         Some(token)
     }
 
-    fn scan_symbol(&mut self) -> Option<Token> {
+    fn scan_symbol(&mut self) -> Option<Token<'a>> {
         self.next_if_token(|c| match c {
             '.' => Some(Token::Period),
             '=' => Some(Token::Equal),
@@ -235,6 +682,8 @@ impl<'a> Lexer<'a> {
             ')' => Some(Token::CloseParen),
             ',' => Some(Token::Comma),
             ';' => Some(Token::Semicolon),
+            ':' => Some(Token::Colon),
+            '~' => Some(Token::Tilde),
             _ => None,
         })
         .map(|token| match token {
@@ -254,6 +703,22 @@ impl<'a> Lexer<'a> {
                     token
                 }
             }
+            Token::Minus => {
+                if self.next_if(|c| c == '>').is_none() {
+                    token
+                } else if self.next_if(|c| c == '>').is_some() {
+                    Token::DoubleArrow
+                } else {
+                    Token::Arrow
+                }
+            }
+            Token::Colon => {
+                if self.next_if(|c| c == ':').is_some() {
+                    Token::DoubleColon
+                } else {
+                    token
+                }
+            }
             Token::GreaterThan => {
                 if self.next_if(|c| c == '=').is_some() {
                     Token::GreaterThanOrEqual
@@ -0,0 +1,361 @@
+use super::ast::{
+    AlterTableAction, Column, ConflictAction, Expression, FromItem, InsertSource, Operation,
+    Statement,
+};
+
+/// Traverses a parsed statement. Every method defaults to walking the node's
+/// children, so implementors only override the nodes they care about and call
+/// the matching walk_* function to keep descending.
+pub trait Visitor {
+    fn visit_statement(&mut self, statement: &Statement) {
+        walk_statement(self, statement)
+    }
+
+    fn visit_from_item(&mut self, item: &FromItem) {
+        walk_from_item(self, item)
+    }
+
+    fn visit_column(&mut self, column: &Column) {
+        walk_column(self, column)
+    }
+
+    fn visit_expression(&mut self, expr: &Expression) {
+        walk_expression(self, expr)
+    }
+}
+
+/// Rewrites a parsed statement in place, e.g. to replace expressions. The
+/// mutable counterpart of Visitor.
+pub trait VisitorMut {
+    fn visit_statement_mut(&mut self, statement: &mut Statement) {
+        walk_statement_mut(self, statement)
+    }
+
+    fn visit_from_item_mut(&mut self, item: &mut FromItem) {
+        walk_from_item_mut(self, item)
+    }
+
+    fn visit_column_mut(&mut self, column: &mut Column) {
+        walk_column_mut(self, column)
+    }
+
+    fn visit_expression_mut(&mut self, expr: &mut Expression) {
+        walk_expression_mut(self, expr)
+    }
+}
+
+pub fn walk_statement<V: Visitor + ?Sized>(visitor: &mut V, statement: &Statement) {
+    match statement {
+        Statement::Explain { statement, .. } => visitor.visit_statement(statement),
+        Statement::CreateTable { columns, .. } => {
+            columns.iter().for_each(|c| visitor.visit_column(c))
+        }
+        Statement::AlterTable {
+            action: AlterTableAction::AddColumn(column),
+            ..
+        } => visitor.visit_column(column),
+        Statement::CreateView { query, .. } => visitor.visit_statement(query),
+        Statement::CreateEnum { .. }
+        | Statement::DropTable(_)
+        | Statement::DropView(_)
+        | Statement::AlterTable { .. }
+        | Statement::Truncate(_)
+        | Statement::Copy { .. }
+        | Statement::ShowStatus => {}
+        Statement::Insert {
+            source,
+            on_conflict,
+            ..
+        } => {
+            match source {
+                InsertSource::Values(rows) => rows
+                    .iter()
+                    .flatten()
+                    .for_each(|e| visitor.visit_expression(e)),
+                InsertSource::Select(query) => visitor.visit_statement(query),
+            }
+            if let Some(ConflictAction::Update { set, r#where }) =
+                on_conflict.as_ref().map(|c| &c.action)
+            {
+                set.values().for_each(|e| visitor.visit_expression(e));
+                r#where.iter().for_each(|e| visitor.visit_expression(e));
+            }
+        }
+        Statement::Select {
+            select,
+            from,
+            r#where,
+            group_by,
+            having,
+            order,
+            offset,
+            limit,
+        } => {
+            select.iter().for_each(|(e, _)| visitor.visit_expression(e));
+            from.iter().for_each(|i| visitor.visit_from_item(i));
+            r#where.iter().for_each(|e| visitor.visit_expression(e));
+            group_by.iter().for_each(|e| visitor.visit_expression(e));
+            having.iter().for_each(|e| visitor.visit_expression(e));
+            order.iter().for_each(|(e, _)| visitor.visit_expression(e));
+            offset.iter().for_each(|e| visitor.visit_expression(e));
+            limit.iter().for_each(|e| visitor.visit_expression(e));
+        }
+        Statement::SetOperation {
+            left,
+            right,
+            order,
+            offset,
+            limit,
+            ..
+        } => {
+            visitor.visit_statement(left);
+            visitor.visit_statement(right);
+            order.iter().for_each(|(e, _)| visitor.visit_expression(e));
+            offset.iter().for_each(|e| visitor.visit_expression(e));
+            limit.iter().for_each(|e| visitor.visit_expression(e));
+        }
+    }
+}
+
+pub fn walk_from_item<V: Visitor + ?Sized>(visitor: &mut V, item: &FromItem) {
+    match item {
+        FromItem::Table { .. } => {}
+        FromItem::Function { args, .. } => args.iter().for_each(|a| visitor.visit_expression(a)),
+        FromItem::Subquery { query, .. } => visitor.visit_statement(query),
+    }
+}
+
+pub fn walk_column<V: Visitor + ?Sized>(visitor: &mut V, column: &Column) {
+    if let Some(default) = &column.default {
+        visitor.visit_expression(default)
+    }
+}
+
+pub fn walk_expression<V: Visitor + ?Sized>(visitor: &mut V, expr: &Expression) {
+    match expr {
+        Expression::Field(_, _) | Expression::Literal(_) => {}
+        Expression::Function(_, args) => args.iter().for_each(|a| visitor.visit_expression(a)),
+        Expression::Window(_, args, window) => {
+            args.iter().for_each(|a| visitor.visit_expression(a));
+            window
+                .partition_by
+                .iter()
+                .for_each(|e| visitor.visit_expression(e));
+            window
+                .order_by
+                .iter()
+                .for_each(|(e, _)| visitor.visit_expression(e));
+        }
+        Expression::Operation(operation) => {
+            let (lhs, rhs) = operands(operation);
+            visitor.visit_expression(lhs);
+            if let Some(rhs) = rhs {
+                visitor.visit_expression(rhs);
+            }
+        }
+        Expression::Subquery(query) => visitor.visit_statement(query),
+    }
+}
+
+pub fn walk_statement_mut<V: VisitorMut + ?Sized>(visitor: &mut V, statement: &mut Statement) {
+    match statement {
+        Statement::Explain { statement, .. } => visitor.visit_statement_mut(statement),
+        Statement::CreateTable { columns, .. } => {
+            columns.iter_mut().for_each(|c| visitor.visit_column_mut(c))
+        }
+        Statement::AlterTable {
+            action: AlterTableAction::AddColumn(column),
+            ..
+        } => visitor.visit_column_mut(column),
+        Statement::CreateView { query, .. } => visitor.visit_statement_mut(query),
+        Statement::CreateEnum { .. }
+        | Statement::DropTable(_)
+        | Statement::DropView(_)
+        | Statement::AlterTable { .. }
+        | Statement::Truncate(_)
+        | Statement::Copy { .. }
+        | Statement::ShowStatus => {}
+        Statement::Insert {
+            source,
+            on_conflict,
+            ..
+        } => {
+            match source {
+                InsertSource::Values(rows) => rows
+                    .iter_mut()
+                    .flatten()
+                    .for_each(|e| visitor.visit_expression_mut(e)),
+                InsertSource::Select(query) => visitor.visit_statement_mut(query),
+            }
+            if let Some(ConflictAction::Update { set, r#where }) =
+                on_conflict.as_mut().map(|c| &mut c.action)
+            {
+                set.values_mut()
+                    .for_each(|e| visitor.visit_expression_mut(e));
+                r#where
+                    .iter_mut()
+                    .for_each(|e| visitor.visit_expression_mut(e));
+            }
+        }
+        Statement::Select {
+            select,
+            from,
+            r#where,
+            group_by,
+            having,
+            order,
+            offset,
+            limit,
+        } => {
+            select
+                .iter_mut()
+                .for_each(|(e, _)| visitor.visit_expression_mut(e));
+            from.iter_mut().for_each(|i| visitor.visit_from_item_mut(i));
+            r#where
+                .iter_mut()
+                .for_each(|e| visitor.visit_expression_mut(e));
+            group_by
+                .iter_mut()
+                .for_each(|e| visitor.visit_expression_mut(e));
+            having
+                .iter_mut()
+                .for_each(|e| visitor.visit_expression_mut(e));
+            order
+                .iter_mut()
+                .for_each(|(e, _)| visitor.visit_expression_mut(e));
+            offset
+                .iter_mut()
+                .for_each(|e| visitor.visit_expression_mut(e));
+            limit
+                .iter_mut()
+                .for_each(|e| visitor.visit_expression_mut(e));
+        }
+        Statement::SetOperation {
+            left,
+            right,
+            order,
+            offset,
+            limit,
+            ..
+        } => {
+            visitor.visit_statement_mut(left);
+            visitor.visit_statement_mut(right);
+            order
+                .iter_mut()
+                .for_each(|(e, _)| visitor.visit_expression_mut(e));
+            offset
+                .iter_mut()
+                .for_each(|e| visitor.visit_expression_mut(e));
+            limit
+                .iter_mut()
+                .for_each(|e| visitor.visit_expression_mut(e));
+        }
+    }
+}
+
+pub fn walk_from_item_mut<V: VisitorMut + ?Sized>(visitor: &mut V, item: &mut FromItem) {
+    match item {
+        FromItem::Table { .. } => {}
+        FromItem::Function { args, .. } => args
+            .iter_mut()
+            .for_each(|a| visitor.visit_expression_mut(a)),
+        FromItem::Subquery { query, .. } => visitor.visit_statement_mut(query),
+    }
+}
+
+pub fn walk_column_mut<V: VisitorMut + ?Sized>(visitor: &mut V, column: &mut Column) {
+    if let Some(default) = &mut column.default {
+        visitor.visit_expression_mut(default)
+    }
+}
+
+pub fn walk_expression_mut<V: VisitorMut + ?Sized>(visitor: &mut V, expr: &mut Expression) {
+    match expr {
+        Expression::Field(_, _) | Expression::Literal(_) => {}
+        Expression::Function(_, args) => args
+            .iter_mut()
+            .for_each(|a| visitor.visit_expression_mut(a)),
+        Expression::Window(_, args, window) => {
+            args.iter_mut()
+                .for_each(|a| visitor.visit_expression_mut(a));
+            window
+                .partition_by
+                .iter_mut()
+                .for_each(|e| visitor.visit_expression_mut(e));
+            window
+                .order_by
+                .iter_mut()
+                .for_each(|(e, _)| visitor.visit_expression_mut(e));
+        }
+        Expression::Operation(operation) => {
+            let (lhs, rhs) = operands_mut(operation);
+            visitor.visit_expression_mut(lhs);
+            if let Some(rhs) = rhs {
+                visitor.visit_expression_mut(rhs);
+            }
+        }
+        Expression::Subquery(query) => visitor.visit_statement_mut(query),
+    }
+}
+
+/// Returns the operands of an operation, the second one only for binary operators
+fn operands(operation: &Operation) -> (&Expression, Option<&Expression>) {
+    match operation {
+        Operation::Cast(expr, _)
+        | Operation::Not(expr)
+        | Operation::IsNull(expr)
+        | Operation::Assert(expr)
+        | Operation::Negate(expr) => (expr, None),
+        Operation::And(lhs, rhs)
+        | Operation::Or(lhs, rhs)
+        | Operation::Equal(lhs, rhs)
+        | Operation::GreaterThan(lhs, rhs)
+        | Operation::GreaterThanOrEqual(lhs, rhs)
+        | Operation::LessThan(lhs, rhs)
+        | Operation::LessThanOrEqual(lhs, rhs)
+        | Operation::NotEqual(lhs, rhs)
+        | Operation::Add(lhs, rhs)
+        | Operation::Divide(lhs, rhs)
+        | Operation::Exponentiate(lhs, rhs)
+        | Operation::Modulo(lhs, rhs)
+        | Operation::Multiply(lhs, rhs)
+        | Operation::Subtract(lhs, rhs)
+        | Operation::Like(lhs, rhs, _)
+        | Operation::ILike(lhs, rhs, _)
+        | Operation::Regex(lhs, rhs)
+        | Operation::IsDistinctFrom(lhs, rhs)
+        | Operation::JsonGet(lhs, rhs)
+        | Operation::JsonGetText(lhs, rhs) => (lhs, Some(rhs)),
+    }
+}
+
+/// The mutable counterpart of operands()
+fn operands_mut(operation: &mut Operation) -> (&mut Expression, Option<&mut Expression>) {
+    match operation {
+        Operation::Cast(expr, _)
+        | Operation::Not(expr)
+        | Operation::IsNull(expr)
+        | Operation::Assert(expr)
+        | Operation::Negate(expr) => (expr, None),
+        Operation::And(lhs, rhs)
+        | Operation::Or(lhs, rhs)
+        | Operation::Equal(lhs, rhs)
+        | Operation::GreaterThan(lhs, rhs)
+        | Operation::GreaterThanOrEqual(lhs, rhs)
+        | Operation::LessThan(lhs, rhs)
+        | Operation::LessThanOrEqual(lhs, rhs)
+        | Operation::NotEqual(lhs, rhs)
+        | Operation::Add(lhs, rhs)
+        | Operation::Divide(lhs, rhs)
+        | Operation::Exponentiate(lhs, rhs)
+        | Operation::Modulo(lhs, rhs)
+        | Operation::Multiply(lhs, rhs)
+        | Operation::Subtract(lhs, rhs)
+        | Operation::Like(lhs, rhs, _)
+        | Operation::ILike(lhs, rhs, _)
+        | Operation::Regex(lhs, rhs)
+        | Operation::IsDistinctFrom(lhs, rhs)
+        | Operation::JsonGet(lhs, rhs)
+        | Operation::JsonGetText(lhs, rhs) => (lhs, Some(rhs)),
+    }
+}
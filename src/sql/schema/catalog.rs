@@ -0,0 +1,543 @@
+use super::super::types::EnumType;
+use super::{Table, View};
+use crate::error::{EasyDbError, EasyDbResult};
+use crate::storage::keycode::write_bytes;
+use crate::storage::{Compression, Engine, Log, Status, SyncPolicy};
+
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::Path;
+
+/// Stores table, view and enum type definitions. Tables and views share a
+/// namespace, and types have their own.
+pub trait Catalog {
+    /// Validates and creates a table, failing if one with the same name
+    /// exists
+    fn create_table(&mut self, table: Table) -> EasyDbResult<()>;
+
+    /// Replaces the definition of an existing table, e.g. after adding or
+    /// dropping columns. The new definition is validated, and so are the
+    /// tables referencing it.
+    fn update_table(&mut self, table: Table) -> EasyDbResult<()>;
+
+    /// Deletes a table, failing if it doesn't exist or another table's
+    /// foreign key references it
+    fn delete_table(&mut self, name: &str) -> EasyDbResult<()>;
+
+    /// Reads a table, if it exists
+    fn read_table(&self, name: &str) -> EasyDbResult<Option<Table>>;
+
+    /// Lists all tables, ordered by name
+    fn scan_tables(&self) -> EasyDbResult<Vec<Table>>;
+
+    /// Reads a table, failing if it doesn't exist
+    fn must_read_table(&self, name: &str) -> EasyDbResult<Table> {
+        self.read_table(name)?
+            .ok_or_else(|| EasyDbError::Value(format!("Table {} does not exist", name)))
+    }
+
+    /// Creates a view, failing if a table or view with the same name exists
+    fn create_view(&mut self, view: View) -> EasyDbResult<()>;
+
+    /// Deletes a view, failing if it doesn't exist
+    fn delete_view(&mut self, name: &str) -> EasyDbResult<()>;
+
+    /// Reads a view, if it exists
+    fn read_view(&self, name: &str) -> EasyDbResult<Option<View>>;
+
+    /// Lists all views, ordered by name
+    fn scan_views(&self) -> EasyDbResult<Vec<View>>;
+
+    /// Creates an enum type, failing if one with the same name exists
+    fn create_enum(&mut self, datatype: EnumType) -> EasyDbResult<()>;
+
+    /// Reads an enum type, if it exists
+    fn read_enum(&self, name: &str) -> EasyDbResult<Option<EnumType>>;
+
+    /// Reads an enum type, failing if it doesn't exist
+    fn must_read_enum(&self, name: &str) -> EasyDbResult<EnumType> {
+        self.read_enum(name)?
+            .ok_or_else(|| EasyDbError::Value(format!("Type {} does not exist", name)))
+    }
+
+    /// Returns the next value of a table's sequence, which generates the
+    /// values of its AUTOINCREMENT column starting from 1. Values are never
+    /// handed out twice, even if the rows using them are deleted.
+    fn next_sequence(&mut self, table: &str) -> EasyDbResult<i64>;
+
+    /// Records that a schema migration was applied
+    fn record_migration(&mut self, version: u64, name: &str) -> EasyDbResult<()>;
+
+    /// Lists the applied schema migrations as (version, name), ordered by
+    /// version
+    fn scan_migrations(&self) -> EasyDbResult<Vec<(u64, String)>>;
+
+    /// Copies the whole catalog into memory, e.g. to try out changes on
+    fn snapshot(&self) -> EasyDbResult<MemoryCatalog>;
+
+    /// Replaces the whole catalog with a snapshot, as a single change
+    fn restore(&mut self, snapshot: MemoryCatalog) -> EasyDbResult<()>;
+}
+
+/// A catalog held in memory, which is lost on exit
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct MemoryCatalog {
+    tables: BTreeMap<String, Table>,
+    views: BTreeMap<String, View>,
+    enums: BTreeMap<String, EnumType>,
+    /// The last value handed out by each table's sequence. EngineCatalog
+    /// stores these under their own keys instead.
+    sequences: BTreeMap<String, i64>,
+    /// The names of the applied schema migrations, by version
+    migrations: BTreeMap<u64, String>,
+}
+
+impl MemoryCatalog {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Checks that a table exists and has a sequence, i.e. an AUTOINCREMENT
+    /// column
+    fn check_sequence(&self, table: &str) -> EasyDbResult<()> {
+        if !self
+            .must_read_table(table)?
+            .columns
+            .iter()
+            .any(|c| c.autoincrement)
+        {
+            return Err(EasyDbError::Value(format!(
+                "Table {} has no AUTOINCREMENT column",
+                table
+            )));
+        }
+        Ok(())
+    }
+
+    /// Checks that no table or view has the given name
+    fn check_unused(&self, name: &str) -> EasyDbResult<()> {
+        match (
+            self.tables.contains_key(name),
+            self.views.contains_key(name),
+        ) {
+            (true, _) => Err(EasyDbError::Value(format!("Table {} already exists", name))),
+            (_, true) => Err(EasyDbError::Value(format!("View {} already exists", name))),
+            (false, false) => Ok(()),
+        }
+    }
+}
+
+impl Catalog for MemoryCatalog {
+    fn create_table(&mut self, table: Table) -> EasyDbResult<()> {
+        table.validate(self)?;
+        self.check_unused(&table.name)?;
+        self.tables.insert(table.name.clone(), table);
+        Ok(())
+    }
+
+    fn update_table(&mut self, table: Table) -> EasyDbResult<()> {
+        let old = self.must_read_table(&table.name)?;
+        table.validate(self)?;
+        let name = table.name.clone();
+        self.tables.insert(name.clone(), table);
+        let result = self
+            .tables
+            .values()
+            .filter(|t| t.name != name && t.references(&name))
+            .try_for_each(|t| t.validate(self));
+        if result.is_err() {
+            self.tables.insert(name, old);
+        }
+        result
+    }
+
+    fn delete_table(&mut self, name: &str) -> EasyDbResult<()> {
+        if let Some((table, foreign_key)) = self
+            .tables
+            .values()
+            .filter(|t| t.name != name)
+            .find_map(|t| Some((t, t.foreign_keys.iter().find(|fk| fk.table == name)?)))
+        {
+            return Err(EasyDbError::Value(format!(
+                "Can't drop table {}, foreign key ({}) of table {} references it",
+                name,
+                foreign_key.columns.join(", "),
+                table.name
+            )));
+        }
+        match self.tables.remove(name) {
+            Some(_) => {
+                self.sequences.remove(name);
+                Ok(())
+            }
+            None => Err(EasyDbError::Value(format!("Table {} does not exist", name))),
+        }
+    }
+
+    fn read_table(&self, name: &str) -> EasyDbResult<Option<Table>> {
+        Ok(self.tables.get(name).cloned())
+    }
+
+    fn scan_tables(&self) -> EasyDbResult<Vec<Table>> {
+        Ok(self.tables.values().cloned().collect())
+    }
+
+    fn create_view(&mut self, view: View) -> EasyDbResult<()> {
+        self.check_unused(&view.name)?;
+        self.views.insert(view.name.clone(), view);
+        Ok(())
+    }
+
+    fn delete_view(&mut self, name: &str) -> EasyDbResult<()> {
+        match self.views.remove(name) {
+            Some(_) => Ok(()),
+            None => Err(EasyDbError::Value(format!("View {} does not exist", name))),
+        }
+    }
+
+    fn read_view(&self, name: &str) -> EasyDbResult<Option<View>> {
+        Ok(self.views.get(name).cloned())
+    }
+
+    fn scan_views(&self) -> EasyDbResult<Vec<View>> {
+        Ok(self.views.values().cloned().collect())
+    }
+
+    fn create_enum(&mut self, datatype: EnumType) -> EasyDbResult<()> {
+        if self.enums.contains_key(&datatype.name) {
+            return Err(EasyDbError::Value(format!(
+                "Type {} already exists",
+                datatype.name
+            )));
+        }
+        if datatype.labels.is_empty() {
+            return Err(EasyDbError::Value(format!(
+                "Enum type {} has no labels",
+                datatype.name
+            )));
+        }
+        if let Some((_, label)) = datatype
+            .labels
+            .iter()
+            .enumerate()
+            .find(|(i, label)| datatype.labels[..*i].contains(label))
+        {
+            return Err(EasyDbError::Value(format!(
+                "Duplicate label '{}' in enum type {}",
+                label, datatype.name
+            )));
+        }
+        self.enums.insert(datatype.name.clone(), datatype);
+        Ok(())
+    }
+
+    fn read_enum(&self, name: &str) -> EasyDbResult<Option<EnumType>> {
+        Ok(self.enums.get(name).cloned())
+    }
+
+    fn next_sequence(&mut self, table: &str) -> EasyDbResult<i64> {
+        self.check_sequence(table)?;
+        let value = self.sequences.entry(table.to_string()).or_insert(0);
+        *value = next_value(table, *value)?;
+        Ok(*value)
+    }
+
+    fn record_migration(&mut self, version: u64, name: &str) -> EasyDbResult<()> {
+        if self.migrations.contains_key(&version) {
+            return Err(EasyDbError::Value(format!(
+                "Migration {} was already applied",
+                version
+            )));
+        }
+        self.migrations.insert(version, name.to_string());
+        Ok(())
+    }
+
+    fn scan_migrations(&self) -> EasyDbResult<Vec<(u64, String)>> {
+        Ok(self
+            .migrations
+            .iter()
+            .map(|(version, name)| (*version, name.clone()))
+            .collect())
+    }
+
+    fn snapshot(&self) -> EasyDbResult<MemoryCatalog> {
+        Ok(self.clone())
+    }
+
+    fn restore(&mut self, snapshot: MemoryCatalog) -> EasyDbResult<()> {
+        *self = snapshot;
+        Ok(())
+    }
+}
+
+/// Returns the value a sequence hands out after the given one
+fn next_value(table: &str, value: i64) -> EasyDbResult<i64> {
+    value
+        .checked_add(1)
+        .ok_or_else(|| EasyDbError::Value(format!("Sequence of table {} is exhausted", table)))
+}
+
+/// A catalog persisted in a storage engine, so that schemas survive
+/// restarts. The whole catalog is stored under a single key and rewritten
+/// on every change, so a change is saved either entirely or not at all.
+/// Sequences change with every generated value, so each is stored under
+/// its own key, sequence/{table}, encoded with keycode.
+#[derive(Debug)]
+pub struct EngineCatalog<E: Engine> {
+    engine: E,
+    memory: MemoryCatalog,
+}
+
+/// A catalog persisted to a log file
+pub type FileCatalog = EngineCatalog<Log>;
+
+/// The engine key the catalog is stored under
+const CATALOG_KEY: &[u8] = b"catalog";
+
+impl<E: Engine> EngineCatalog<E> {
+    /// Loads the catalog stored in the engine, or starts an empty one
+    pub fn new(mut engine: E) -> EasyDbResult<Self> {
+        let memory = match engine.get(CATALOG_KEY)? {
+            Some(bytes) => bincode::deserialize(&bytes)?,
+            None => MemoryCatalog::new(),
+        };
+        Ok(Self { engine, memory })
+    }
+
+    /// Reports the statistics of the engine the catalog is stored in
+    pub fn status(&mut self) -> EasyDbResult<Status> {
+        self.engine.status()
+    }
+
+    /// Applies a change and saves the catalog, undoing the change in memory
+    /// if it can't be saved
+    fn write<T>(
+        &mut self,
+        change: impl FnOnce(&mut MemoryCatalog) -> EasyDbResult<T>,
+    ) -> EasyDbResult<T> {
+        let old = self.memory.clone();
+        let result = change(&mut self.memory)?;
+        self.save().inspect_err(|_| self.memory = old)?;
+        Ok(result)
+    }
+
+    fn save(&mut self) -> EasyDbResult<()> {
+        let bytes = bincode::serialize(&self.memory)?;
+        self.engine.set(CATALOG_KEY, bytes)?;
+        self.engine.flush()
+    }
+
+    /// Removes a table's sequence, e.g. one left behind by a crash while
+    /// dropping the table
+    fn delete_sequence(&mut self, table: &str) -> EasyDbResult<()> {
+        self.engine.delete(&sequence_key(table))?;
+        self.engine.flush()
+    }
+}
+
+/// The engine key a table's sequence is stored under
+fn sequence_key(table: &str) -> Vec<u8> {
+    let mut key = Vec::new();
+    write_bytes(&mut key, b"sequence");
+    write_bytes(&mut key, table.as_bytes());
+    key
+}
+
+impl FileCatalog {
+    /// Opens the catalog stored at the given path, creating an empty one if
+    /// the file doesn't exist. Every change is synced to disk.
+    pub fn open(path: impl AsRef<Path>) -> EasyDbResult<Self> {
+        Self::new(Log::open(
+            path,
+            SyncPolicy::Always,
+            Compression::None,
+            None,
+        )?)
+    }
+}
+
+impl<E: Engine> Catalog for EngineCatalog<E> {
+    fn create_table(&mut self, table: Table) -> EasyDbResult<()> {
+        let name = table.name.clone();
+        self.write(|memory| memory.create_table(table))?;
+        self.delete_sequence(&name)
+    }
+
+    fn update_table(&mut self, table: Table) -> EasyDbResult<()> {
+        self.write(|memory| memory.update_table(table))
+    }
+
+    fn delete_table(&mut self, name: &str) -> EasyDbResult<()> {
+        self.write(|memory| memory.delete_table(name))?;
+        self.delete_sequence(name)
+    }
+
+    fn read_table(&self, name: &str) -> EasyDbResult<Option<Table>> {
+        self.memory.read_table(name)
+    }
+
+    fn scan_tables(&self) -> EasyDbResult<Vec<Table>> {
+        self.memory.scan_tables()
+    }
+
+    fn create_view(&mut self, view: View) -> EasyDbResult<()> {
+        self.write(|memory| memory.create_view(view))
+    }
+
+    fn delete_view(&mut self, name: &str) -> EasyDbResult<()> {
+        self.write(|memory| memory.delete_view(name))
+    }
+
+    fn read_view(&self, name: &str) -> EasyDbResult<Option<View>> {
+        self.memory.read_view(name)
+    }
+
+    fn scan_views(&self) -> EasyDbResult<Vec<View>> {
+        self.memory.scan_views()
+    }
+
+    fn create_enum(&mut self, datatype: EnumType) -> EasyDbResult<()> {
+        self.write(|memory| memory.create_enum(datatype))
+    }
+
+    fn read_enum(&self, name: &str) -> EasyDbResult<Option<EnumType>> {
+        self.memory.read_enum(name)
+    }
+
+    fn next_sequence(&mut self, table: &str) -> EasyDbResult<i64> {
+        self.memory.check_sequence(table)?;
+        let key = sequence_key(table);
+        let value = match self.engine.get(&key)? {
+            Some(bytes) => bincode::deserialize(&bytes)?,
+            None => 0,
+        };
+        let value = next_value(table, value)?;
+        self.engine.set(&key, bincode::serialize(&value)?)?;
+        self.engine.flush()?;
+        Ok(value)
+    }
+
+    fn record_migration(&mut self, version: u64, name: &str) -> EasyDbResult<()> {
+        self.write(|memory| memory.record_migration(version, name))
+    }
+
+    fn scan_migrations(&self) -> EasyDbResult<Vec<(u64, String)>> {
+        self.memory.scan_migrations()
+    }
+
+    fn snapshot(&self) -> EasyDbResult<MemoryCatalog> {
+        self.memory.snapshot()
+    }
+
+    fn restore(&mut self, snapshot: MemoryCatalog) -> EasyDbResult<()> {
+        self.write(|memory| memory.restore(snapshot))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::super::parser::ast::{Parser, Statement};
+    use super::*;
+    use crate::storage::Memory;
+
+    /// Builds a table definition from a CREATE TABLE statement
+    fn table(sql: &str) -> Table {
+        match Parser::new(sql).parse().unwrap() {
+            Statement::CreateTable {
+                name,
+                columns,
+                constraints,
+            } => Table::from_ast(&name, &columns, &constraints).unwrap(),
+            statement => panic!("expected CREATE TABLE, got {}", statement),
+        }
+    }
+
+    #[test]
+    fn sequences() -> EasyDbResult<()> {
+        let mut catalog = EngineCatalog::new(Memory::new())?;
+        catalog.create_table(table("CREATE TABLE t (id INT PRIMARY KEY AUTOINCREMENT)"))?;
+        catalog.create_table(table("CREATE TABLE u (id INT PRIMARY KEY)"))?;
+        let blob = catalog.engine.get(CATALOG_KEY)?;
+        assert_eq!(catalog.next_sequence("t")?, 1);
+        assert_eq!(catalog.next_sequence("t")?, 2);
+        assert!(catalog.next_sequence("u").is_err());
+        assert!(catalog.next_sequence("missing").is_err());
+        // Sequences don't rewrite the catalog
+        assert_eq!(catalog.engine.get(CATALOG_KEY)?, blob);
+
+        let mut catalog = EngineCatalog::new(catalog.engine)?;
+        assert_eq!(catalog.next_sequence("t")?, 3);
+        catalog.delete_table("t")?;
+        catalog.create_table(table("CREATE TABLE t (id INT PRIMARY KEY AUTOINCREMENT)"))?;
+        assert_eq!(catalog.next_sequence("t")?, 1);
+        Ok(())
+    }
+
+    #[test]
+    fn delete_referenced_table() -> EasyDbResult<()> {
+        let mut catalog = MemoryCatalog::new();
+        catalog.create_table(table("CREATE TABLE p (id INT PRIMARY KEY)"))?;
+        catalog.create_table(table(
+            "CREATE TABLE c (id INT PRIMARY KEY, parent INT REFERENCES c, p_id INT REFERENCES p)",
+        ))?;
+        let err = catalog.delete_table("p").unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            EasyDbError::Value(
+                "Can't drop table p, foreign key (p_id) of table c references it".into()
+            )
+            .to_string()
+        );
+        assert!(catalog.read_table("p")?.is_some());
+        // A table may reference itself
+        catalog.delete_table("c")?;
+        catalog.delete_table("p")?;
+        assert!(catalog.delete_table("p").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn memory_sequences() -> EasyDbResult<()> {
+        let mut catalog = MemoryCatalog::new();
+        catalog.create_table(table("CREATE TABLE t (id INT PRIMARY KEY AUTOINCREMENT)"))?;
+        assert_eq!(catalog.next_sequence("t")?, 1);
+        assert_eq!(catalog.next_sequence("t")?, 2);
+        catalog.delete_table("t")?;
+        assert!(catalog.next_sequence("t").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn enums() -> EasyDbResult<()> {
+        let mood = EnumType {
+            name: "mood".into(),
+            labels: vec!["sad".into(), "ok".into(), "happy".into()],
+        };
+        let mut catalog = EngineCatalog::new(Memory::new())?;
+        let sql = "CREATE TABLE t (id INT PRIMARY KEY, m mood DEFAULT 'ok')";
+        let err = catalog.create_table(table(sql)).unwrap_err();
+        assert!(
+            err.to_string().contains("Type mood does not exist"),
+            "{}",
+            err
+        );
+        catalog.create_enum(mood.clone())?;
+        assert!(catalog.create_enum(mood.clone()).is_err());
+        let mut duplicate = mood.clone();
+        duplicate.name = "other".into();
+        duplicate.labels.push("ok".into());
+        assert!(catalog.create_enum(duplicate).is_err());
+        catalog.create_table(table(sql))?;
+        assert!(catalog
+            .create_table(table(
+                "CREATE TABLE u (id INT PRIMARY KEY, m mood DEFAULT 'glad')"
+            ))
+            .is_err());
+
+        let catalog = EngineCatalog::new(catalog.engine)?;
+        assert_eq!(catalog.read_enum("mood")?, Some(mood));
+        assert_eq!(catalog.read_enum("other")?, None);
+        Ok(())
+    }
+}
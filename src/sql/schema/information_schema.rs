@@ -0,0 +1,141 @@
+use super::super::types::{DataType, Value};
+use super::Catalog;
+use crate::error::{EasyDbError, EasyDbResult};
+
+/// The schema the system tables are qualified with, as in
+/// `SELECT * FROM information_schema.tables`
+pub const SCHEMA: &str = "information_schema";
+
+/// The contents of a system table, generated from the catalog
+#[derive(Clone, Debug, PartialEq)]
+pub struct SystemTable {
+    pub columns: Vec<String>,
+    pub rows: Vec<Vec<Value>>,
+}
+
+/// Generates one of the information_schema tables, or returns None if there
+/// is no such table:
+///
+/// * tables: table_name, table_type ('BASE TABLE' or 'VIEW')
+/// * columns: table_name, column_name, ordinal_position, data_type,
+///   is_nullable ('YES' or 'NO'), column_default (as SQL), collation_name
+/// * indexes: table_name, index_name, column_name, ordinal_position,
+///   is_unique, is_primary, with a row per indexed column
+/// * migrations: version, name, for the applied schema migrations
+pub fn scan(catalog: &dyn Catalog, name: &str) -> EasyDbResult<Option<SystemTable>> {
+    let (columns, rows): (&[&str], _) = match name {
+        "tables" => (&["table_name", "table_type"], scan_tables(catalog)?),
+        "columns" => (
+            &[
+                "table_name",
+                "column_name",
+                "ordinal_position",
+                "data_type",
+                "is_nullable",
+                "column_default",
+                "collation_name",
+            ],
+            scan_columns(catalog)?,
+        ),
+        "indexes" => (
+            &[
+                "table_name",
+                "index_name",
+                "column_name",
+                "ordinal_position",
+                "is_unique",
+                "is_primary",
+            ],
+            scan_indexes(catalog)?,
+        ),
+        "migrations" => (&["version", "name"], scan_migrations(catalog)?),
+        _ => return Ok(None),
+    };
+    Ok(Some(SystemTable {
+        columns: columns.iter().map(|c| c.to_string()).collect(),
+        rows,
+    }))
+}
+
+fn scan_tables(catalog: &dyn Catalog) -> EasyDbResult<Vec<Vec<Value>>> {
+    let tables = catalog
+        .scan_tables()?
+        .into_iter()
+        .map(|t| (t.name, "BASE TABLE"));
+    let views = catalog.scan_views()?.into_iter().map(|v| (v.name, "VIEW"));
+    let mut rows: Vec<_> = tables.chain(views).collect();
+    rows.sort();
+    Ok(rows
+        .into_iter()
+        .map(|(name, kind)| vec![Value::String(name), Value::String(kind.into())])
+        .collect())
+}
+
+fn scan_columns(catalog: &dyn Catalog) -> EasyDbResult<Vec<Vec<Value>>> {
+    let mut rows = Vec::new();
+    for table in catalog.scan_tables()? {
+        for (i, column) in table.columns.iter().enumerate() {
+            rows.push(vec![
+                Value::String(table.name.clone()),
+                Value::String(column.name.clone()),
+                Value::Integer(i as i64 + 1),
+                Value::String(column.datatype.to_string()),
+                Value::String(if column.nullable { "YES" } else { "NO" }.into()),
+                match &column.default {
+                    Some(default) => Value::String(sql_literal(default)),
+                    None => Value::Null,
+                },
+                match column.datatype {
+                    DataType::String(_) => Value::String(column.collation.to_string()),
+                    _ => Value::Null,
+                },
+            ]);
+        }
+    }
+    Ok(rows)
+}
+
+fn scan_indexes(catalog: &dyn Catalog) -> EasyDbResult<Vec<Vec<Value>>> {
+    let mut rows = Vec::new();
+    for table in catalog.scan_tables()? {
+        for index in table.indexes() {
+            for (i, column) in index.columns.into_iter().enumerate() {
+                rows.push(vec![
+                    Value::String(table.name.clone()),
+                    Value::String(index.name.clone()),
+                    Value::String(column),
+                    Value::Integer(i as i64 + 1),
+                    Value::Boolean(index.unique),
+                    Value::Boolean(index.primary),
+                ]);
+            }
+        }
+    }
+    Ok(rows)
+}
+
+fn scan_migrations(catalog: &dyn Catalog) -> EasyDbResult<Vec<Vec<Value>>> {
+    catalog
+        .scan_migrations()?
+        .into_iter()
+        .map(|(version, name)| {
+            let version = i64::try_from(version).map_err(|_| {
+                EasyDbError::Value(format!("Migration version {} is out of range", version))
+            })?;
+            Ok(vec![Value::Integer(version), Value::String(name)])
+        })
+        .collect()
+}
+
+/// Formats a value as the SQL literal it would be written as
+fn sql_literal(value: &Value) -> String {
+    match value {
+        Value::String(s) | Value::Json(s) => format!("'{}'", s.replace('\'', "''")),
+        Value::Bytes(bytes) => {
+            let hex: String = bytes.iter().map(|b| format!("{:02X}", b)).collect();
+            format!("x'{}'", hex)
+        }
+        Value::Interval(interval) => format!("INTERVAL '{}'", interval),
+        value => value.to_string(),
+    }
+}
@@ -0,0 +1,116 @@
+use super::super::parser::ast::{Parser, Statement};
+use super::{Catalog, MemoryCatalog, Table, View};
+use crate::error::{EasyDbError, EasyDbResult};
+
+/// A schema migration: a script of schema changes, identified by a version
+#[derive(Clone, Debug, PartialEq)]
+pub struct Migration {
+    pub version: u64,
+    pub name: String,
+    pub sql: String,
+}
+
+/// Brings a catalog's schema up to date by applying, in version order, the
+/// migrations that haven't been applied to it yet. Applied versions are
+/// recorded in the catalog, and listed by information_schema.migrations.
+///
+/// The pending migrations are applied to a snapshot of the catalog, which
+/// then replaces it, so either all of them are applied or none are. Scripts
+/// may only change the schema: CREATE/DROP TABLE, ALTER TABLE ADD/DROP
+/// COLUMN and CREATE/DROP VIEW. Only the catalog is changed, so tables
+/// altered by a migration must not have rows yet; execution::alter
+/// rewrites the rows of tables altered outside migrations.
+#[derive(Clone, Debug)]
+pub struct Migrator {
+    migrations: Vec<Migration>,
+}
+
+impl Migrator {
+    /// Creates a migrator, failing if two migrations share a version
+    pub fn new(mut migrations: Vec<Migration>) -> EasyDbResult<Self> {
+        migrations.sort_by_key(|m| m.version);
+        if let Some(pair) = migrations.windows(2).find(|p| p[0].version == p[1].version) {
+            return Err(EasyDbError::Value(format!(
+                "Migrations {} and {} share version {}",
+                pair[0].name, pair[1].name, pair[0].version
+            )));
+        }
+        Ok(Self { migrations })
+    }
+
+    /// Lists the migrations not yet applied to the catalog, in version order
+    pub fn pending(&self, catalog: &dyn Catalog) -> EasyDbResult<Vec<&Migration>> {
+        let applied = catalog.scan_migrations()?;
+        Ok(self
+            .migrations
+            .iter()
+            .filter(|m| !applied.iter().any(|(version, _)| *version == m.version))
+            .collect())
+    }
+
+    /// Applies the pending migrations, returning their versions
+    pub fn migrate(&self, catalog: &mut dyn Catalog) -> EasyDbResult<Vec<u64>> {
+        let (snapshot, versions) = self.apply_pending(catalog)?;
+        if !versions.is_empty() {
+            catalog.restore(snapshot)?;
+        }
+        Ok(versions)
+    }
+
+    /// Checks that the pending migrations apply cleanly, returning their
+    /// versions, without changing the catalog
+    pub fn dry_run(&self, catalog: &dyn Catalog) -> EasyDbResult<Vec<u64>> {
+        Ok(self.apply_pending(catalog)?.1)
+    }
+
+    /// Applies the pending migrations to a snapshot of the catalog
+    fn apply_pending(&self, catalog: &dyn Catalog) -> EasyDbResult<(MemoryCatalog, Vec<u64>)> {
+        let mut snapshot = catalog.snapshot()?;
+        let mut versions = Vec::new();
+        for migration in self.pending(catalog)? {
+            apply(&mut snapshot, migration).map_err(|err| {
+                let message = format!(
+                    "Migration {} ({}) failed: {}",
+                    migration.version, migration.name, err
+                );
+                match err {
+                    EasyDbError::Internal(_) => EasyDbError::Internal(message),
+                    EasyDbError::Parse(_) => EasyDbError::Parse(message),
+                    EasyDbError::Value(_) => EasyDbError::Value(message),
+                }
+            })?;
+            versions.push(migration.version);
+        }
+        Ok((snapshot, versions))
+    }
+}
+
+/// Applies a migration's statements and records it
+fn apply(catalog: &mut MemoryCatalog, migration: &Migration) -> EasyDbResult<()> {
+    for statement in Parser::new(&migration.sql).parse_all()? {
+        match statement {
+            Statement::CreateTable {
+                name,
+                columns,
+                constraints,
+            } => catalog.create_table(Table::from_ast(&name, &columns, &constraints)?)?,
+            Statement::DropTable(name) => catalog.delete_table(&name)?,
+            Statement::AlterTable { name, action } => {
+                let mut table = catalog.must_read_table(&name)?;
+                table.alter(&action)?;
+                catalog.update_table(table)?
+            }
+            Statement::CreateView { name, query } => {
+                catalog.create_view(View::new(&name, &query))?
+            }
+            Statement::DropView(name) => catalog.delete_view(&name)?,
+            statement => {
+                return Err(EasyDbError::Value(format!(
+                    "Only schema changes can be migrated, found {}",
+                    statement
+                )))
+            }
+        }
+    }
+    catalog.record_migration(migration.version, &migration.name)
+}
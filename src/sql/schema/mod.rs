@@ -0,0 +1,512 @@
+pub mod catalog;
+pub mod information_schema;
+pub mod migrate;
+
+pub use catalog::{Catalog, EngineCatalog, FileCatalog, MemoryCatalog};
+pub use migrate::{Migration, Migrator};
+
+use std::collections::HashSet;
+
+use super::execution::expr::evaluate;
+use super::parser::ast;
+use super::types::coerce::{self, LengthPolicy};
+use super::types::{Collation, DataType, Value};
+use crate::error::{EasyDbError, EasyDbResult};
+
+use serde::{Deserialize, Serialize};
+
+/// A table definition, as stored in the catalog. Column-level PRIMARY KEY,
+/// UNIQUE and REFERENCES constraints are folded into the table-level ones.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Table {
+    pub name: String,
+    pub columns: Vec<Column>,
+    /// The primary key column names, in key order
+    pub primary_key: Vec<String>,
+    /// Sets of columns whose values must be unique together
+    pub unique: Vec<Vec<String>>,
+    pub foreign_keys: Vec<ForeignKey>,
+}
+
+/// A column definition
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Column {
+    pub name: String,
+    pub datatype: DataType,
+    pub nullable: bool,
+    /// The default value, already converted to the column's type. Enum
+    /// types are defined in the catalog, so an enum column's default is
+    /// converted once the table is validated against it.
+    pub default: Option<Value>,
+    pub index: bool,
+    /// Whether values are generated from the table's sequence, see
+    /// Catalog::next_sequence()
+    pub autoincrement: bool,
+    pub collation: Collation,
+}
+
+/// A foreign key, referencing columns of another table
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ForeignKey {
+    pub columns: Vec<String>,
+    pub table: String,
+    /// The referenced columns, or empty for the referenced table's primary key
+    pub references: Vec<String>,
+    pub on_delete: ReferentialAction,
+}
+
+/// An index over some of a table's columns, implied by its primary key, a
+/// unique constraint or an INDEX column
+#[derive(Clone, Debug, PartialEq)]
+pub struct Index {
+    pub name: String,
+    pub columns: Vec<String>,
+    pub unique: bool,
+    pub primary: bool,
+}
+
+/// A view definition. The query is kept as SQL text, which the parser's
+/// output round-trips through, and is expanded into queries reading from
+/// the view by optimizer::views::expand_views().
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct View {
+    pub name: String,
+    pub query: String,
+}
+
+impl View {
+    pub fn new(name: &str, query: &ast::Statement) -> Self {
+        Self {
+            name: name.to_string(),
+            query: query.to_string(),
+        }
+    }
+}
+
+/// What happens to referencing rows when a referenced row is deleted
+#[derive(Clone, Copy, Debug, Default, Hash, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ReferentialAction {
+    /// Block the deletion, from ON DELETE RESTRICT or NO ACTION
+    #[default]
+    Restrict,
+    /// Delete the referencing rows too
+    Cascade,
+    /// Set the referencing columns to NULL
+    SetNull,
+}
+
+impl std::fmt::Display for ReferentialAction {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::Restrict => "RESTRICT",
+            Self::Cascade => "CASCADE",
+            Self::SetNull => "SET NULL",
+        })
+    }
+}
+
+impl Table {
+    /// Builds a table definition from a parsed CREATE TABLE statement.
+    /// Column defaults must be constant, and are evaluated here. Primary
+    /// key columns are NOT NULL unless declared otherwise.
+    ///
+    /// Only one PRIMARY KEY may be declared, since once folded together
+    /// several of them would look like a composite key. The rest of the
+    /// definition is checked by validate().
+    pub fn from_ast(
+        name: &str,
+        columns: &[ast::Column],
+        constraints: &[ast::Constraint],
+    ) -> EasyDbResult<Self> {
+        let mut table = Self {
+            name: name.to_string(),
+            columns: Vec::new(),
+            primary_key: Vec::new(),
+            unique: Vec::new(),
+            foreign_keys: Vec::new(),
+        };
+        let mut primary_keys = constraints
+            .iter()
+            .filter(|c| matches!(c, ast::Constraint::PrimaryKey(_)))
+            .count();
+        for column in columns {
+            if column.primary_key {
+                primary_keys += 1;
+                if primary_keys > 1 {
+                    return Err(EasyDbError::Value(format!(
+                        "Column {} can't be a primary key, table {} already has one",
+                        column.name, name
+                    )));
+                }
+                table.primary_key.push(column.name.clone());
+            }
+            table.push_column(column)?;
+        }
+        if primary_keys > 1 {
+            return Err(EasyDbError::Value(format!(
+                "Table {} can't have more than one primary key",
+                name
+            )));
+        }
+        for constraint in constraints {
+            match constraint {
+                ast::Constraint::PrimaryKey(columns) => table.primary_key.extend(columns.clone()),
+                ast::Constraint::Unique(columns) => table.unique.push(columns.clone()),
+                ast::Constraint::ForeignKey {
+                    columns,
+                    table: references_table,
+                    references,
+                    on_delete,
+                } => table.foreign_keys.push(ForeignKey {
+                    columns: columns.clone(),
+                    table: references_table.clone(),
+                    references: references.clone(),
+                    on_delete: *on_delete,
+                }),
+            }
+        }
+        for (column, declared) in table.columns.iter_mut().zip(columns) {
+            if declared.nullable.is_none() && table.primary_key.contains(&column.name) {
+                column.nullable = false;
+            }
+        }
+        Ok(table)
+    }
+
+    /// Applies an ALTER TABLE action which changes the columns. Renames
+    /// aren't supported yet.
+    pub fn alter(&mut self, action: &ast::AlterTableAction) -> EasyDbResult<()> {
+        match action {
+            ast::AlterTableAction::AddColumn(column) => self.add_column(column),
+            ast::AlterTableAction::DropColumn(column) => self.drop_column(column),
+            ast::AlterTableAction::RenameTable(_) | ast::AlterTableAction::RenameColumn { .. } => {
+                Err(EasyDbError::Value(format!(
+                    "Can't rename in table {}, ALTER TABLE RENAME isn't supported yet",
+                    self.name
+                )))
+            }
+        }
+    }
+
+    /// Adds a column, as ALTER TABLE ... ADD COLUMN does. Existing rows
+    /// take its default, or NULL without one, so it must either be nullable
+    /// or have a default. Primary key and AUTOINCREMENT columns can't be
+    /// added.
+    pub fn add_column(&mut self, column: &ast::Column) -> EasyDbResult<()> {
+        if column.primary_key {
+            return Err(EasyDbError::Value(format!(
+                "Can't add primary key column {} to table {}",
+                column.name, self.name
+            )));
+        }
+        if column.autoincrement {
+            return Err(EasyDbError::Value(format!(
+                "Can't add AUTOINCREMENT column {} to table {}",
+                column.name, self.name
+            )));
+        }
+        if self.column(&column.name).is_some() {
+            return Err(EasyDbError::Value(format!(
+                "Column {} already exists in table {}",
+                column.name, self.name
+            )));
+        }
+        if column.nullable == Some(false) && column.default.is_none() {
+            return Err(EasyDbError::Value(format!(
+                "Column {} added to table {} must be nullable or have a default",
+                column.name, self.name
+            )));
+        }
+        self.push_column(column)
+    }
+
+    /// Drops a column, as ALTER TABLE ... DROP COLUMN does, along with the
+    /// unique constraints and foreign keys that include it. Primary key
+    /// columns can't be dropped.
+    pub fn drop_column(&mut self, name: &str) -> EasyDbResult<()> {
+        if self.column(name).is_none() {
+            return Err(EasyDbError::Value(format!(
+                "Unknown column {} in table {}",
+                name, self.name
+            )));
+        }
+        if self.primary_key.iter().any(|c| c == name) {
+            return Err(EasyDbError::Value(format!(
+                "Can't drop primary key column {} of table {}",
+                name, self.name
+            )));
+        }
+        self.columns.retain(|c| c.name != name);
+        self.unique
+            .retain(|columns| !columns.iter().any(|c| c == name));
+        self.foreign_keys
+            .retain(|fk| !fk.columns.iter().any(|c| c == name));
+        Ok(())
+    }
+
+    /// Appends a parsed column along with its column-level UNIQUE and
+    /// REFERENCES constraints
+    fn push_column(&mut self, column: &ast::Column) -> EasyDbResult<()> {
+        if column.unique {
+            self.unique.push(vec![column.name.clone()]);
+        }
+        if let Some(references) = &column.references {
+            self.foreign_keys.push(ForeignKey {
+                columns: vec![column.name.clone()],
+                table: references.clone(),
+                references: Vec::new(),
+                on_delete: column.on_delete,
+            });
+        }
+        self.columns.push(Column::from_ast(column)?);
+        Ok(())
+    }
+
+    /// Whether any of the table's foreign keys reference the given table
+    pub fn references(&self, table: &str) -> bool {
+        self.foreign_keys.iter().any(|fk| fk.table == table)
+    }
+
+    /// Checks that the definition is valid: column names are unique, enum
+    /// columns have types in the catalog and defaults among their labels, at
+    /// most one column is AUTOINCREMENT, there is a NOT NULL primary key,
+    /// constraints name existing columns, and foreign keys reference
+    /// existing columns of this table or of tables in the catalog.
+    pub fn validate(&self, catalog: &dyn Catalog) -> EasyDbResult<()> {
+        let mut names = HashSet::new();
+        for column in &self.columns {
+            if !names.insert(&column.name) {
+                return Err(EasyDbError::Value(format!(
+                    "Duplicate column {} in table {}",
+                    column.name, self.name
+                )));
+            }
+            if let DataType::Enum(name) = &column.datatype {
+                let datatype = catalog.must_read_enum(name)?;
+                if let Some(default) = &column.default {
+                    datatype.convert(default.clone()).map_err(|err| {
+                        EasyDbError::Value(format!(
+                            "Invalid default for column {}: {}",
+                            column.name, err
+                        ))
+                    })?;
+                }
+            }
+        }
+
+        let mut autoincrement = self.columns.iter().filter(|c| c.autoincrement);
+        if let (Some(_), Some(column)) = (autoincrement.next(), autoincrement.next()) {
+            return Err(EasyDbError::Value(format!(
+                "Column {} can't be AUTOINCREMENT, table {} already has such a column",
+                column.name, self.name
+            )));
+        }
+
+        if self.primary_key.is_empty() {
+            return Err(EasyDbError::Value(format!(
+                "Table {} has no primary key",
+                self.name
+            )));
+        }
+        self.check_columns(&self.primary_key, "primary key")?;
+        for name in &self.primary_key {
+            if self.column(name).is_some_and(|c| c.nullable) {
+                return Err(EasyDbError::Value(format!(
+                    "Primary key column {} of table {} can't be nullable",
+                    name, self.name
+                )));
+            }
+        }
+        for columns in &self.unique {
+            self.check_columns(columns, "unique constraint")?;
+        }
+
+        for foreign_key in &self.foreign_keys {
+            self.check_columns(&foreign_key.columns, "foreign key")?;
+            if foreign_key.on_delete == ReferentialAction::SetNull {
+                if let Some(column) = foreign_key
+                    .columns
+                    .iter()
+                    .find(|c| self.column(c).is_some_and(|c| !c.nullable))
+                {
+                    return Err(EasyDbError::Value(format!(
+                        "Column {} of table {} can't be set to NULL on delete, it is NOT NULL",
+                        column, self.name
+                    )));
+                }
+            }
+            let column = foreign_key.columns.join(", ");
+            let target = match &foreign_key.table {
+                table if *table == self.name => self.clone(),
+                table => catalog.read_table(table)?.ok_or_else(|| {
+                    EasyDbError::Value(format!(
+                        "Column {} of table {} references unknown table {}",
+                        column, self.name, table
+                    ))
+                })?,
+            };
+            let references = match foreign_key.references.is_empty() {
+                true => &target.primary_key,
+                false => &foreign_key.references,
+            };
+            if let Some(missing) = references.iter().find(|c| target.column(c).is_none()) {
+                return Err(EasyDbError::Value(format!(
+                    "Column {} of table {} references unknown column {}.{}",
+                    column, self.name, target.name, missing
+                )));
+            }
+            if references.len() != foreign_key.columns.len() {
+                return Err(EasyDbError::Value(format!(
+                    "Foreign key ({}) of table {} has {} columns, but references {}",
+                    column,
+                    self.name,
+                    foreign_key.columns.len(),
+                    references.len()
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    /// Checks that a constraint names existing columns, each only once
+    fn check_columns(&self, columns: &[String], constraint: &str) -> EasyDbResult<()> {
+        let mut names = HashSet::new();
+        for name in columns {
+            if self.column(name).is_none() {
+                return Err(EasyDbError::Value(format!(
+                    "Unknown column {} in {} of table {}",
+                    name, constraint, self.name
+                )));
+            }
+            if !names.insert(name) {
+                return Err(EasyDbError::Value(format!(
+                    "Duplicate column {} in {} of table {}",
+                    name, constraint, self.name
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    /// Looks up a column by name
+    pub fn column(&self, name: &str) -> Option<&Column> {
+        self.columns.iter().find(|c| c.name == name)
+    }
+
+    /// Lists the table's indexes, primary key first, named the way
+    /// PostgreSQL names them
+    pub fn indexes(&self) -> Vec<Index> {
+        let mut indexes = vec![Index {
+            name: format!("{}_pkey", self.name),
+            columns: self.primary_key.clone(),
+            unique: true,
+            primary: true,
+        }];
+        for columns in &self.unique {
+            indexes.push(Index {
+                name: format!("{}_{}_key", self.name, columns.join("_")),
+                columns: columns.clone(),
+                unique: true,
+                primary: false,
+            });
+        }
+        for column in self.columns.iter().filter(|c| c.index) {
+            indexes.push(Index {
+                name: format!("{}_{}_idx", self.name, column.name),
+                columns: vec![column.name.clone()],
+                unique: false,
+                primary: false,
+            });
+        }
+        indexes
+    }
+}
+
+impl Column {
+    fn from_ast(column: &ast::Column) -> EasyDbResult<Self> {
+        let default = match &column.default {
+            Some(expr) => {
+                let value = evaluate(expr, None).map_err(|err| {
+                    EasyDbError::Value(format!(
+                        "Invalid default for column {}: {}",
+                        column.name, err
+                    ))
+                })?;
+                match &column.datatype {
+                    DataType::Enum(_) => Some(value),
+                    datatype => Some(coerce::assign(value, datatype, LengthPolicy::Error)?),
+                }
+            }
+            None => None,
+        };
+        if column.autoincrement && default.is_some() {
+            return Err(EasyDbError::Value(format!(
+                "AUTOINCREMENT column {} can't have a default",
+                column.name
+            )));
+        }
+        Ok(Self {
+            name: column.name.clone(),
+            datatype: column.datatype.clone(),
+            nullable: column.nullable.unwrap_or(true),
+            default,
+            index: column.index,
+            autoincrement: column.autoincrement,
+            collation: column.collation.unwrap_or_default(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::parser::ast::{Parser, Statement};
+    use super::super::types::Interval;
+    use super::*;
+
+    /// Builds a table definition from a CREATE TABLE statement
+    fn table(sql: &str) -> EasyDbResult<Table> {
+        match Parser::new(sql).parse()? {
+            Statement::CreateTable {
+                name,
+                columns,
+                constraints,
+            } => Table::from_ast(&name, &columns, &constraints),
+            statement => panic!("expected CREATE TABLE, got {}", statement),
+        }
+    }
+
+    #[test]
+    fn interval_default() {
+        let table =
+            table("CREATE TABLE t (id INT PRIMARY KEY, i INTERVAL DEFAULT INTERVAL '1 day')")
+                .unwrap();
+        assert_eq!(
+            table.columns[1].default,
+            Some(Value::Interval("1 day".parse::<Interval>().unwrap()))
+        );
+    }
+
+    #[test]
+    fn alter() -> EasyDbResult<()> {
+        let mut t =
+            table("CREATE TABLE t (id INT PRIMARY KEY, a INT UNIQUE, b INT, UNIQUE (a, b))")?;
+        let alter = |t: &mut Table, sql: &str| match Parser::new(sql).parse()? {
+            Statement::AlterTable { action, .. } => t.alter(&action),
+            statement => panic!("expected ALTER TABLE, got {}", statement),
+        };
+        alter(&mut t, "ALTER TABLE t DROP COLUMN a")?;
+        assert_eq!(t.columns.len(), 2);
+        assert!(t.unique.is_empty());
+        assert!(alter(&mut t, "ALTER TABLE t DROP COLUMN id").is_err());
+        assert!(alter(&mut t, "ALTER TABLE t DROP COLUMN a").is_err());
+        assert!(alter(&mut t, "ALTER TABLE t ADD COLUMN b INT").is_err());
+        assert!(alter(&mut t, "ALTER TABLE t ADD COLUMN c INT NOT NULL").is_err());
+        alter(
+            &mut t,
+            "ALTER TABLE t ADD COLUMN c INT NOT NULL DEFAULT 1 UNIQUE",
+        )?;
+        assert_eq!(t.unique, vec![vec!["c".to_string()]]);
+        assert!(alter(&mut t, "ALTER TABLE t RENAME TO u").is_err());
+        Ok(())
+    }
+}
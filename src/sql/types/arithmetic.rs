@@ -0,0 +1,139 @@
+use super::{coerce, Value};
+use crate::error::{EasyDbError, EasyDbResult};
+
+/// The arithmetic operators, for error messages
+#[derive(Clone, Copy)]
+enum Op {
+    Add,
+    Subtract,
+    Multiply,
+    Divide,
+    Remainder,
+    Exponentiate,
+}
+
+impl Op {
+    fn verb(self) -> &'static str {
+        match self {
+            Self::Add => "add",
+            Self::Subtract => "subtract",
+            Self::Multiply => "multiply",
+            Self::Divide => "divide",
+            Self::Remainder => "take the remainder of",
+            Self::Exponentiate => "exponentiate",
+        }
+    }
+}
+
+fn overflow() -> EasyDbError {
+    EasyDbError::Value("Integer overflow".into())
+}
+
+fn divide_by_zero() -> EasyDbError {
+    EasyDbError::Value("Can't divide by zero".into())
+}
+
+/// Arithmetic on values. NULL operands give NULL, mixed numeric operands are
+/// first coerced to their common type, and integer overflow or division by
+/// zero are errors rather than wrapping or panicking.
+impl Value {
+    pub fn checked_add(&self, other: &Value) -> EasyDbResult<Value> {
+        self.arithmetic(other, Op::Add)
+    }
+
+    pub fn checked_sub(&self, other: &Value) -> EasyDbResult<Value> {
+        self.arithmetic(other, Op::Subtract)
+    }
+
+    pub fn checked_mul(&self, other: &Value) -> EasyDbResult<Value> {
+        self.arithmetic(other, Op::Multiply)
+    }
+
+    /// Divides, truncating towards zero for integers
+    pub fn checked_div(&self, other: &Value) -> EasyDbResult<Value> {
+        self.arithmetic(other, Op::Divide)
+    }
+
+    /// The remainder of truncating division, with the sign of the dividend
+    pub fn checked_rem(&self, other: &Value) -> EasyDbResult<Value> {
+        self.arithmetic(other, Op::Remainder)
+    }
+
+    /// Raises to a power. Integers raised to negative powers give floats.
+    pub fn checked_pow(&self, other: &Value) -> EasyDbResult<Value> {
+        self.arithmetic(other, Op::Exponentiate)
+    }
+
+    pub fn checked_neg(&self) -> EasyDbResult<Value> {
+        Ok(match self {
+            Self::Null => Self::Null,
+            Self::Integer(i) => Self::Integer(i.checked_neg().ok_or_else(overflow)?),
+            Self::Float(f) => Self::Float(-f),
+            Self::Decimal(d) => Self::Decimal(d.neg()),
+            Self::Interval(i) => Self::Interval(i.checked_neg()?),
+            value => {
+                return Err(EasyDbError::Value(format!(
+                    "Can't negate {}",
+                    value.datatype().map_or("NULL".into(), |t| t.to_string())
+                )))
+            }
+        })
+    }
+
+    fn arithmetic(&self, other: &Value, op: Op) -> EasyDbResult<Value> {
+        let error = || {
+            let name = |v: &Value| v.datatype().map_or("NULL".into(), |t| t.to_string());
+            EasyDbError::Value(format!(
+                "Can't {} {} and {}",
+                op.verb(),
+                name(self),
+                name(other)
+            ))
+        };
+        let (lhs, rhs) = coerce::unify(self, other)?.ok_or_else(error)?;
+
+        Ok(match (lhs, rhs, op) {
+            (Self::Null, _, _) | (_, Self::Null, _) => Self::Null,
+
+            (Self::Integer(a), Self::Integer(b), op) => match op {
+                Op::Add => Self::Integer(a.checked_add(b).ok_or_else(overflow)?),
+                Op::Subtract => Self::Integer(a.checked_sub(b).ok_or_else(overflow)?),
+                Op::Multiply => Self::Integer(a.checked_mul(b).ok_or_else(overflow)?),
+                Op::Divide | Op::Remainder if b == 0 => return Err(divide_by_zero()),
+                Op::Divide => Self::Integer(a.checked_div(b).ok_or_else(overflow)?),
+                Op::Remainder => Self::Integer(a.checked_rem(b).ok_or_else(overflow)?),
+                Op::Exponentiate => match u32::try_from(b) {
+                    Ok(b) => Self::Integer(a.checked_pow(b).ok_or_else(overflow)?),
+                    Err(_) if b < 0 => Self::Float((a as f64).powf(b as f64)),
+                    Err(_) => return Err(overflow()),
+                },
+            },
+
+            (Self::Float(a), Self::Float(b), op) => Self::Float(match op {
+                Op::Add => a + b,
+                Op::Subtract => a - b,
+                Op::Multiply => a * b,
+                Op::Divide | Op::Remainder if b == 0.0 => return Err(divide_by_zero()),
+                Op::Divide => a / b,
+                Op::Remainder => a % b,
+                Op::Exponentiate => a.powf(b),
+            }),
+
+            (Self::Decimal(a), Self::Decimal(b), op) => match op {
+                Op::Add => Self::Decimal(a.checked_add(&b)?),
+                Op::Subtract => Self::Decimal(a.checked_sub(&b)?),
+                Op::Multiply => Self::Decimal(a.checked_mul(&b)?),
+                Op::Divide => Self::Decimal(a.checked_div(&b)?),
+                Op::Remainder => Self::Decimal(a.checked_rem(&b)?),
+                Op::Exponentiate => Self::Float(a.to_f64().powf(b.to_f64())),
+            },
+
+            (Self::Interval(a), Self::Interval(b), Op::Add) => Self::Interval(a.checked_add(&b)?),
+            (Self::Interval(a), Self::Interval(b), Op::Subtract) => {
+                Self::Interval(a.checked_add(&b.checked_neg()?)?)
+            }
+
+            _ => return Err(error()),
+        })
+    }
+}
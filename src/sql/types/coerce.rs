@@ -0,0 +1,256 @@
+use super::{DataType, Decimal, Value};
+use crate::error::{EasyDbError, EasyDbResult};
+
+/// Converts a value to the given type where that can happen implicitly, e.g.
+/// when storing it in a column: NULL to any type, integers to floats and
+/// decimals, decimals to other precisions and scales, and strings to JSON.
+pub fn implicit(value: Value, to: &DataType) -> EasyDbResult<Value> {
+    let value = match (value, to) {
+        (Value::Null, _) => Value::Null,
+        (Value::Boolean(b), DataType::Boolean) => Value::Boolean(b),
+        (Value::Integer(i), DataType::SmallInt | DataType::Int | DataType::BigInt) => {
+            Value::Integer(i)
+        }
+        (Value::Integer(i), DataType::Float) => Value::Float(i as f64),
+        (Value::Integer(i), DataType::Decimal(p, s)) => {
+            Value::Decimal(Decimal::from(i).fit(*p, *s)?)
+        }
+        (Value::Float(f), DataType::Float) => Value::Float(f),
+        (Value::Decimal(d), DataType::Decimal(p, s)) => Value::Decimal(d.fit(*p, *s)?),
+        (Value::Decimal(d), DataType::Float) => Value::Float(d.to_f64()),
+        (Value::String(s), DataType::String(_)) => Value::String(s),
+        (Value::String(s), DataType::Json) => Value::json(&s)?,
+        (Value::Bytes(b), DataType::Bytes) => Value::Bytes(b),
+        (Value::Json(j), DataType::Json) => Value::Json(j),
+        (Value::Interval(i), DataType::Interval) => Value::Interval(i),
+        (Value::Enum(e), DataType::Enum(name)) if e.datatype == *name => Value::Enum(e),
+        (value, to) => {
+            return Err(EasyDbError::Value(format!(
+                "Can't use {} as {} without an explicit cast",
+                describe(&value),
+                to
+            )))
+        }
+    };
+    check_integer_range(value, to)
+}
+
+/// What to do with a string longer than its VARCHAR(n) column allows
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum LengthPolicy {
+    /// Reject the value
+    #[default]
+    Error,
+    /// Cut the value down to the maximum length
+    Truncate,
+}
+
+/// Converts a value for storage in a column of the given type, as INSERT
+/// and UPDATE do: an implicit coercion which also enforces the maximum
+/// length of strings according to the policy.
+pub fn assign(value: Value, to: &DataType, policy: LengthPolicy) -> EasyDbResult<Value> {
+    match (implicit(value, to)?, to) {
+        (Value::String(s), DataType::String(Some(max))) => {
+            let max = *max as usize;
+            match s.char_indices().nth(max) {
+                None => Ok(Value::String(s)),
+                Some((end, _)) if policy == LengthPolicy::Truncate => {
+                    Ok(Value::String(s[..end].to_string()))
+                }
+                Some(_) => Err(EasyDbError::Value(format!(
+                    "String of {} characters is too long for {}",
+                    s.chars().count(),
+                    to
+                ))),
+            }
+        }
+        (value, _) => Ok(value),
+    }
+}
+
+/// Checks that an integer value is within the range of an integer type
+fn check_integer_range(value: Value, to: &DataType) -> EasyDbResult<Value> {
+    match (&value, to.integer_range()) {
+        (Value::Integer(i), Some((min, max))) if *i < min || *i > max => Err(EasyDbError::Value(
+            format!("Integer {} is out of range for {}", i, to),
+        )),
+        _ => Ok(value),
+    }
+}
+
+/// Returns the type both operands of a binary operation are coerced to, if
+/// any: the same type, or the wider numeric type for mixed numbers. NULL
+/// takes the type of the other operand.
+pub fn common_type(lhs: &Value, rhs: &Value) -> Option<DataType> {
+    use DataType::*;
+    match (lhs.datatype(), rhs.datatype()) {
+        (None, None) => None,
+        (Some(t), None) | (None, Some(t)) => Some(t),
+        (Some(BigInt), Some(Float)) | (Some(Float), Some(BigInt)) => Some(Float),
+        (Some(Decimal(..)), Some(Float)) | (Some(Float), Some(Decimal(..))) => Some(Float),
+        (Some(BigInt), Some(Decimal(p, s))) | (Some(Decimal(p, s)), Some(BigInt)) => {
+            // Leave room for the integer's up to 19 digits before the point
+            Some(Decimal(
+                (p - s)
+                    .max(19)
+                    .saturating_add(s)
+                    .min(super::MAX_DECIMAL_PRECISION),
+                s,
+            ))
+        }
+        (Some(Decimal(p1, s1)), Some(Decimal(p2, s2))) => {
+            let scale = s1.max(s2);
+            let precision = (p1 - s1).max(p2 - s2) + scale;
+            Some(Decimal(precision.min(super::MAX_DECIMAL_PRECISION), scale))
+        }
+        (Some(String(_)), Some(String(_))) => Some(String(None)),
+        (Some(a), Some(b)) if a == b => Some(a),
+        _ => None,
+    }
+}
+
+/// Converts the operands of a binary operation to their common type, or
+/// returns None if they have none. A NULL operand is left as is. Decimals
+/// keep their own scales rather than being rounded to a common one.
+pub fn unify(lhs: &Value, rhs: &Value) -> EasyDbResult<Option<(Value, Value)>> {
+    let to_decimal = |value: &Value| match value {
+        Value::Integer(i) => Value::Decimal((*i).into()),
+        value => value.clone(),
+    };
+    Ok(match common_type(lhs, rhs) {
+        None if matches!((lhs, rhs), (Value::Null, _) | (_, Value::Null)) => {
+            Some((lhs.clone(), rhs.clone()))
+        }
+        None => None,
+        Some(DataType::Decimal(..)) => Some((to_decimal(lhs), to_decimal(rhs))),
+        Some(datatype) => Some((
+            implicit(lhs.clone(), &datatype)?,
+            implicit(rhs.clone(), &datatype)?,
+        )),
+    })
+}
+
+/// Converts a value to the given type for an explicit CAST(x AS type) or
+/// x::type, which allows any conversion that makes sense for the value
+pub fn cast(value: Value, to: &DataType) -> EasyDbResult<Value> {
+    let error =
+        |value: &Value| EasyDbError::Value(format!("Can't cast {} to {}", describe(value), to));
+    let value = match (value, to) {
+        (Value::Null, _) => Value::Null,
+
+        (Value::Integer(i), DataType::Boolean) => Value::Boolean(i != 0),
+        (Value::String(s), DataType::Boolean) => match s.trim().to_lowercase().as_str() {
+            "true" | "t" | "yes" | "y" | "on" | "1" => Value::Boolean(true),
+            "false" | "f" | "no" | "n" | "off" | "0" => Value::Boolean(false),
+            _ => return Err(error(&Value::String(s))),
+        },
+
+        (Value::Integer(i), DataType::SmallInt | DataType::Int | DataType::BigInt) => {
+            Value::Integer(i)
+        }
+        (Value::Boolean(b), DataType::SmallInt | DataType::Int | DataType::BigInt) => {
+            Value::Integer(b.into())
+        }
+        (Value::Float(f), DataType::SmallInt | DataType::Int | DataType::BigInt) => {
+            // Out of range floats, including NaN and infinities, are errors
+            // rather than saturating as the `as` conversion would
+            let truncated = f.trunc();
+            if !(i64::MIN as f64..i64::MAX as f64).contains(&truncated) {
+                return Err(error(&Value::Float(f)));
+            }
+            Value::Integer(truncated as i64)
+        }
+        (Value::Decimal(d), DataType::SmallInt | DataType::Int | DataType::BigInt) => {
+            let mantissa = d.rescale(0)?.mantissa();
+            match i64::try_from(mantissa) {
+                Ok(i) => Value::Integer(i),
+                Err(_) => return Err(error(&Value::Decimal(d))),
+            }
+        }
+        (Value::String(s), DataType::SmallInt | DataType::Int | DataType::BigInt) => {
+            match s.trim().parse() {
+                Ok(i) => Value::Integer(i),
+                Err(_) => return Err(error(&Value::String(s))),
+            }
+        }
+
+        (Value::String(s), DataType::Float) => match s.trim().parse() {
+            Ok(f) => Value::Float(f),
+            Err(_) => return Err(error(&Value::String(s))),
+        },
+
+        (Value::Float(f), DataType::Decimal(p, s)) => {
+            Value::Decimal(Decimal::from_f64(f)?.fit(*p, *s)?)
+        }
+        (Value::String(text), DataType::Decimal(p, s)) => {
+            Value::Decimal(text.trim().parse::<Decimal>()?.fit(*p, *s)?)
+        }
+
+        (Value::Bytes(b), DataType::String(_)) => match String::from_utf8(b) {
+            Ok(s) => Value::String(s),
+            Err(err) => return Err(error(&Value::Bytes(err.into_bytes()))),
+        },
+        (value, DataType::String(_)) => Value::String(value.to_string()),
+
+        (Value::String(s), DataType::Bytes) => Value::Bytes(s.into_bytes()),
+
+        (Value::String(s), DataType::Interval) => Value::Interval(s.trim().parse()?),
+
+        (value, to) => match implicit(value.clone(), to) {
+            Ok(value) => value,
+            Err(_) => return Err(error(&value)),
+        },
+    };
+    check_integer_range(value, to)
+}
+
+/// Describes a value along with its type for error messages
+pub(crate) fn describe(value: &Value) -> String {
+    match (value, value.datatype()) {
+        (Value::String(s), _) => format!("string '{}'", s),
+        (value, Some(datatype)) => format!("{} value {}", datatype, value),
+        (value, None) => value.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::Interval;
+    use super::*;
+
+    #[test]
+    fn assign_interval() {
+        let day = Value::Interval("1 day".parse::<Interval>().unwrap());
+        assert_eq!(
+            assign(day.clone(), &DataType::Interval, LengthPolicy::Error).unwrap(),
+            day
+        );
+        assert_eq!(
+            assign(Value::Null, &DataType::Interval, LengthPolicy::Error).unwrap(),
+            Value::Null
+        );
+        assert!(assign(day, &DataType::Int, LengthPolicy::Error).is_err());
+        assert!(assign(
+            Value::String("1 day".into()),
+            &DataType::Interval,
+            LengthPolicy::Error
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn cast_interval() {
+        let day = Value::Interval("1 day".parse::<Interval>().unwrap());
+        assert_eq!(
+            cast(Value::String(" 1 day ".into()), &DataType::Interval).unwrap(),
+            day
+        );
+        assert_eq!(cast(day.clone(), &DataType::Interval).unwrap(), day);
+        assert_eq!(
+            cast(day, &DataType::String(None)).unwrap(),
+            Value::String("1 day".into())
+        );
+        assert!(cast(Value::String("1 fortnight".into()), &DataType::Interval).is_err());
+        assert!(cast(Value::Integer(1), &DataType::Interval).is_err());
+    }
+}
@@ -0,0 +1,364 @@
+use super::MAX_DECIMAL_PRECISION;
+use crate::error::{EasyDbError, EasyDbResult};
+
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+use std::hash::{Hash, Hasher};
+use std::str::FromStr;
+
+/// The largest mantissa magnitude, i.e. MAX_DECIMAL_PRECISION nines
+const MAX_MANTISSA: i128 = 10i128.pow(MAX_DECIMAL_PRECISION) - 1;
+
+/// The smallest scale of a division result, so that e.g. 1 / 3 doesn't
+/// truncate to 0
+const MIN_DIVISION_SCALE: u32 = 6;
+
+/// An exact fixed-point number, mantissa * 10^-scale. Arithmetic never
+/// rounds silently except where noted, and fails rather than overflowing
+/// MAX_DECIMAL_PRECISION digits.
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+pub struct Decimal {
+    mantissa: i128,
+    scale: u32,
+}
+
+fn pow10(exp: u32) -> EasyDbResult<i128> {
+    10i128.checked_pow(exp).ok_or_else(overflow)
+}
+
+fn overflow() -> EasyDbError {
+    EasyDbError::Value("Decimal overflow".into())
+}
+
+/// Divides and rounds half away from zero
+fn div_round(n: i128, d: i128) -> i128 {
+    let (q, r) = (n / d, n % d);
+    if r.abs() >= d.abs() - r.abs() {
+        q + n.signum() * d.signum()
+    } else {
+        q
+    }
+}
+
+impl Decimal {
+    pub fn new(mantissa: i128, scale: u32) -> EasyDbResult<Self> {
+        if mantissa.abs() > MAX_MANTISSA || scale > MAX_DECIMAL_PRECISION {
+            return Err(overflow());
+        }
+        Ok(Self { mantissa, scale })
+    }
+
+    pub fn mantissa(&self) -> i128 {
+        self.mantissa
+    }
+
+    pub fn scale(&self) -> u32 {
+        self.scale
+    }
+
+    /// The number of significant digits, which is at least the scale
+    pub fn precision(&self) -> u32 {
+        let digits = self
+            .mantissa
+            .unsigned_abs()
+            .checked_ilog10()
+            .map_or(1, |d| d + 1);
+        digits.max(self.scale)
+    }
+
+    /// Changes the scale, rounding half away from zero if it shrinks
+    pub fn rescale(&self, scale: u32) -> EasyDbResult<Self> {
+        match scale.cmp(&self.scale) {
+            Ordering::Equal => Ok(*self),
+            Ordering::Greater => Self::new(
+                self.mantissa
+                    .checked_mul(pow10(scale - self.scale)?)
+                    .ok_or_else(overflow)?,
+                scale,
+            ),
+            Ordering::Less => {
+                Self::new(div_round(self.mantissa, pow10(self.scale - scale)?), scale)
+            }
+        }
+    }
+
+    /// Converts the value to a DECIMAL(precision, scale) column type,
+    /// rounding to the scale and failing if the digits don't fit
+    pub fn fit(&self, precision: u32, scale: u32) -> EasyDbResult<Self> {
+        let value = self.rescale(scale)?;
+        if value.precision() > precision {
+            return Err(EasyDbError::Value(format!(
+                "Decimal {} does not fit in DECIMAL({}, {})",
+                self, precision, scale
+            )));
+        }
+        Ok(value)
+    }
+
+    /// Brings both values to the larger of their scales
+    fn align(&self, other: &Self) -> EasyDbResult<(i128, i128, u32)> {
+        let scale = self.scale.max(other.scale);
+        Ok((
+            self.rescale(scale)?.mantissa,
+            other.rescale(scale)?.mantissa,
+            scale,
+        ))
+    }
+
+    pub fn checked_add(&self, other: &Self) -> EasyDbResult<Self> {
+        let (a, b, scale) = self.align(other)?;
+        Self::new(a.checked_add(b).ok_or_else(overflow)?, scale)
+    }
+
+    pub fn checked_sub(&self, other: &Self) -> EasyDbResult<Self> {
+        let (a, b, scale) = self.align(other)?;
+        Self::new(a.checked_sub(b).ok_or_else(overflow)?, scale)
+    }
+
+    /// Multiplies exactly, only rounding if the scale would exceed the
+    /// maximum precision
+    pub fn checked_mul(&self, other: &Self) -> EasyDbResult<Self> {
+        let mantissa = self
+            .mantissa
+            .checked_mul(other.mantissa)
+            .ok_or_else(overflow)?;
+        let scale = self.scale + other.scale;
+        if scale > MAX_DECIMAL_PRECISION {
+            let mantissa = div_round(mantissa, pow10(scale - MAX_DECIMAL_PRECISION)?);
+            return Self::new(mantissa, MAX_DECIMAL_PRECISION);
+        }
+        Self::new(mantissa, scale)
+    }
+
+    /// Divides, rounding to the larger of the operand scales but at least
+    /// MIN_DIVISION_SCALE decimal places
+    pub fn checked_div(&self, other: &Self) -> EasyDbResult<Self> {
+        if other.mantissa == 0 {
+            return Err(EasyDbError::Value("Can't divide by zero".into()));
+        }
+        let scale = self.scale.max(other.scale).max(MIN_DIVISION_SCALE);
+        let numerator = self
+            .mantissa
+            .checked_mul(pow10(scale + other.scale - self.scale)?)
+            .ok_or_else(overflow)?;
+        Self::new(div_round(numerator, other.mantissa), scale)
+    }
+
+    /// The remainder of truncating division, with the sign of self
+    pub fn checked_rem(&self, other: &Self) -> EasyDbResult<Self> {
+        if other.mantissa == 0 {
+            return Err(EasyDbError::Value("Can't divide by zero".into()));
+        }
+        let (a, b, scale) = self.align(other)?;
+        Self::new(a % b, scale)
+    }
+
+    pub fn neg(&self) -> Self {
+        Self {
+            mantissa: -self.mantissa,
+            scale: self.scale,
+        }
+    }
+
+    /// Converts a float, failing for NaN, infinities and out of range values.
+    /// The float's shortest decimal representation is used, so 0.1 converts
+    /// to exactly 0.1.
+    pub fn from_f64(f: f64) -> EasyDbResult<Self> {
+        if !f.is_finite() {
+            return Err(EasyDbError::Value(format!(
+                "Can't convert {} to decimal",
+                f
+            )));
+        }
+        f.to_string().parse()
+    }
+
+    /// Converts to the nearest float
+    pub fn to_f64(&self) -> f64 {
+        // Parsing the decimal text rounds correctly, unlike dividing by a power of 10
+        self.to_string().parse().unwrap_or(f64::NAN)
+    }
+
+    /// Strips trailing fractional zeros, so equal values have equal parts
+    fn normalize(&self) -> Self {
+        let mut value = *self;
+        while value.scale > 0 && value.mantissa % 10 == 0 {
+            value.mantissa /= 10;
+            value.scale -= 1;
+        }
+        value
+    }
+}
+
+impl From<i64> for Decimal {
+    fn from(i: i64) -> Self {
+        Self {
+            mantissa: i.into(),
+            scale: 0,
+        }
+    }
+}
+
+impl FromStr for Decimal {
+    type Err = EasyDbError;
+
+    fn from_str(s: &str) -> EasyDbResult<Self> {
+        let invalid = || EasyDbError::Value(format!("Invalid decimal {}", s));
+        let (negative, digits) = match s.strip_prefix('-') {
+            Some(digits) => (true, digits),
+            None => (false, s.strip_prefix('+').unwrap_or(s)),
+        };
+        let (int, frac) = digits.split_once('.').unwrap_or((digits, ""));
+        if int.is_empty() && frac.is_empty()
+            || !int.chars().chain(frac.chars()).all(|c| c.is_ascii_digit())
+        {
+            return Err(invalid());
+        }
+
+        let mut mantissa: i128 = 0;
+        for c in int.chars().chain(frac.chars()) {
+            mantissa = mantissa
+                .checked_mul(10)
+                .and_then(|m| m.checked_add(i128::from(c as u8 - b'0')))
+                .ok_or_else(overflow)?;
+        }
+        if negative {
+            mantissa = -mantissa;
+        }
+        Self::new(mantissa, frac.len().try_into().map_err(|_| overflow())?)
+    }
+}
+
+impl std::fmt::Display for Decimal {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        if self.mantissa < 0 {
+            f.write_str("-")?;
+        }
+        let digits = self.mantissa.unsigned_abs().to_string();
+        let scale = self.scale as usize;
+        if scale == 0 {
+            return f.write_str(&digits);
+        }
+        let digits = format!("{:0>width$}", digits, width = scale + 1);
+        let (int, frac) = digits.split_at(digits.len() - scale);
+        write!(f, "{}.{}", int, frac)
+    }
+}
+
+impl PartialEq for Decimal {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Decimal {}
+
+impl PartialOrd for Decimal {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// Compares the integral parts first and then the fractional parts, which
+/// unlike rescaling to a common scale can't overflow
+impl Ord for Decimal {
+    fn cmp(&self, other: &Self) -> Ordering {
+        let split = |d: &Self| {
+            let unit = 10i128.pow(d.scale);
+            (d.mantissa / unit, d.mantissa % unit)
+        };
+        let ((a_int, a_frac), (b_int, b_frac)) = (split(self), split(other));
+        let scale = self.scale.max(other.scale);
+        a_int.cmp(&b_int).then_with(|| {
+            let a_frac = a_frac * 10i128.pow(scale - self.scale);
+            let b_frac = b_frac * 10i128.pow(scale - other.scale);
+            a_frac.cmp(&b_frac)
+        })
+    }
+}
+
+impl Hash for Decimal {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        let Self { mantissa, scale } = self.normalize();
+        mantissa.hash(state);
+        scale.hash(state);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn d(s: &str) -> Decimal {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn parse_and_display() {
+        for s in ["0", "1.50", "-0.5", "123.456", "0.001", "-10"] {
+            assert_eq!(d(s).to_string(), s);
+        }
+        assert_eq!(d("+.5").to_string(), "0.5");
+        assert_eq!(d("5.").to_string(), "5");
+        assert_eq!(d("1.50").precision(), 3);
+        assert_eq!(d("0.001").precision(), 3);
+        for s in ["", ".", "-", "1.2.3", "1e5", "abc", "- 1"] {
+            assert!(s.parse::<Decimal>().is_err(), "{}", s);
+        }
+        assert!("1"
+            .repeat(MAX_DECIMAL_PRECISION as usize + 1)
+            .parse::<Decimal>()
+            .is_err());
+    }
+
+    #[test]
+    fn equality_ignores_scale() {
+        assert_eq!(d("1.50"), d("1.5"));
+        assert_eq!(d("-0"), d("0.000"));
+        assert!(d("-1.5") < d("-1.25"));
+        assert!(d("0.1") < d("0.10001"));
+        let hash = |d: Decimal| {
+            let mut hasher = std::collections::hash_map::DefaultHasher::new();
+            d.hash(&mut hasher);
+            hasher.finish()
+        };
+        assert_eq!(hash(d("1.50")), hash(d("1.5")));
+    }
+
+    #[test]
+    fn arithmetic() -> EasyDbResult<()> {
+        assert_eq!(d("1.5").checked_add(&d("2.25"))?.to_string(), "3.75");
+        assert_eq!(d("1.5").checked_sub(&d("2.25"))?.to_string(), "-0.75");
+        assert_eq!(d("1.5").checked_mul(&d("-2.25"))?.to_string(), "-3.375");
+        assert_eq!(d("1").checked_div(&d("3"))?.to_string(), "0.333333");
+        assert_eq!(d("2").checked_div(&d("3"))?.to_string(), "0.666667");
+        assert_eq!(d("-7.5").checked_rem(&d("2"))?.to_string(), "-1.5");
+        assert!(d("1").checked_div(&d("0")).is_err());
+        assert!(d("1").checked_rem(&d("0.0")).is_err());
+        let max = Decimal::new(MAX_MANTISSA, 0)?;
+        assert!(max.checked_add(&d("1")).is_err());
+        assert!(max.checked_mul(&d("10")).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn rescale_and_fit() -> EasyDbResult<()> {
+        assert_eq!(d("1.25").rescale(1)?.to_string(), "1.3");
+        assert_eq!(d("-1.25").rescale(1)?.to_string(), "-1.3");
+        assert_eq!(d("1.24").rescale(1)?.to_string(), "1.2");
+        assert_eq!(d("1.2").rescale(3)?.to_string(), "1.200");
+        assert_eq!(d("12.345").fit(5, 2)?.to_string(), "12.35");
+        assert!(d("1234.5").fit(5, 2).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn floats() -> EasyDbResult<()> {
+        assert_eq!(Decimal::from_f64(0.1)?.to_string(), "0.1");
+        assert_eq!(Decimal::from_f64(-2.5)?.to_string(), "-2.5");
+        assert!(Decimal::from_f64(f64::NAN).is_err());
+        assert!(Decimal::from_f64(f64::INFINITY).is_err());
+        assert_eq!(d("0.1").to_f64(), 0.1);
+        Ok(())
+    }
+}
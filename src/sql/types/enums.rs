@@ -0,0 +1,90 @@
+use super::Value;
+use crate::error::{EasyDbError, EasyDbResult};
+
+use serde::{Deserialize, Serialize};
+
+/// A user-defined enum type, from CREATE TYPE ... AS ENUM
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct EnumType {
+    pub name: String,
+    /// The labels, in order. A label's position is its ordinal.
+    pub labels: Vec<String>,
+}
+
+/// A label of an enum type. Labels of a type order by their ordinals, i.e.
+/// the order the type declares them in, rather than alphabetically.
+#[derive(Clone, Debug, Hash, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct EnumValue {
+    pub ordinal: u32,
+    pub label: String,
+    /// The name of the enum type
+    pub datatype: String,
+}
+
+impl EnumType {
+    /// Returns the label with an ordinal, if the type has one
+    pub fn value(&self, ordinal: u32) -> Option<Value> {
+        let label = self.labels.get(ordinal as usize)?;
+        Some(Value::Enum(EnumValue {
+            ordinal,
+            label: label.clone(),
+            datatype: self.name.clone(),
+        }))
+    }
+
+    /// Converts a value to a label of the type, as casts and assignments to
+    /// columns of the type do: a string must be one of the labels, and a
+    /// label must be of this type. NULL stays NULL.
+    pub fn convert(&self, value: Value) -> EasyDbResult<Value> {
+        match value {
+            Value::Null => Ok(Value::Null),
+            Value::String(s) => self
+                .labels
+                .iter()
+                .position(|label| *label == s)
+                .and_then(|ordinal| self.value(ordinal as u32))
+                .ok_or_else(|| {
+                    EasyDbError::Value(format!("Invalid label '{}' for enum type {}", s, self.name))
+                }),
+            Value::Enum(e) if self.value(e.ordinal).as_ref() == Some(&Value::Enum(e.clone())) => {
+                Ok(Value::Enum(e))
+            }
+            value => Err(EasyDbError::Value(format!(
+                "Can't convert {} to enum type {}",
+                super::coerce::describe(&value),
+                self.name
+            ))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn convert() {
+        let mood = EnumType {
+            name: "mood".into(),
+            labels: vec!["sad".into(), "ok".into(), "happy".into()],
+        };
+        let happy = mood.convert(Value::String("happy".into())).unwrap();
+        assert_eq!(happy, mood.value(2).unwrap());
+        assert_eq!(happy.to_string(), "happy");
+        assert_eq!(mood.convert(happy.clone()).unwrap(), happy);
+        assert_eq!(mood.convert(Value::Null).unwrap(), Value::Null);
+        assert_eq!(mood.value(3), None);
+
+        // Labels order by ordinal, not alphabetically
+        let sad = mood.convert(Value::String("sad".into())).unwrap();
+        assert!(sad < happy);
+
+        assert!(mood.convert(Value::String("HAPPY".into())).is_err());
+        assert!(mood.convert(Value::Integer(1)).is_err());
+        let other = EnumType {
+            name: "other".into(),
+            labels: mood.labels.clone(),
+        };
+        assert!(other.convert(happy).is_err());
+    }
+}
@@ -0,0 +1,170 @@
+use crate::error::{EasyDbError, EasyDbResult};
+
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+use std::hash::{Hash, Hasher};
+use std::str::FromStr;
+
+const MICROS_PER_SECOND: i64 = 1_000_000;
+const MICROS_PER_MINUTE: i64 = 60 * MICROS_PER_SECOND;
+const MICROS_PER_HOUR: i64 = 60 * MICROS_PER_MINUTE;
+const MICROS_PER_DAY: i64 = 24 * MICROS_PER_HOUR;
+/// Months have no fixed length, so they count as 30 days when comparing
+const DAYS_PER_MONTH: i64 = 30;
+
+/// A span of time. Months and days are kept apart from the sub-day part,
+/// since their length in absolute time depends on the date they're added to.
+#[derive(Clone, Copy, Debug, Default, Serialize, Deserialize)]
+pub struct Interval {
+    pub months: i32,
+    pub days: i32,
+    pub micros: i64,
+}
+
+impl Interval {
+    /// The interval's approximate length in microseconds, with 30-day
+    /// months and 24-hour days, used to compare intervals
+    pub fn approximate_micros(&self) -> i128 {
+        (i128::from(self.months) * i128::from(DAYS_PER_MONTH) + i128::from(self.days))
+            * i128::from(MICROS_PER_DAY)
+            + i128::from(self.micros)
+    }
+
+    pub fn checked_add(&self, other: &Self) -> EasyDbResult<Self> {
+        let overflow = || EasyDbError::Value("Interval overflow".into());
+        Ok(Self {
+            months: self.months.checked_add(other.months).ok_or_else(overflow)?,
+            days: self.days.checked_add(other.days).ok_or_else(overflow)?,
+            micros: self.micros.checked_add(other.micros).ok_or_else(overflow)?,
+        })
+    }
+
+    pub fn checked_neg(&self) -> EasyDbResult<Self> {
+        let overflow = || EasyDbError::Value("Interval overflow".into());
+        Ok(Self {
+            months: self.months.checked_neg().ok_or_else(overflow)?,
+            days: self.days.checked_neg().ok_or_else(overflow)?,
+            micros: self.micros.checked_neg().ok_or_else(overflow)?,
+        })
+    }
+}
+
+/// Parses intervals such as '1 day', '2 hours 30 minutes' or '-1 year 6 months'
+impl FromStr for Interval {
+    type Err = EasyDbError;
+
+    fn from_str(s: &str) -> EasyDbResult<Self> {
+        let invalid = || EasyDbError::Value(format!("Invalid interval '{}'", s));
+        let overflow = || EasyDbError::Value(format!("Interval '{}' out of range", s));
+
+        let mut interval = Self::default();
+        let mut parts = s.split_whitespace();
+        let mut empty = true;
+        while let Some(quantity) = parts.next() {
+            empty = false;
+            let quantity: i64 = quantity.parse().map_err(|_| invalid())?;
+            let unit = parts.next().ok_or_else(invalid)?.to_lowercase();
+            let months = |n: Option<i64>| {
+                let months = i32::try_from(n?).ok()?;
+                Some(Self {
+                    months,
+                    ..Self::default()
+                })
+            };
+            let days = |n: Option<i64>| {
+                let days = i32::try_from(n?).ok()?;
+                Some(Self {
+                    days,
+                    ..Self::default()
+                })
+            };
+            let micros = |n: Option<i64>| {
+                Some(Self {
+                    micros: n?,
+                    ..Self::default()
+                })
+            };
+            let add = match unit.strip_suffix('s').unwrap_or(&unit) {
+                "year" => months(quantity.checked_mul(12)),
+                "month" | "mon" => months(Some(quantity)),
+                "week" => days(quantity.checked_mul(7)),
+                "day" => days(Some(quantity)),
+                "hour" => micros(quantity.checked_mul(MICROS_PER_HOUR)),
+                "minute" | "min" => micros(quantity.checked_mul(MICROS_PER_MINUTE)),
+                "second" | "sec" => micros(quantity.checked_mul(MICROS_PER_SECOND)),
+                "millisecond" | "msec" => micros(quantity.checked_mul(1000)),
+                "microsecond" | "usec" => micros(Some(quantity)),
+                _ => return Err(invalid()),
+            }
+            .ok_or_else(overflow)?;
+            interval = interval.checked_add(&add).map_err(|_| overflow())?;
+        }
+        if empty {
+            return Err(invalid());
+        }
+        Ok(interval)
+    }
+}
+
+/// Formats the interval as parseable text, e.g. '1 year 2 months 3 days
+/// 4 hours 5 minutes 6 seconds 7 microseconds', leaving out zero parts
+impl std::fmt::Display for Interval {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let parts = [
+            (i64::from(self.months / 12), "year"),
+            (i64::from(self.months % 12), "month"),
+            (i64::from(self.days), "day"),
+            (self.micros / MICROS_PER_HOUR, "hour"),
+            (self.micros % MICROS_PER_HOUR / MICROS_PER_MINUTE, "minute"),
+            (
+                self.micros % MICROS_PER_MINUTE / MICROS_PER_SECOND,
+                "second",
+            ),
+            (self.micros % MICROS_PER_SECOND, "microsecond"),
+        ];
+        let mut first = true;
+        for (quantity, unit) in parts.into_iter().filter(|(q, _)| *q != 0) {
+            if !first {
+                f.write_str(" ")?;
+            }
+            first = false;
+            write!(
+                f,
+                "{} {}{}",
+                quantity,
+                unit,
+                if quantity.abs() == 1 { "" } else { "s" }
+            )?;
+        }
+        if first {
+            f.write_str("0 seconds")?;
+        }
+        Ok(())
+    }
+}
+
+impl PartialEq for Interval {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Interval {}
+
+impl PartialOrd for Interval {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Interval {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.approximate_micros().cmp(&other.approximate_micros())
+    }
+}
+
+impl Hash for Interval {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.approximate_micros().hash(state)
+    }
+}
@@ -0,0 +1,66 @@
+use super::Value;
+use crate::error::{EasyDbError, EasyDbResult};
+
+/// JSON values are stored as compact JSON text with object keys sorted, so
+/// that equal documents compare and hash equal as plain strings
+pub fn normalize(text: &str) -> EasyDbResult<String> {
+    let json: serde_json::Value = serde_json::from_str(text)
+        .map_err(|err| EasyDbError::Value(format!("Invalid JSON: {}", err)))?;
+    Ok(json.to_string())
+}
+
+impl Value {
+    /// Parses JSON text into a JSON value
+    pub fn json(text: &str) -> EasyDbResult<Self> {
+        Ok(Self::Json(normalize(text)?))
+    }
+
+    /// Implements `json -> key`: extracts an object field by string key or
+    /// an array element by integer index, as JSON. Missing fields are NULL.
+    pub fn json_get(&self, key: &Value) -> EasyDbResult<Value> {
+        Ok(match self.json_lookup(key)? {
+            Some(json) => Self::Json(json.to_string()),
+            None => Self::Null,
+        })
+    }
+
+    /// Implements `json ->> key`: like json_get, but returns the element as
+    /// text, with strings unquoted and JSON null as NULL
+    pub fn json_get_text(&self, key: &Value) -> EasyDbResult<Value> {
+        Ok(match self.json_lookup(key)? {
+            Some(serde_json::Value::Null) | None => Self::Null,
+            Some(serde_json::Value::String(s)) => Self::String(s),
+            Some(json) => Self::String(json.to_string()),
+        })
+    }
+
+    fn json_lookup(&self, key: &Value) -> EasyDbResult<Option<serde_json::Value>> {
+        let text = match self {
+            Self::Null => return Ok(None),
+            Self::Json(text) => text,
+            value => {
+                return Err(EasyDbError::Value(format!(
+                    "Can't extract a JSON field from {}",
+                    value
+                )))
+            }
+        };
+        let mut json: serde_json::Value = serde_json::from_str(text)
+            .map_err(|err| EasyDbError::Internal(format!("Corrupt JSON value: {}", err)))?;
+        Ok(match (&mut json, key) {
+            (_, Self::Null) => None,
+            (serde_json::Value::Object(object), Self::String(field)) => object.remove(field),
+            (serde_json::Value::Array(array), Self::Integer(index)) => usize::try_from(*index)
+                .ok()
+                .filter(|&i| i < array.len())
+                .map(|i| array.swap_remove(i)),
+            (_, Self::String(_) | Self::Integer(_)) => None,
+            (_, key) => {
+                return Err(EasyDbError::Value(format!(
+                    "Invalid JSON key {}, expected a string or integer",
+                    key
+                )))
+            }
+        })
+    }
+}
@@ -1,10 +1,268 @@
+mod arithmetic;
+pub mod coerce;
+pub mod decimal;
+pub mod enums;
+pub mod interval;
+pub mod json;
+
+pub use decimal::Decimal;
+pub use enums::{EnumType, EnumValue};
+pub use interval::Interval;
+
 use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+use std::hash::{Hash, Hasher};
+
+/// The largest supported DECIMAL precision, in decimal digits
+pub const MAX_DECIMAL_PRECISION: u32 = 38;
 
 /// A datatype
 #[derive(Clone, Debug, Hash, PartialEq, Serialize, Deserialize)]
 pub enum DataType {
     Boolean,
-    Integer,
+    /// A 16-bit integer
+    SmallInt,
+    /// A 32-bit integer
+    Int,
+    /// A 64-bit integer
+    BigInt,
     Float,
-    String,
+    /// A string, with an optional maximum length in characters
+    String(Option<u32>),
+    /// A fixed-point number with the given precision and scale
+    Decimal(u32, u32),
+    Bytes,
+    Json,
+    Interval,
+    /// A user-defined enum type, by name. Values are stored as the ordinal
+    /// of their label.
+    Enum(String),
+}
+
+impl DataType {
+    /// The range of values of an integer type, or None for other types
+    pub fn integer_range(&self) -> Option<(i64, i64)> {
+        match self {
+            Self::SmallInt => Some((i16::MIN.into(), i16::MAX.into())),
+            Self::Int => Some((i32::MIN.into(), i32::MAX.into())),
+            Self::BigInt => Some((i64::MIN, i64::MAX)),
+            _ => None,
+        }
+    }
+}
+
+impl std::fmt::Display for DataType {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Self::Boolean => f.write_str("BOOLEAN"),
+            Self::SmallInt => f.write_str("SMALLINT"),
+            Self::Int => f.write_str("INTEGER"),
+            Self::BigInt => f.write_str("BIGINT"),
+            Self::Float => f.write_str("FLOAT"),
+            Self::String(None) => f.write_str("STRING"),
+            Self::String(Some(length)) => write!(f, "VARCHAR({})", length),
+            Self::Decimal(precision, scale) => write!(f, "DECIMAL({}, {})", precision, scale),
+            Self::Bytes => f.write_str("BYTEA"),
+            Self::Json => f.write_str("JSON"),
+            Self::Interval => f.write_str("INTERVAL"),
+            Self::Enum(name) => {
+                let mut chars = name.chars();
+                let plain = chars
+                    .next()
+                    .is_some_and(|c| c.is_ascii_lowercase() || c == '_')
+                    && chars.all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_');
+                match plain {
+                    true => f.write_str(name),
+                    false => write!(f, "\"{}\"", name.replace('"', "\"\"")),
+                }
+            }
+        }
+    }
+}
+
+/// A string collation, which determines how strings compare and sort
+#[derive(Clone, Copy, Debug, Default, Hash, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Collation {
+    /// Compares strings by their UTF-8 bytes
+    #[default]
+    Binary,
+    /// Compares strings ignoring case
+    NoCase,
+}
+
+impl Collation {
+    /// Looks up a collation by its (case-insensitive) name
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_ascii_uppercase().as_str() {
+            "BINARY" => Some(Self::Binary),
+            "NOCASE" => Some(Self::NoCase),
+            _ => None,
+        }
+    }
+
+    /// Returns a value as the collation sees it, so that values comparing
+    /// equal under it are equal: strings are lowercased under NOCASE
+    pub fn normalize(&self, value: &Value) -> Value {
+        match (value, self) {
+            (Value::String(s), Self::NoCase) => {
+                Value::String(s.chars().flat_map(char::to_lowercase).collect())
+            }
+            (value, _) => value.clone(),
+        }
+    }
+
+    pub fn compare(&self, a: &str, b: &str) -> Ordering {
+        match self {
+            Self::Binary => a.cmp(b),
+            Self::NoCase => a
+                .chars()
+                .flat_map(char::to_lowercase)
+                .cmp(b.chars().flat_map(char::to_lowercase)),
+        }
+    }
+}
+
+impl std::fmt::Display for Collation {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::Binary => "BINARY",
+            Self::NoCase => "NOCASE",
+        })
+    }
+}
+
+/// A runtime value. Values are totally ordered, with values of different
+/// types ordered by type (NULL first) and floats ordered by f64::total_cmp
+/// after folding -0.0 into 0.0 and all NaNs into one. Equality and hashing
+/// follow the same rules, so values can be used as sort and hash keys.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub enum Value {
+    Null,
+    Boolean(bool),
+    Integer(i64),
+    Float(f64),
+    String(String),
+    Decimal(Decimal),
+    Bytes(Vec<u8>),
+    /// A JSON document, as normalized JSON text (see json::normalize)
+    Json(String),
+    Interval(Interval),
+    Enum(EnumValue),
+}
+
+impl Value {
+    /// Returns the value's datatype, or None for NULL
+    pub fn datatype(&self) -> Option<DataType> {
+        match self {
+            Self::Null => None,
+            Self::Boolean(_) => Some(DataType::Boolean),
+            Self::Integer(_) => Some(DataType::BigInt),
+            Self::Float(_) => Some(DataType::Float),
+            Self::String(_) => Some(DataType::String(None)),
+            Self::Decimal(d) => Some(DataType::Decimal(d.precision(), d.scale())),
+            Self::Bytes(_) => Some(DataType::Bytes),
+            Self::Json(_) => Some(DataType::Json),
+            Self::Interval(_) => Some(DataType::Interval),
+            Self::Enum(e) => Some(DataType::Enum(e.datatype.clone())),
+        }
+    }
+
+    /// The position of the value's type in the ordering of values
+    fn rank(&self) -> u8 {
+        match self {
+            Self::Null => 0,
+            Self::Boolean(_) => 1,
+            Self::Integer(_) => 2,
+            Self::Float(_) => 3,
+            Self::String(_) => 4,
+            Self::Decimal(_) => 5,
+            Self::Bytes(_) => 6,
+            Self::Json(_) => 7,
+            Self::Interval(_) => 8,
+            Self::Enum(_) => 9,
+        }
+    }
+}
+
+/// Folds -0.0 into 0.0 and all NaNs into a single NaN, so that floats which
+/// compare equal also have the same bits
+fn normalize_float(f: f64) -> f64 {
+    if f == 0.0 {
+        0.0
+    } else if f.is_nan() {
+        f64::NAN
+    } else {
+        f
+    }
+}
+
+impl PartialEq for Value {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Value {}
+
+impl PartialOrd for Value {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Value {
+    fn cmp(&self, other: &Self) -> Ordering {
+        match (self, other) {
+            (Self::Null, Self::Null) => Ordering::Equal,
+            (Self::Boolean(a), Self::Boolean(b)) => a.cmp(b),
+            (Self::Integer(a), Self::Integer(b)) => a.cmp(b),
+            (Self::Float(a), Self::Float(b)) => normalize_float(*a).total_cmp(&normalize_float(*b)),
+            (Self::String(a), Self::String(b)) => a.cmp(b),
+            (Self::Decimal(a), Self::Decimal(b)) => a.cmp(b),
+            (Self::Bytes(a), Self::Bytes(b)) => a.cmp(b),
+            (Self::Json(a), Self::Json(b)) => a.cmp(b),
+            (Self::Interval(a), Self::Interval(b)) => a.cmp(b),
+            (Self::Enum(a), Self::Enum(b)) => a.cmp(b),
+            (a, b) => a.rank().cmp(&b.rank()),
+        }
+    }
+}
+
+impl Hash for Value {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.rank().hash(state);
+        match self {
+            Self::Null => {}
+            Self::Boolean(b) => b.hash(state),
+            Self::Integer(i) => i.hash(state),
+            Self::Float(f) => normalize_float(*f).to_bits().hash(state),
+            Self::String(s) => s.hash(state),
+            Self::Decimal(d) => d.hash(state),
+            Self::Bytes(b) => b.hash(state),
+            Self::Json(j) => j.hash(state),
+            Self::Interval(i) => i.hash(state),
+            Self::Enum(e) => e.hash(state),
+        }
+    }
+}
+
+impl std::fmt::Display for Value {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Self::Null => f.write_str("NULL"),
+            Self::Boolean(true) => f.write_str("TRUE"),
+            Self::Boolean(false) => f.write_str("FALSE"),
+            Self::Integer(i) => write!(f, "{}", i),
+            Self::Float(n) => write!(f, "{:?}", n),
+            Self::String(s) => f.write_str(s),
+            Self::Decimal(d) => write!(f, "{}", d),
+            Self::Bytes(bytes) => {
+                f.write_str("\\x")?;
+                bytes.iter().try_for_each(|b| write!(f, "{:02x}", b))
+            }
+            Self::Json(j) => f.write_str(j),
+            Self::Interval(i) => write!(f, "{}", i),
+            Self::Enum(e) => f.write_str(&e.label),
+        }
+    }
 }
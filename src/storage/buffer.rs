@@ -0,0 +1,436 @@
+use super::encryption::{self, Encryption};
+use super::CacheStatus;
+use crate::error::{EasyDbError, EasyDbResult};
+
+use std::collections::HashMap;
+use std::fs::{File, OpenOptions};
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+
+/// The size of a page in bytes
+pub const PAGE_SIZE: usize = 4096;
+
+/// The length of the checksum at the end of each page
+const CHECKSUM_LEN: usize = 4;
+
+/// The number of bytes a page holds. The rest is its checksum and room for
+/// encrypting it, which is left unused without encryption so that pages are
+/// the same size either way.
+pub const PAGE_DATA_SIZE: usize = PAGE_SIZE - CHECKSUM_LEN - encryption::OVERHEAD;
+
+/// A page number within a page file
+pub type PageId = u64;
+
+/// A file of fixed-size pages. Each page ends with a big-endian CRC32
+/// checksum of the rest, which is checked when it is read. With an
+/// encryption, pages are stored encrypted. Pages that were never written,
+/// including those past the end of the file, read as zeros.
+#[derive(Debug)]
+pub struct PageFile {
+    path: PathBuf,
+    file: File,
+    encryption: Option<Encryption>,
+}
+
+impl PageFile {
+    /// Opens the page file at the given path, creating it if it doesn't
+    /// exist. A file written with an encryption must be opened with the same
+    /// one.
+    pub fn open(path: impl AsRef<Path>, encryption: Option<Encryption>) -> EasyDbResult<Self> {
+        let path = path.as_ref().to_path_buf();
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(&path)?;
+        Ok(Self {
+            path,
+            file,
+            encryption,
+        })
+    }
+
+    fn read(&mut self, page: PageId, data: &mut [u8]) -> EasyDbResult<()> {
+        let mut buf = [0; PAGE_SIZE];
+        self.file.seek(SeekFrom::Start(page * PAGE_SIZE as u64))?;
+        let mut read = 0;
+        while read < PAGE_SIZE {
+            match self.file.read(&mut buf[read..])? {
+                0 => break,
+                n => read += n,
+            }
+        }
+        if buf.iter().all(|b| *b == 0) {
+            data.fill(0);
+            return Ok(());
+        }
+        let (stored, checksum) = buf.split_at(PAGE_SIZE - CHECKSUM_LEN);
+        if checksum != crc32fast::hash(stored).to_be_bytes() {
+            return Err(EasyDbError::Internal(format!(
+                "Checksum mismatch in page {} of {}, it is corrupted",
+                page,
+                self.path.display()
+            )));
+        }
+        match &self.encryption {
+            Some(encryption) => data.copy_from_slice(
+                &encryption
+                    .decrypt(stored, &format!("page {} of {}", page, self.path.display()))?,
+            ),
+            None => data.copy_from_slice(&stored[..PAGE_DATA_SIZE]),
+        }
+        Ok(())
+    }
+
+    fn write(&mut self, page: PageId, data: &[u8]) -> EasyDbResult<()> {
+        let mut buf = [0; PAGE_SIZE];
+        let stored = encryption::seal(&self.encryption, data)?;
+        buf[..stored.len()].copy_from_slice(&stored);
+        let checksum = crc32fast::hash(&buf[..PAGE_SIZE - CHECKSUM_LEN]);
+        buf[PAGE_SIZE - CHECKSUM_LEN..].copy_from_slice(&checksum.to_be_bytes());
+        self.file.seek(SeekFrom::Start(page * PAGE_SIZE as u64))?;
+        Ok(self.file.write_all(&buf)?)
+    }
+
+    /// Drops the pages from the given one on
+    fn truncate(&mut self, page: PageId) -> EasyDbResult<()> {
+        Ok(self.file.set_len(page * PAGE_SIZE as u64)?)
+    }
+
+    /// Returns the length of the file in bytes
+    fn size(&self) -> EasyDbResult<u64> {
+        Ok(self.file.metadata()?.len())
+    }
+}
+
+/// A frame of the buffer pool, holding a cached page
+#[derive(Debug)]
+struct Frame {
+    page: PageId,
+    data: Box<[u8]>,
+    /// The number of pins, while which the frame can't be evicted
+    pins: usize,
+    /// Whether the data was changed since it was read or written back
+    dirty: bool,
+    /// When the frame was last pinned, for LRU eviction
+    used: u64,
+}
+
+/// A fixed number of page frames caching a page file. A page is pinned
+/// while in use, which keeps it in memory. When a page that isn't cached is
+/// pinned and all frames are taken, the least recently used unpinned page is
+/// evicted, and written back first if it is dirty.
+#[derive(Debug)]
+pub struct BufferPool {
+    file: PageFile,
+    capacity: usize,
+    frames: Vec<Frame>,
+    /// The frame index of each cached page
+    pages: HashMap<PageId, usize>,
+    /// A counter advanced on every pin, ordering frames by last use
+    clock: u64,
+    /// The number of pins which found their page cached, and which didn't
+    hits: u64,
+    misses: u64,
+}
+
+impl BufferPool {
+    /// Creates a pool caching up to `capacity` pages of the file
+    pub fn new(file: PageFile, capacity: usize) -> EasyDbResult<Self> {
+        if capacity == 0 {
+            return Err(EasyDbError::Value(
+                "Buffer pool must hold at least one page".into(),
+            ));
+        }
+        Ok(Self {
+            file,
+            capacity,
+            frames: Vec::with_capacity(capacity),
+            pages: HashMap::new(),
+            clock: 0,
+            hits: 0,
+            misses: 0,
+        })
+    }
+
+    /// Pins a page, reading it into a frame if it isn't cached. Each pin
+    /// must be matched by an unpin().
+    pub fn pin(&mut self, page: PageId) -> EasyDbResult<()> {
+        self.clock += 1;
+        let index = match self.pages.get(&page) {
+            Some(index) => {
+                self.hits += 1;
+                *index
+            }
+            None => {
+                self.misses += 1;
+                self.load(page)?
+            }
+        };
+        let frame = &mut self.frames[index];
+        frame.pins += 1;
+        frame.used = self.clock;
+        Ok(())
+    }
+
+    /// Reads a page into a free frame, or into the frame of the least
+    /// recently used unpinned page, returning the frame index
+    fn load(&mut self, page: PageId) -> EasyDbResult<usize> {
+        let index = if self.frames.len() < self.capacity {
+            self.frames.push(Frame {
+                page,
+                data: vec![0; PAGE_DATA_SIZE].into_boxed_slice(),
+                pins: 0,
+                dirty: false,
+                used: 0,
+            });
+            self.frames.len() - 1
+        } else {
+            let index = self
+                .frames
+                .iter()
+                .enumerate()
+                .filter(|(_, frame)| frame.pins == 0)
+                .min_by_key(|(_, frame)| frame.used)
+                .map(|(index, _)| index)
+                .ok_or_else(|| {
+                    EasyDbError::Internal(format!(
+                        "Can't read page {}, all {} buffer frames are pinned",
+                        page, self.capacity
+                    ))
+                })?;
+            let frame = &mut self.frames[index];
+            if frame.dirty {
+                self.file.write(frame.page, &frame.data)?;
+                frame.dirty = false;
+            }
+            self.pages.remove(&frame.page);
+            frame.page = page;
+            index
+        };
+        let frame = &mut self.frames[index];
+        self.file.read(page, &mut frame.data)?;
+        self.pages.insert(page, index);
+        Ok(index)
+    }
+
+    /// Releases a pin on a page
+    pub fn unpin(&mut self, page: PageId) -> EasyDbResult<()> {
+        let frame = self.pinned(page)?;
+        frame.pins -= 1;
+        Ok(())
+    }
+
+    /// Returns the contents of a pinned page, PAGE_DATA_SIZE bytes long
+    pub fn read(&mut self, page: PageId) -> EasyDbResult<&[u8]> {
+        Ok(&self.pinned(page)?.data)
+    }
+
+    /// Returns the contents of a pinned page for changing, marking it dirty
+    pub fn write(&mut self, page: PageId) -> EasyDbResult<&mut [u8]> {
+        let frame = self.pinned(page)?;
+        frame.dirty = true;
+        Ok(&mut frame.data)
+    }
+
+    fn pinned(&mut self, page: PageId) -> EasyDbResult<&mut Frame> {
+        match self.pages.get(&page).map(|index| &mut self.frames[*index]) {
+            Some(frame) if frame.pins > 0 => Ok(frame),
+            _ => Err(EasyDbError::Internal(format!("Page {} isn't pinned", page))),
+        }
+    }
+
+    /// Drops the pages from the given one on, both cached and in the file,
+    /// so that they read as zeros. None of them may be pinned.
+    pub fn truncate(&mut self, page: PageId) -> EasyDbResult<()> {
+        if let Some(frame) = self
+            .frames
+            .iter()
+            .find(|frame| frame.page >= page && frame.pins > 0)
+        {
+            return Err(EasyDbError::Internal(format!(
+                "Can't truncate page {}, it is pinned",
+                frame.page
+            )));
+        }
+        for frame in self.frames.iter_mut().filter(|frame| frame.page >= page) {
+            self.pages.remove(&frame.page);
+            // The frame is now free, and is the first to be reused
+            frame.page = PageId::MAX;
+            frame.dirty = false;
+            frame.used = 0;
+        }
+        self.file.truncate(page)
+    }
+
+    /// Returns the length of the page file in bytes, which doesn't include
+    /// pages that are only cached so far
+    pub fn disk_size(&self) -> EasyDbResult<u64> {
+        self.file.size()
+    }
+
+    /// Reports the pool's cache statistics
+    pub fn status(&self) -> CacheStatus {
+        CacheStatus {
+            capacity: self.capacity as u64,
+            pages: self.pages.len() as u64,
+            hits: self.hits,
+            misses: self.misses,
+        }
+    }
+
+    /// Writes all dirty pages back to the file and syncs it
+    pub fn flush(&mut self) -> EasyDbResult<()> {
+        for frame in self.frames.iter_mut().filter(|frame| frame.dirty) {
+            self.file.write(frame.page, &frame.data)?;
+            frame.dirty = false;
+        }
+        Ok(self.file.file.sync_all()?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pool(path: &Path, capacity: usize) -> EasyDbResult<BufferPool> {
+        BufferPool::new(PageFile::open(path, None)?, capacity)
+    }
+
+    /// Pins a page, sets its first byte and unpins it
+    fn put(pool: &mut BufferPool, page: PageId, byte: u8) -> EasyDbResult<()> {
+        pool.pin(page)?;
+        pool.write(page)?[0] = byte;
+        pool.unpin(page)
+    }
+
+    /// Pins a page and returns its first byte, unpinning it
+    fn get(pool: &mut BufferPool, page: PageId) -> EasyDbResult<u8> {
+        pool.pin(page)?;
+        let byte = pool.read(page)?[0];
+        pool.unpin(page)?;
+        Ok(byte)
+    }
+
+    #[test]
+    fn pin_unpin() -> EasyDbResult<()> {
+        let dir = tempfile::tempdir()?;
+        let mut pool = pool(&dir.path().join("pages"), 2)?;
+        assert!(BufferPool::new(PageFile::open(dir.path().join("other"), None)?, 0).is_err());
+
+        // Pages read as zeros until written, and must be pinned to be used
+        assert!(pool.read(0).is_err());
+        pool.pin(0)?;
+        assert_eq!(pool.read(0)?, &[0; PAGE_DATA_SIZE][..]);
+        pool.write(0)?[..3].copy_from_slice(b"abc");
+        pool.pin(0)?;
+        pool.unpin(0)?;
+        assert_eq!(&pool.read(0)?[..3], b"abc");
+        pool.unpin(0)?;
+        assert!(pool.unpin(0).is_err());
+        assert!(pool.write(0).is_err());
+
+        // With every frame pinned, another page can't be read
+        pool.pin(0)?;
+        pool.pin(1)?;
+        assert!(pool.pin(2).is_err());
+        pool.unpin(1)?;
+        pool.pin(2)?;
+        assert!(pool.pin(1).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn eviction() -> EasyDbResult<()> {
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("pages");
+        let mut pool = pool(&path, 2)?;
+        put(&mut pool, 0, 10)?;
+        put(&mut pool, 1, 11)?;
+        assert_eq!(pool.disk_size()?, 0);
+
+        // Page 0 was used last, so reading page 2 evicts page 1, writing it
+        // back since it is dirty
+        assert_eq!(get(&mut pool, 0)?, 10);
+        put(&mut pool, 2, 12)?;
+        assert_eq!(pool.disk_size()?, 2 * PAGE_SIZE as u64);
+        assert_eq!(get(&mut pool, 1)?, 11);
+        assert_eq!(get(&mut pool, 0)?, 10);
+        assert_eq!(get(&mut pool, 2)?, 12);
+        let status = pool.status();
+        assert_eq!((status.pages, status.capacity), (2, 2));
+        assert_eq!((status.hits, status.misses), (1, 6));
+        assert_eq!(status.hit_rate(), 1.0 / 7.0);
+
+        // Flushing writes back the rest, which a new pool then reads
+        pool.flush()?;
+        drop(pool);
+        let mut pool = self::pool(&path, 1)?;
+        assert_eq!(get(&mut pool, 0)?, 10);
+        assert_eq!(get(&mut pool, 1)?, 11);
+        assert_eq!(get(&mut pool, 2)?, 12);
+        assert_eq!(get(&mut pool, 3)?, 0);
+        Ok(())
+    }
+
+    #[test]
+    fn truncate() -> EasyDbResult<()> {
+        let dir = tempfile::tempdir()?;
+        let mut pool = pool(&dir.path().join("pages"), 2)?;
+        for page in 0..4 {
+            put(&mut pool, page, page as u8 + 1)?;
+        }
+        pool.pin(3)?;
+        assert!(pool.truncate(1).is_err());
+        pool.unpin(3)?;
+        pool.truncate(1)?;
+        assert_eq!(pool.disk_size()?, PAGE_SIZE as u64);
+        assert_eq!(get(&mut pool, 0)?, 1);
+        for page in 1..4 {
+            assert_eq!(get(&mut pool, page)?, 0);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn checksum() -> EasyDbResult<()> {
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("pages");
+        let mut pool = pool(&path, 1)?;
+        put(&mut pool, 0, 1)?;
+        put(&mut pool, 1, 2)?;
+        pool.flush()?;
+        drop(pool);
+
+        let mut file = OpenOptions::new().write(true).open(&path)?;
+        file.seek(SeekFrom::Start(PAGE_SIZE as u64 + 1))?;
+        file.write_all(&[0xff])?;
+        let mut pool = self::pool(&path, 1)?;
+        assert_eq!(get(&mut pool, 0)?, 1);
+        assert!(pool.pin(1).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn encryption() -> EasyDbResult<()> {
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("pages");
+        let open =
+            |key: &[u8; 32]| BufferPool::new(PageFile::open(&path, Some(Encryption::new(key)))?, 1);
+        let mut pool = open(&[1; 32])?;
+        pool.pin(0)?;
+        pool.write(0)?[..6].copy_from_slice(b"secret");
+        pool.unpin(0)?;
+        pool.flush()?;
+        drop(pool);
+        assert!(!std::fs::read(&path)?.windows(6).any(|w| w == b"secret"));
+
+        let mut pool = open(&[1; 32])?;
+        pool.pin(0)?;
+        assert_eq!(&pool.read(0)?[..6], b"secret");
+        pool.unpin(0)?;
+        assert!(open(&[2; 32])?.pin(0).is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,63 @@
+use crate::error::{EasyDbError, EasyDbResult};
+
+/// How values are compressed when written. Each stored value starts with a
+/// byte naming its compression, so a database can be reopened with a
+/// different setting, and values that don't shrink are stored as is. The
+/// algorithms other than None are behind the lz4 and zstd features.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Compression {
+    #[default]
+    None,
+    /// LZ4, which is fast but compresses less
+    #[cfg(feature = "lz4")]
+    Lz4,
+    /// Zstandard at the given level, from 1 (fastest) to 22 (smallest)
+    #[cfg(feature = "zstd")]
+    Zstd(i32),
+}
+
+const NONE: u8 = 0;
+const LZ4: u8 = 1;
+const ZSTD: u8 = 2;
+
+impl Compression {
+    /// Compresses a value for storage, prefixed by its compression byte
+    pub(super) fn compress(&self, value: &[u8]) -> EasyDbResult<Vec<u8>> {
+        let compressed: Option<(u8, Vec<u8>)> = match self {
+            Self::None => None,
+            #[cfg(feature = "lz4")]
+            Self::Lz4 => Some((LZ4, lz4_flex::compress_prepend_size(value))),
+            #[cfg(feature = "zstd")]
+            Self::Zstd(level) => Some((ZSTD, zstd::encode_all(value, *level)?)),
+        };
+        let (kind, body) = match compressed {
+            Some((kind, body)) if body.len() < value.len() => (kind, body),
+            _ => (NONE, value.to_vec()),
+        };
+        let mut stored = Vec::with_capacity(1 + body.len());
+        stored.push(kind);
+        stored.extend(body);
+        Ok(stored)
+    }
+
+    /// Decompresses a stored value, whatever compression it was written with
+    pub(super) fn decompress(stored: &[u8]) -> EasyDbResult<Vec<u8>> {
+        match stored.split_first() {
+            Some((&NONE, value)) => Ok(value.to_vec()),
+            #[cfg(feature = "lz4")]
+            Some((&LZ4, body)) => lz4_flex::decompress_size_prepended(body)
+                .map_err(|err| EasyDbError::Internal(format!("Invalid LZ4 value: {}", err))),
+            #[cfg(not(feature = "lz4"))]
+            Some((&LZ4, _)) => Err(EasyDbError::Internal(
+                "Can't read a value compressed with LZ4, the lz4 feature is disabled".into(),
+            )),
+            #[cfg(feature = "zstd")]
+            Some((&ZSTD, body)) => Ok(zstd::decode_all(body)?),
+            #[cfg(not(feature = "zstd"))]
+            Some((&ZSTD, _)) => Err(EasyDbError::Internal(
+                "Can't read a value compressed with Zstandard, the zstd feature is disabled".into(),
+            )),
+            _ => Err(EasyDbError::Internal("Unknown value compression".into())),
+        }
+    }
+}
@@ -0,0 +1,131 @@
+use crate::error::{EasyDbError, EasyDbResult};
+
+use aes_gcm::aead::{Aead, AeadCore, KeyInit, OsRng};
+use aes_gcm::{Aes256Gcm, Nonce};
+
+/// The length of a nonce
+const NONCE_LEN: usize = 12;
+
+/// The number of bytes encryption adds: the nonce and the authentication tag
+pub const OVERHEAD: usize = NONCE_LEN + 16;
+
+/// AES-256-GCM encryption of stored data with a key supplied when opening
+/// the storage. Each piece of data is encrypted under a random nonce, which
+/// is stored in front of it, and its authentication tag makes reading it
+/// with the wrong key or after tampering fail rather than return garbage.
+#[derive(Clone)]
+pub struct Encryption {
+    cipher: Aes256Gcm,
+}
+
+impl Encryption {
+    /// Creates an encryption with a 256-bit key
+    pub fn new(key: &[u8; 32]) -> Self {
+        Self {
+            cipher: Aes256Gcm::new(key.into()),
+        }
+    }
+
+    /// Encrypts data, returning the nonce followed by the ciphertext and tag
+    pub(super) fn encrypt(&self, data: &[u8]) -> EasyDbResult<Vec<u8>> {
+        let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+        let ciphertext = self
+            .cipher
+            .encrypt(&nonce, data)
+            .map_err(|_| EasyDbError::Internal("Encryption failed".into()))?;
+        let mut sealed = nonce.to_vec();
+        sealed.extend(ciphertext);
+        Ok(sealed)
+    }
+
+    /// Decrypts data written by encrypt(). `what` describes where it was
+    /// read from, for the error when the key is wrong or the data damaged.
+    pub(super) fn decrypt(&self, sealed: &[u8], what: &str) -> EasyDbResult<Vec<u8>> {
+        let error = || {
+            EasyDbError::Internal(format!(
+                "Can't decrypt {}, the key is wrong or the data is damaged",
+                what
+            ))
+        };
+        if sealed.len() < OVERHEAD {
+            return Err(error());
+        }
+        let (nonce, ciphertext) = sealed.split_at(NONCE_LEN);
+        self.cipher
+            .decrypt(Nonce::from_slice(nonce), ciphertext)
+            .map_err(|_| error())
+    }
+}
+
+impl std::fmt::Debug for Encryption {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.write_str("Encryption { .. }")
+    }
+}
+
+/// Encrypts data if there is an encryption, or else returns it as is
+pub(super) fn seal(encryption: &Option<Encryption>, data: &[u8]) -> EasyDbResult<Vec<u8>> {
+    match encryption {
+        Some(encryption) => encryption.encrypt(data),
+        None => Ok(data.to_vec()),
+    }
+}
+
+/// Decrypts data written by seal() with the same encryption
+pub(super) fn unseal(
+    encryption: &Option<Encryption>,
+    data: &[u8],
+    what: &str,
+) -> EasyDbResult<Vec<u8>> {
+    match encryption {
+        Some(encryption) => encryption.decrypt(data, what),
+        None => Ok(data.to_vec()),
+    }
+}
+
+/// The number of bytes seal() adds
+pub(super) fn overhead(encryption: &Option<Encryption>) -> usize {
+    match encryption {
+        Some(_) => OVERHEAD,
+        None => 0,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn roundtrip() -> EasyDbResult<()> {
+        let encryption = Some(Encryption::new(&[7; 32]));
+        for data in [&b""[..], b"secret", &[0; 1000]] {
+            let sealed = seal(&encryption, data)?;
+            assert_eq!(sealed.len(), data.len() + overhead(&encryption));
+            assert_eq!(unseal(&encryption, &sealed, "test data")?, data);
+        }
+        // Each seal uses a fresh nonce
+        assert_ne!(seal(&encryption, b"x")?, seal(&encryption, b"x")?);
+        Ok(())
+    }
+
+    #[test]
+    fn none_is_plaintext() -> EasyDbResult<()> {
+        assert_eq!(seal(&None, b"data")?, b"data");
+        assert_eq!(unseal(&None, b"data", "test data")?, b"data");
+        assert_eq!(overhead(&None), 0);
+        Ok(())
+    }
+
+    #[test]
+    fn wrong_key_or_damage_is_error() -> EasyDbResult<()> {
+        let encryption = Some(Encryption::new(&[7; 32]));
+        let mut sealed = seal(&encryption, b"secret")?;
+        let wrong = Some(Encryption::new(&[8; 32]));
+        assert!(unseal(&wrong, &sealed, "test data").is_err());
+        let last = sealed.len() - 1;
+        sealed[last] ^= 1;
+        assert!(unseal(&encryption, &sealed, "test data").is_err());
+        assert!(unseal(&encryption, &sealed[..OVERHEAD - 1], "test data").is_err());
+        Ok(())
+    }
+}
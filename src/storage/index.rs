@@ -0,0 +1,424 @@
+use super::keycode::{encode_value, encode_values, write_bytes};
+use super::Engine;
+use crate::error::{EasyDbError, EasyDbResult};
+use crate::sql::schema::Table;
+use crate::sql::types::{Collation, Value};
+
+use std::collections::BTreeSet;
+use std::ops::Bound;
+
+/// A table's secondary indexes stored in an engine, one for each unique
+/// constraint and INDEX column. Each indexed tuple of values is stored
+/// under the key index/{table}/{columns}/{values}, encoded with keycode so
+/// that an index's entries are ordered by value, and maps to the primary
+/// keys of the rows holding it. The primary key itself isn't indexed here,
+/// since rows are stored under it.
+///
+/// Rows with a NULL in an index's columns aren't indexed by it, since NULL
+/// never matches a lookup nor duplicates another key. Strings in NOCASE
+/// columns are indexed lowercased, so lookups and unique constraints ignore
+/// case. Unique indexes reject values held by another row.
+#[derive(Clone, Debug)]
+pub struct SecondaryIndexes {
+    table: String,
+    /// The positions of the primary key columns
+    primary_key: Vec<usize>,
+    indexes: Vec<SecondaryIndex>,
+}
+
+#[derive(Clone, Debug)]
+struct SecondaryIndex {
+    name: String,
+    /// The names, positions and collations of the indexed columns
+    columns: Vec<(String, usize, Collation)>,
+    unique: bool,
+}
+
+impl SecondaryIndexes {
+    pub fn new(table: &Table) -> Self {
+        let position = |name: &str| table.columns.iter().position(|c| c.name == name);
+        let mut indexes: Vec<SecondaryIndex> = Vec::new();
+        for index in table.indexes().into_iter().filter(|index| !index.primary) {
+            let Some(columns) = index
+                .columns
+                .iter()
+                .map(|name| {
+                    let position = position(name)?;
+                    Some((name.clone(), position, table.columns[position].collation))
+                })
+                .collect::<Option<Vec<_>>>()
+            else {
+                continue;
+            };
+            // Columns both unique and INDEX need only the unique index
+            if indexes.iter().any(|i| i.columns == columns) {
+                continue;
+            }
+            indexes.push(SecondaryIndex {
+                name: index.name,
+                columns,
+                unique: index.unique,
+            });
+        }
+        Self {
+            table: table.name.clone(),
+            primary_key: table
+                .primary_key
+                .iter()
+                .filter_map(|c| position(c))
+                .collect(),
+            indexes,
+        }
+    }
+
+    /// Looks up the primary keys of the rows holding a value in an indexed
+    /// column, in primary key order
+    pub fn lookup(
+        &self,
+        engine: &mut dyn Engine,
+        column: &str,
+        value: &Value,
+    ) -> EasyDbResult<Vec<Vec<Value>>> {
+        let index = self.index(column)?;
+        match index.normalize(std::slice::from_ref(value)) {
+            Some(values) => Ok(self.read(engine, index, &values)?.into_iter().collect()),
+            None => Ok(Vec::new()),
+        }
+    }
+
+    /// Looks up the primary keys of the rows holding a tuple of values in
+    /// some columns, in primary key order. Returns None if no index covers
+    /// exactly those columns, in that order.
+    pub fn find(
+        &self,
+        engine: &mut dyn Engine,
+        columns: &[String],
+        values: &[Value],
+    ) -> EasyDbResult<Option<Vec<Vec<Value>>>> {
+        let Some(index) = self
+            .indexes
+            .iter()
+            .find(|index| index.columns.iter().map(|(name, _, _)| name).eq(columns))
+        else {
+            return Ok(None);
+        };
+        match index.normalize(values) {
+            Some(values) => Ok(Some(
+                self.read(engine, index, &values)?.into_iter().collect(),
+            )),
+            None => Ok(Some(Vec::new())),
+        }
+    }
+
+    /// Looks up the primary keys of the rows holding values within a range
+    /// in an indexed column, ordered by value and then primary key
+    pub fn range(
+        &self,
+        engine: &mut dyn Engine,
+        column: &str,
+        range: (Bound<Value>, Bound<Value>),
+    ) -> EasyDbResult<Vec<Vec<Value>>> {
+        let index = self.index(column)?;
+        let prefix = self.prefix(index);
+        let bound = |bound: Bound<Value>| {
+            bound.map(|value| {
+                let mut key = prefix.clone();
+                key.extend(encode_value(&index.columns[0].2.normalize(&value)));
+                key
+            })
+        };
+        let start = match bound(range.0) {
+            Bound::Unbounded => Bound::Included(prefix.clone()),
+            start => start,
+        };
+        let end = match bound(range.1) {
+            Bound::Unbounded => Bound::Excluded(self.prefix_end(index)),
+            end => end,
+        };
+        let mut keys = Vec::new();
+        for entry in engine.scan((start, end)) {
+            let (_, entry) = entry?;
+            keys.extend(bincode::deserialize::<BTreeSet<Vec<Value>>>(&entry)?);
+        }
+        Ok(keys)
+    }
+
+    /// Adds a row's index entries, failing without adding any if a unique
+    /// index already holds its values for another row
+    pub fn insert(&self, engine: &mut dyn Engine, row: &[Value]) -> EasyDbResult<()> {
+        let primary_key = self.primary_key(row);
+        let mut writes = Vec::new();
+        for index in &self.indexes {
+            let Some(values) = index.normalize(&index.values(row)) else {
+                continue;
+            };
+            let mut keys = self.read(engine, index, &values)?;
+            if index.unique && keys.iter().any(|key| *key != primary_key) {
+                let names: Vec<&str> = index.columns.iter().map(|(n, _, _)| n.as_str()).collect();
+                let values: Vec<String> = index.values(row).iter().map(|v| v.to_string()).collect();
+                return Err(EasyDbError::Value(format!(
+                    "Duplicate key ({}) = ({}) violates unique constraint {} of table {}",
+                    names.join(", "),
+                    values.join(", "),
+                    index.name,
+                    self.table
+                )));
+            }
+            keys.insert(primary_key.clone());
+            writes.push((index, values, keys));
+        }
+        for (index, values, keys) in writes {
+            self.write(engine, index, &values, &keys)?;
+        }
+        Ok(())
+    }
+
+    /// Removes a row's index entries, e.g. when it is deleted
+    pub fn delete(&self, engine: &mut dyn Engine, row: &[Value]) -> EasyDbResult<()> {
+        let primary_key = self.primary_key(row);
+        for index in &self.indexes {
+            let Some(values) = index.normalize(&index.values(row)) else {
+                continue;
+            };
+            let mut keys = self.read(engine, index, &values)?;
+            keys.remove(&primary_key);
+            self.write(engine, index, &values, &keys)?;
+        }
+        Ok(())
+    }
+
+    /// Replaces a row's index entries with those of its updated version,
+    /// keeping the old ones if the new ones would violate a unique index
+    pub fn update(
+        &self,
+        engine: &mut dyn Engine,
+        old: &[Value],
+        new: &[Value],
+    ) -> EasyDbResult<()> {
+        self.delete(engine, old)?;
+        if let Err(err) = self.insert(engine, new) {
+            self.insert(engine, old)?;
+            return Err(err);
+        }
+        Ok(())
+    }
+
+    /// Finds the index of a single column
+    fn index(&self, column: &str) -> EasyDbResult<&SecondaryIndex> {
+        self.indexes
+            .iter()
+            .find(|index| matches!(index.columns.as_slice(), [(name, _, _)] if name == column))
+            .ok_or_else(|| {
+                EasyDbError::Value(format!(
+                    "Column {} of table {} isn't indexed",
+                    column, self.table
+                ))
+            })
+    }
+
+    fn primary_key(&self, row: &[Value]) -> Vec<Value> {
+        self.primary_key.iter().map(|p| row[*p].clone()).collect()
+    }
+
+    /// The key prefix shared by an index's entries. The column names are
+    /// encoded as one byte string, so that no index's prefix is a prefix of
+    /// another's.
+    fn prefix(&self, index: &SecondaryIndex) -> Vec<u8> {
+        let mut columns = Vec::new();
+        for (name, _, _) in &index.columns {
+            write_bytes(&mut columns, name.as_bytes());
+        }
+        let mut key = Vec::new();
+        write_bytes(&mut key, b"index");
+        write_bytes(&mut key, self.table.as_bytes());
+        write_bytes(&mut key, &columns);
+        key
+    }
+
+    /// The first key after an index's entries. Every encoded value starts
+    /// with a type rank below 0xff.
+    fn prefix_end(&self, index: &SecondaryIndex) -> Vec<u8> {
+        let mut key = self.prefix(index);
+        key.push(0xff);
+        key
+    }
+
+    fn key(&self, index: &SecondaryIndex, values: &[Value]) -> Vec<u8> {
+        let mut key = self.prefix(index);
+        key.extend(encode_values(values));
+        key
+    }
+
+    fn read(
+        &self,
+        engine: &mut dyn Engine,
+        index: &SecondaryIndex,
+        values: &[Value],
+    ) -> EasyDbResult<BTreeSet<Vec<Value>>> {
+        match engine.get(&self.key(index, values))? {
+            Some(entry) => Ok(bincode::deserialize(&entry)?),
+            None => Ok(BTreeSet::new()),
+        }
+    }
+
+    /// Writes an index entry, deleting it once no rows hold the values
+    fn write(
+        &self,
+        engine: &mut dyn Engine,
+        index: &SecondaryIndex,
+        values: &[Value],
+        keys: &BTreeSet<Vec<Value>>,
+    ) -> EasyDbResult<()> {
+        let key = self.key(index, values);
+        match keys.is_empty() {
+            true => engine.delete(&key),
+            false => engine.set(&key, bincode::serialize(keys)?),
+        }
+    }
+}
+
+impl SecondaryIndex {
+    /// Returns a row's values in the indexed columns
+    fn values(&self, row: &[Value]) -> Vec<Value> {
+        self.columns
+            .iter()
+            .map(|(_, p, _)| row[*p].clone())
+            .collect()
+    }
+
+    /// Returns values of the indexed columns as indexed, or None if one is
+    /// NULL
+    fn normalize(&self, values: &[Value]) -> Option<Vec<Value>> {
+        self.columns
+            .iter()
+            .zip(values)
+            .map(|((_, _, collation), value)| match value {
+                Value::Null => None,
+                value => Some(collation.normalize(value)),
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::Memory;
+    use super::*;
+    use crate::sql::parser::ast::{Parser, Statement};
+
+    fn indexes(sql: &str) -> SecondaryIndexes {
+        let Statement::CreateTable {
+            name,
+            columns,
+            constraints,
+        } = Parser::new(sql).parse().unwrap()
+        else {
+            panic!("expected CREATE TABLE");
+        };
+        SecondaryIndexes::new(&Table::from_ast(&name, &columns, &constraints).unwrap())
+    }
+
+    fn row(id: i64, a: Value, b: Value) -> Vec<Value> {
+        vec![Value::Integer(id), a, b]
+    }
+
+    fn string(s: &str) -> Value {
+        Value::String(s.into())
+    }
+
+    #[test]
+    fn unique_single_column() -> EasyDbResult<()> {
+        let indexes =
+            indexes("CREATE TABLE t (id INT PRIMARY KEY, a TEXT UNIQUE COLLATE NOCASE, b INT)");
+        let mut engine = Memory::new();
+        indexes.insert(&mut engine, &row(1, string("x"), Value::Null))?;
+        // The same row may be indexed again, e.g. by an update
+        indexes.insert(&mut engine, &row(1, string("x"), Value::Null))?;
+        let err = indexes
+            .insert(&mut engine, &row(2, string("X"), Value::Null))
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            EasyDbError::Value(
+                "Duplicate key (a) = (X) violates unique constraint t_a_key of table t".into()
+            )
+            .to_string()
+        );
+        // NULLs are never duplicates
+        indexes.insert(&mut engine, &row(3, Value::Null, Value::Null))?;
+        indexes.insert(&mut engine, &row(4, Value::Null, Value::Null))?;
+        assert_eq!(
+            indexes.lookup(&mut engine, "a", &string("X"))?,
+            vec![vec![Value::Integer(1)]]
+        );
+        assert!(indexes
+            .lookup(&mut engine, "b", &Value::Integer(1))
+            .is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn unique_multiple_columns() -> EasyDbResult<()> {
+        let indexes = indexes("CREATE TABLE t (id INT PRIMARY KEY, a TEXT, b INT, UNIQUE (a, b))");
+        let mut engine = Memory::new();
+        indexes.insert(&mut engine, &row(1, string("x"), Value::Integer(1)))?;
+        indexes.insert(&mut engine, &row(2, string("x"), Value::Integer(2)))?;
+        indexes.insert(&mut engine, &row(3, string("y"), Value::Integer(1)))?;
+        indexes.insert(&mut engine, &row(4, string("x"), Value::Null))?;
+        indexes.insert(&mut engine, &row(5, string("x"), Value::Null))?;
+        let err = indexes
+            .insert(&mut engine, &row(6, string("x"), Value::Integer(2)))
+            .unwrap_err();
+        assert!(err.to_string().contains("(a, b) = (x, 2)"), "{}", err);
+
+        // Deleting a row frees its key
+        indexes.delete(&mut engine, &row(2, string("x"), Value::Integer(2)))?;
+        indexes.insert(&mut engine, &row(6, string("x"), Value::Integer(2)))?;
+
+        let columns = ["a".to_string(), "b".to_string()];
+        let find = |engine: &mut Memory, a: &str, b: Value| {
+            indexes.find(engine, &columns, &[string(a), b])
+        };
+        assert_eq!(
+            find(&mut engine, "x", Value::Integer(1))?,
+            Some(vec![vec![Value::Integer(1)]])
+        );
+        assert_eq!(find(&mut engine, "x", Value::Integer(3))?, Some(vec![]));
+        assert_eq!(find(&mut engine, "x", Value::Null)?, Some(vec![]));
+        let reversed = ["b".to_string(), "a".to_string()];
+        assert_eq!(
+            indexes.find(&mut engine, &reversed, &[Value::Integer(1), string("x")])?,
+            None
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn update_keeps_old_entries_on_violation() -> EasyDbResult<()> {
+        let indexes = indexes("CREATE TABLE t (id INT PRIMARY KEY, a INT UNIQUE, b INT INDEX)");
+        let mut engine = Memory::new();
+        let one = row(1, Value::Integer(10), Value::Integer(5));
+        let two = row(2, Value::Integer(20), Value::Integer(5));
+        indexes.insert(&mut engine, &one)?;
+        indexes.insert(&mut engine, &two)?;
+        let clash = row(2, Value::Integer(10), Value::Integer(6));
+        assert!(indexes.update(&mut engine, &two, &clash).is_err());
+        assert_eq!(
+            indexes.lookup(&mut engine, "a", &Value::Integer(20))?,
+            vec![vec![Value::Integer(2)]]
+        );
+        assert_eq!(
+            indexes.lookup(&mut engine, "b", &Value::Integer(5))?,
+            vec![vec![Value::Integer(1)], vec![Value::Integer(2)]]
+        );
+        assert_eq!(
+            indexes.range(
+                &mut engine,
+                "a",
+                (Bound::Excluded(Value::Integer(10)), Bound::Unbounded)
+            )?,
+            vec![vec![Value::Integer(2)]]
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,337 @@
+use crate::error::{EasyDbError, EasyDbResult};
+use crate::sql::types::{Decimal, EnumValue, Interval, Value, MAX_DECIMAL_PRECISION};
+
+/// Order-preserving encodings of values into keys: the encodings of two
+/// values compare bytewise the same way the values do, so range scans over
+/// encoded keys return them in SQL order. Tuples of values are encoded by
+/// concatenation, since no value's encoding is a prefix of another's.
+///
+/// Each value starts with a byte giving its type's rank in the order of
+/// Value, followed by:
+///
+/// * Boolean: 0 or 1
+/// * Integer: big-endian, with the sign bit flipped
+/// * Float: big-endian after normalizing -0.0 and NaN, with the sign bit
+///   flipped for positive floats and all bits flipped for negative ones
+/// * String, Bytes, Json: the bytes with 0x00 escaped as 0x00 0xff, ending
+///   with 0x00 0x00
+/// * Decimal: the integer part and the fraction scaled to 38 digits, both
+///   as 128-bit integers like Integer. Trailing zeros of the fraction aren't
+///   kept, so 1.50 decodes as 1.5.
+/// * Interval: the approximate length in microseconds as a 128-bit integer,
+///   then the months, days and microseconds. Intervals which compare equal
+///   but differ in their parts, like 1 month and 30 days, get distinct keys.
+/// * Enum: the ordinal as a big-endian 32-bit integer, then the label and
+///   the type name like String
+pub fn encode_value(value: &Value) -> Vec<u8> {
+    let mut key = Vec::new();
+    write_value(&mut key, value);
+    key
+}
+
+/// Encodes a tuple of values, ordered like the values in turn
+pub fn encode_values(values: &[Value]) -> Vec<u8> {
+    let mut key = Vec::new();
+    values.iter().for_each(|value| write_value(&mut key, value));
+    key
+}
+
+/// Decodes a value encoded by encode_value()
+pub fn decode_value(key: &[u8]) -> EasyDbResult<Value> {
+    let mut key = key;
+    let value = read_value(&mut key)?;
+    match key.is_empty() {
+        true => Ok(value),
+        false => Err(invalid()),
+    }
+}
+
+/// Decodes a tuple of values encoded by encode_values()
+pub fn decode_values(key: &[u8]) -> EasyDbResult<Vec<Value>> {
+    let mut key = key;
+    let mut values = Vec::new();
+    while !key.is_empty() {
+        values.push(read_value(&mut key)?);
+    }
+    Ok(values)
+}
+
+/// Encodes a byte string, ordered bytewise. Used for the parts of keys that
+/// aren't values, like table names.
+pub fn write_bytes(key: &mut Vec<u8>, bytes: &[u8]) {
+    for byte in bytes {
+        match byte {
+            0x00 => key.extend([0x00, 0xff]),
+            byte => key.push(*byte),
+        }
+    }
+    key.extend([0x00, 0x00]);
+}
+
+fn write_value(key: &mut Vec<u8>, value: &Value) {
+    match value {
+        Value::Null => key.push(0),
+        Value::Boolean(b) => key.extend([1, *b as u8]),
+        Value::Integer(i) => {
+            key.push(2);
+            key.extend((*i as u64 ^ 1 << 63).to_be_bytes());
+        }
+        Value::Float(f) => {
+            key.push(3);
+            let f = match f {
+                f if *f == 0.0 => 0.0,
+                f if f.is_nan() => f64::NAN,
+                f => *f,
+            };
+            let bits = f.to_bits();
+            let bits = match bits >> 63 {
+                0 => bits ^ 1 << 63,
+                _ => !bits,
+            };
+            key.extend(bits.to_be_bytes());
+        }
+        Value::String(s) => {
+            key.push(4);
+            write_bytes(key, s.as_bytes());
+        }
+        Value::Decimal(d) => {
+            key.push(5);
+            let unit = 10i128.pow(d.scale());
+            let fraction = d.mantissa() % unit * 10i128.pow(MAX_DECIMAL_PRECISION - d.scale());
+            write_i128(key, d.mantissa() / unit);
+            write_i128(key, fraction);
+        }
+        Value::Bytes(b) => {
+            key.push(6);
+            write_bytes(key, b);
+        }
+        Value::Json(j) => {
+            key.push(7);
+            write_bytes(key, j.as_bytes());
+        }
+        Value::Interval(i) => {
+            key.push(8);
+            write_i128(key, i.approximate_micros());
+            key.extend((i.months as u32 ^ 1 << 31).to_be_bytes());
+            key.extend((i.days as u32 ^ 1 << 31).to_be_bytes());
+            key.extend((i.micros as u64 ^ 1 << 63).to_be_bytes());
+        }
+        Value::Enum(e) => {
+            key.push(9);
+            key.extend(e.ordinal.to_be_bytes());
+            write_bytes(key, e.label.as_bytes());
+            write_bytes(key, e.datatype.as_bytes());
+        }
+    }
+}
+
+fn write_i128(key: &mut Vec<u8>, i: i128) {
+    key.extend((i as u128 ^ 1 << 127).to_be_bytes());
+}
+
+fn invalid() -> EasyDbError {
+    EasyDbError::Internal("Invalid key encoding".into())
+}
+
+/// Takes the next n bytes of a key
+fn take<'a>(key: &mut &'a [u8], n: usize) -> EasyDbResult<&'a [u8]> {
+    if key.len() < n {
+        return Err(invalid());
+    }
+    let (bytes, rest) = key.split_at(n);
+    *key = rest;
+    Ok(bytes)
+}
+
+fn take_array<const N: usize>(key: &mut &[u8]) -> EasyDbResult<[u8; N]> {
+    Ok(take(key, N)?.try_into().unwrap())
+}
+
+/// Reads a byte string written by write_bytes()
+pub fn read_bytes(key: &mut &[u8]) -> EasyDbResult<Vec<u8>> {
+    let mut bytes = Vec::new();
+    loop {
+        match take(key, 1)?[0] {
+            0x00 => match take(key, 1)?[0] {
+                0x00 => return Ok(bytes),
+                0xff => bytes.push(0x00),
+                _ => return Err(invalid()),
+            },
+            byte => bytes.push(byte),
+        }
+    }
+}
+
+fn read_string(key: &mut &[u8]) -> EasyDbResult<String> {
+    String::from_utf8(read_bytes(key)?).map_err(|_| invalid())
+}
+
+fn read_i128(key: &mut &[u8]) -> EasyDbResult<i128> {
+    Ok((u128::from_be_bytes(take_array(key)?) ^ 1 << 127) as i128)
+}
+
+fn read_value(key: &mut &[u8]) -> EasyDbResult<Value> {
+    Ok(match take(key, 1)?[0] {
+        0 => Value::Null,
+        1 => match take(key, 1)?[0] {
+            0 => Value::Boolean(false),
+            1 => Value::Boolean(true),
+            _ => return Err(invalid()),
+        },
+        2 => Value::Integer((u64::from_be_bytes(take_array(key)?) ^ 1 << 63) as i64),
+        3 => {
+            let bits = u64::from_be_bytes(take_array(key)?);
+            let bits = match bits >> 63 {
+                1 => bits ^ 1 << 63,
+                _ => !bits,
+            };
+            Value::Float(f64::from_bits(bits))
+        }
+        4 => Value::String(read_string(key)?),
+        5 => {
+            let integer = read_i128(key)?;
+            let mut fraction = read_i128(key)?;
+            // Use the smallest scale that holds the fraction
+            let mut scale = MAX_DECIMAL_PRECISION;
+            while scale > 0 && fraction % 10 == 0 {
+                fraction /= 10;
+                scale -= 1;
+            }
+            let mantissa = integer
+                .checked_mul(10i128.pow(scale))
+                .and_then(|i| i.checked_add(fraction))
+                .ok_or_else(invalid)?;
+            Value::Decimal(Decimal::new(mantissa, scale).map_err(|_| invalid())?)
+        }
+        6 => Value::Bytes(read_bytes(key)?),
+        7 => Value::Json(read_string(key)?),
+        8 => {
+            read_i128(key)?;
+            Value::Interval(Interval {
+                months: (u32::from_be_bytes(take_array(key)?) ^ 1 << 31) as i32,
+                days: (u32::from_be_bytes(take_array(key)?) ^ 1 << 31) as i32,
+                micros: (u64::from_be_bytes(take_array(key)?) ^ 1 << 63) as i64,
+            })
+        }
+        9 => Value::Enum(EnumValue {
+            ordinal: u32::from_be_bytes(take_array(key)?),
+            label: read_string(key)?,
+            datatype: read_string(key)?,
+        }),
+        _ => return Err(invalid()),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Values of every type, in ascending order
+    fn values() -> Vec<Value> {
+        vec![
+            Value::Null,
+            Value::Boolean(false),
+            Value::Boolean(true),
+            Value::Integer(i64::MIN),
+            Value::Integer(-1),
+            Value::Integer(0),
+            Value::Integer(1),
+            Value::Integer(i64::MAX),
+            Value::Float(f64::NEG_INFINITY),
+            Value::Float(-1.5),
+            Value::Float(0.0),
+            Value::Float(0.25),
+            Value::Float(f64::INFINITY),
+            Value::Float(f64::NAN),
+            Value::String("".into()),
+            Value::String("\0".into()),
+            Value::String("\0\0".into()),
+            Value::String("a".into()),
+            Value::String("a\0b".into()),
+            Value::String("ab".into()),
+            Value::Decimal("-10.5".parse().unwrap()),
+            Value::Decimal("-1.5".parse().unwrap()),
+            Value::Decimal("-1.25".parse().unwrap()),
+            Value::Decimal("-0.001".parse().unwrap()),
+            Value::Decimal("0".parse().unwrap()),
+            Value::Decimal("0.001".parse().unwrap()),
+            Value::Decimal("1.25".parse().unwrap()),
+            Value::Decimal("1.5".parse().unwrap()),
+            Value::Bytes(vec![]),
+            Value::Bytes(vec![0]),
+            Value::Bytes(vec![0, 0xff]),
+            Value::Bytes(vec![0xff]),
+            Value::Json("[]".into()),
+            Value::Json("{}".into()),
+            Value::Interval("-1 day".parse().unwrap()),
+            Value::Interval("1 hour".parse().unwrap()),
+            Value::Interval("1 day".parse().unwrap()),
+            Value::Interval("1 month".parse().unwrap()),
+            Value::Enum(EnumValue {
+                ordinal: 0,
+                label: "z".into(),
+                datatype: "mood".into(),
+            }),
+            Value::Enum(EnumValue {
+                ordinal: 1,
+                label: "a".into(),
+                datatype: "mood".into(),
+            }),
+        ]
+    }
+
+    #[test]
+    fn roundtrip() -> EasyDbResult<()> {
+        for value in values() {
+            assert_eq!(decode_value(&encode_value(&value))?, value);
+        }
+        assert_eq!(decode_values(&encode_values(&values()))?, values());
+        assert_eq!(decode_values(&[])?, vec![]);
+        Ok(())
+    }
+
+    #[test]
+    fn order_is_preserved() {
+        let values = values();
+        for pair in values.windows(2) {
+            assert!(pair[0] < pair[1], "{:?} < {:?}", pair[0], pair[1]);
+            assert!(
+                encode_value(&pair[0]) < encode_value(&pair[1]),
+                "{:?} < {:?}",
+                pair[0],
+                pair[1]
+            );
+        }
+        // Tuples order by their first value, then by the next
+        let tuple = |a: i64, b: &str| encode_values(&[Value::Integer(a), Value::String(b.into())]);
+        assert!(tuple(1, "b") < tuple(2, "a"));
+        assert!(tuple(1, "a") < tuple(1, "a\0"));
+        assert!(tuple(1, "a") < tuple(1, "b"));
+    }
+
+    #[test]
+    fn bytes() -> EasyDbResult<()> {
+        let mut key = Vec::new();
+        write_bytes(&mut key, b"a\0b");
+        write_bytes(&mut key, b"");
+        let mut rest = &key[..];
+        assert_eq!(read_bytes(&mut rest)?, b"a\0b");
+        assert_eq!(read_bytes(&mut rest)?, b"");
+        assert!(rest.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn invalid_is_error() {
+        for value in values() {
+            let key = encode_value(&value);
+            for len in 0..key.len() {
+                assert!(decode_value(&key[..len]).is_err(), "{:?} at {}", value, len);
+            }
+            let mut key = key;
+            key.push(0);
+            assert!(decode_value(&key).is_err());
+        }
+        assert!(decode_value(&[0xff]).is_err());
+    }
+}
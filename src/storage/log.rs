@@ -0,0 +1,584 @@
+use super::encryption::{overhead, seal, unseal};
+use super::{Compression, Encryption, Engine, ScanIterator, Status, SyncPolicy, Syncer};
+use crate::error::{EasyDbError, EasyDbResult};
+
+use std::collections::BTreeMap;
+use std::fs::{File, OpenOptions};
+use std::io::{BufReader, BufWriter, Read, Seek, SeekFrom, Write};
+use std::ops::Bound;
+use std::path::{Path, PathBuf};
+
+/// The value length marking a deletion
+pub(super) const TOMBSTONE: u32 = u32::MAX;
+
+/// The length of an entry header: the checksum, key length and value length
+pub(super) const HEADER_LEN: u64 = 12;
+
+/// The amount of garbage below which flush() doesn't compact the log
+pub const COMPACT_MIN_GARBAGE: u64 = 1 << 20;
+
+/// An engine storing its data in an append-only log file, with an in-memory
+/// index of where each key's latest value is. Each entry is a big-endian u32
+/// CRC32 checksum, key length and value length followed by the key and
+/// value, with a deletion written as a value length of u32::MAX and no
+/// value. The checksum covers the rest of the entry, and is checked whenever
+/// the entry is read. Values are stored compressed according to the
+/// compression setting, and with an encryption both keys and values are
+/// stored encrypted.
+///
+/// Writes are synced by flush() according to the sync policy. Opening the
+/// log replays it to rebuild the index, dropping an entry cut short by a
+/// crash, and reports what it found through recovery().
+///
+/// Replaced values and deletions are garbage, which compact() removes by
+/// rewriting the log. This happens by itself when opening the log once
+/// garbage takes up as much space as the live entries, and when flushing
+/// once it also exceeds COMPACT_MIN_GARBAGE.
+#[derive(Debug)]
+pub struct Log {
+    path: PathBuf,
+    file: File,
+    /// The position and length of each key's value
+    keydir: BTreeMap<Vec<u8>, (u64, u32)>,
+    /// The number of bytes taken up by garbage entries
+    garbage: u64,
+    compression: Compression,
+    encryption: Option<Encryption>,
+    syncer: Syncer,
+    recovery: Recovery,
+}
+
+impl Log {
+    /// Opens the log at the given path, creating it if it doesn't exist.
+    /// Values written from now on are compressed as given. A log written
+    /// with an encryption must be opened with the same one.
+    pub fn open(
+        path: impl AsRef<Path>,
+        sync: SyncPolicy,
+        compression: Compression,
+        encryption: Option<Encryption>,
+    ) -> EasyDbResult<Self> {
+        let path = path.as_ref().to_path_buf();
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(&path)?;
+        let mut log = Self {
+            path,
+            file,
+            keydir: BTreeMap::new(),
+            garbage: 0,
+            compression,
+            encryption,
+            syncer: Syncer::new(sync),
+            recovery: Recovery::default(),
+        };
+        log.load()?;
+        if log.garbage > 0 && log.garbage >= log.live_size() {
+            log.compact()?;
+        }
+        Ok(log)
+    }
+
+    /// Builds the key directory by replaying the log
+    fn load(&mut self) -> EasyDbResult<()> {
+        let (keydir, garbage, encryption) = (&mut self.keydir, &mut self.garbage, &self.encryption);
+        self.recovery = replay(&mut self.file, &self.path, |key, value| {
+            let key_len = key.len() as u64;
+            let key = unseal(encryption, &key, "a log entry's key")?;
+            let old = match value {
+                Some(value) => keydir.insert(key, value),
+                None => {
+                    *garbage += HEADER_LEN + key_len;
+                    keydir.remove(&key)
+                }
+            };
+            if let Some((_, old_len)) = old {
+                *garbage += HEADER_LEN + key_len + old_len as u64;
+            }
+            Ok(())
+        })?;
+        Ok(())
+    }
+
+    /// The number of bytes taken up by live entries
+    fn live_size(&self) -> u64 {
+        self.keydir
+            .iter()
+            .map(|(key, (_, len))| HEADER_LEN + self.stored_key_len(key) + *len as u64)
+            .sum()
+    }
+
+    /// The length of a key as stored, encrypted or not
+    fn stored_key_len(&self, key: &[u8]) -> u64 {
+        (key.len() + overhead(&self.encryption)) as u64
+    }
+
+    /// Rewrites the log with only the live entries, dropping replaced values
+    /// and deletions. The new log is written to a temporary file which then
+    /// replaces the old one, so a crash leaves one of them intact.
+    pub fn compact(&mut self) -> EasyDbResult<()> {
+        let tmp = self.path.with_extension("compact");
+        let mut keydir = BTreeMap::new();
+        let mut writer = BufWriter::new(File::create(&tmp)?);
+        let mut pos = 0;
+        for (key, (value_pos, value_len)) in &self.keydir {
+            let key_len = self.stored_key_len(key);
+            let value = read_value(&mut self.file, &self.path, key_len, *value_pos, *value_len)?;
+            writer.write_all(&encode(&seal(&self.encryption, key)?, Some(&value))?)?;
+            pos += HEADER_LEN + key_len;
+            keydir.insert(key.clone(), (pos, *value_len));
+            pos += *value_len as u64;
+        }
+        writer
+            .into_inner()
+            .map_err(|err| EasyDbError::Internal(err.to_string()))?
+            .sync_all()?;
+        std::fs::rename(&tmp, &self.path)?;
+        self.file = OpenOptions::new().read(true).write(true).open(&self.path)?;
+        self.keydir = keydir;
+        self.garbage = 0;
+        Ok(())
+    }
+
+    /// Reports what was recovered when the log was opened
+    pub fn recovery(&self) -> Recovery {
+        self.recovery
+    }
+
+    /// Appends an entry, encrypting its key, returning the position of its
+    /// value
+    fn append(&mut self, key: &[u8], value: Option<&[u8]>) -> EasyDbResult<u64> {
+        let key = seal(&self.encryption, key)?;
+        let pos = self.file.seek(SeekFrom::End(0))?;
+        self.file.write_all(&encode(&key, value)?)?;
+        Ok(pos + HEADER_LEN + key.len() as u64)
+    }
+
+    /// Reads the value of a key in the key directory
+    fn read(&mut self, key: &[u8], pos: u64, len: u32) -> EasyDbResult<Vec<u8>> {
+        read_live(&mut self.file, &self.path, &self.encryption, key, pos, len)
+    }
+}
+
+/// What opening a log file recovered after a clean or unclean shutdown
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Recovery {
+    /// The number of complete entries read back
+    pub entries: u64,
+    /// The length of an incomplete entry at the end, left by a crash in the
+    /// middle of a write, which was cut off
+    pub truncated: u64,
+}
+
+impl std::fmt::Display for Recovery {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "replayed {} entries", self.entries)?;
+        if self.truncated > 0 {
+            write!(
+                f,
+                ", truncated {} bytes of an incomplete entry",
+                self.truncated
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Reads a log file's entries from the start, passing each key to `apply`
+/// along with the position and length of its value, or None for a
+/// deletion. An incomplete entry at the end is truncated away, as is a last
+/// entry failing its checksum, which a crash can leave behind when the
+/// lengths reached the disk but the rest didn't. A damaged entry anywhere
+/// else is an error, including one whose damaged length runs past the end
+/// of the file while valid entries follow it.
+pub(super) fn replay(
+    file: &mut File,
+    path: &Path,
+    mut apply: impl FnMut(Vec<u8>, Option<(u64, u32)>) -> EasyDbResult<()>,
+) -> EasyDbResult<Recovery> {
+    let len = file.metadata()?.len();
+    let mut reader = BufReader::new(&mut *file);
+    reader.seek(SeekFrom::Start(0))?;
+    let mut recovery = Recovery::default();
+    let mut pos = 0;
+    while pos + HEADER_LEN <= len {
+        let mut header = [0; HEADER_LEN as usize];
+        reader.read_exact(&mut header)?;
+        let (key_len, value_len, entry_len) = lengths(&header);
+        if pos + entry_len > len {
+            if valid_entry_within(reader.get_mut(), pos + 1, len)? {
+                return Err(corrupted(path, pos));
+            }
+            break;
+        }
+        let mut entry = header.to_vec();
+        entry.resize(entry_len as usize, 0);
+        reader.read_exact(&mut entry[HEADER_LEN as usize..])?;
+        if !verify(&entry) {
+            if pos + entry_len == len {
+                break;
+            }
+            return Err(corrupted(path, pos));
+        }
+        let key = entry[HEADER_LEN as usize..][..key_len as usize].to_vec();
+        let value_pos = pos + HEADER_LEN + key_len as u64;
+        apply(
+            key,
+            (value_len != TOMBSTONE).then_some((value_pos, value_len)),
+        )?;
+        recovery.entries += 1;
+        pos += entry_len;
+    }
+    if pos < len {
+        file.set_len(pos)?;
+        recovery.truncated = len - pos;
+    }
+    Ok(recovery)
+}
+
+/// Reads the key and value lengths from the start of an entry, along with
+/// the length of the whole entry
+fn lengths(entry: &[u8]) -> (u32, u32, u64) {
+    let key_len = u32::from_be_bytes(entry[4..8].try_into().unwrap());
+    let value_len = u32::from_be_bytes(entry[8..12].try_into().unwrap());
+    let stored_len = if value_len == TOMBSTONE { 0 } else { value_len };
+    (
+        key_len,
+        value_len,
+        HEADER_LEN + key_len as u64 + stored_len as u64,
+    )
+}
+
+/// Checks whether a complete entry passing its checksum starts anywhere
+/// between two positions of a file. An entry torn by a crash is the last
+/// one written, so nothing valid follows it, whereas one with a damaged
+/// length is followed by the rest of the log.
+fn valid_entry_within(file: &mut File, start: u64, end: u64) -> EasyDbResult<bool> {
+    let mut data = Vec::new();
+    file.seek(SeekFrom::Start(start))?;
+    file.take(end - start).read_to_end(&mut data)?;
+    Ok((0..data.len()).any(|i| {
+        let entry = &data[i..];
+        if entry.len() < HEADER_LEN as usize {
+            return false;
+        }
+        let (_, _, entry_len) = lengths(entry);
+        entry_len <= entry.len() as u64 && verify(&entry[..entry_len as usize])
+    }))
+}
+
+/// Encodes a log entry, with no value for a deletion
+pub(super) fn encode(key: &[u8], value: Option<&[u8]>) -> EasyDbResult<Vec<u8>> {
+    let length = |bytes: &[u8]| match u32::try_from(bytes.len()) {
+        Ok(len) if len != TOMBSTONE => Ok(len),
+        _ => Err(EasyDbError::Value(format!(
+            "Can't store {} bytes under one key",
+            bytes.len()
+        ))),
+    };
+    let mut entry = vec![0; 4];
+    entry.extend(length(key)?.to_be_bytes());
+    entry.extend(value.map_or(Ok(TOMBSTONE), length)?.to_be_bytes());
+    entry.extend(key);
+    entry.extend(value.unwrap_or_default());
+    let checksum = crc32fast::hash(&entry[4..]);
+    entry[..4].copy_from_slice(&checksum.to_be_bytes());
+    Ok(entry)
+}
+
+/// Checks an encoded entry against its checksum
+fn verify(entry: &[u8]) -> bool {
+    entry[..4] == crc32fast::hash(&entry[4..]).to_be_bytes()
+}
+
+fn corrupted(path: &Path, pos: u64) -> EasyDbError {
+    EasyDbError::Internal(format!(
+        "Checksum mismatch in the log entry at byte {} of {}, it is corrupted",
+        pos,
+        path.display()
+    ))
+}
+
+/// Reads a live value, decrypting and decompressing it. Split from
+/// Log::read() so scans can read while borrowing the key directory.
+fn read_live(
+    file: &mut File,
+    path: &Path,
+    encryption: &Option<Encryption>,
+    key: &[u8],
+    pos: u64,
+    len: u32,
+) -> EasyDbResult<Vec<u8>> {
+    let key_len = (key.len() + overhead(encryption)) as u64;
+    let value = read_value(file, path, key_len, pos, len)?;
+    let value = unseal(encryption, &value, "a log entry's value")?;
+    Compression::decompress(&value)
+}
+
+/// Reads the value of an entry, given the stored key's length and the
+/// value's position and length, checking the entry's checksum
+pub(super) fn read_value(
+    file: &mut File,
+    path: &Path,
+    key_len: u64,
+    pos: u64,
+    len: u32,
+) -> EasyDbResult<Vec<u8>> {
+    let start = pos - HEADER_LEN - key_len;
+    let mut entry = vec![0; (pos - start) as usize + len as usize];
+    file.seek(SeekFrom::Start(start))?;
+    file.read_exact(&mut entry)?;
+    if !verify(&entry) {
+        return Err(corrupted(path, start));
+    }
+    entry.drain(..(pos - start) as usize);
+    Ok(entry)
+}
+
+impl Engine for Log {
+    fn get(&mut self, key: &[u8]) -> EasyDbResult<Option<Vec<u8>>> {
+        match self.keydir.get(key) {
+            Some((pos, len)) => Ok(Some(self.read(key, *pos, *len)?)),
+            None => Ok(None),
+        }
+    }
+
+    fn set(&mut self, key: &[u8], value: Vec<u8>) -> EasyDbResult<()> {
+        let value = seal(&self.encryption, &self.compression.compress(&value)?)?;
+        let pos = self.append(key, Some(&value))?;
+        if let Some((_, old_len)) = self.keydir.insert(key.to_vec(), (pos, value.len() as u32)) {
+            self.garbage += HEADER_LEN + self.stored_key_len(key) + old_len as u64;
+        }
+        Ok(())
+    }
+
+    fn delete(&mut self, key: &[u8]) -> EasyDbResult<()> {
+        if let Some((_, old_len)) = self.keydir.get(key).copied() {
+            self.append(key, None)?;
+            self.keydir.remove(key);
+            self.garbage += 2 * (HEADER_LEN + self.stored_key_len(key)) + old_len as u64;
+        }
+        Ok(())
+    }
+
+    fn scan(&mut self, range: (Bound<Vec<u8>>, Bound<Vec<u8>>)) -> Box<dyn ScanIterator + '_> {
+        let (file, path, encryption) = (&mut self.file, &self.path, &self.encryption);
+        Box::new(self.keydir.range(range).map(move |(key, (pos, len))| {
+            let value = read_live(file, path, encryption, key, *pos, *len)?;
+            Ok((key.clone(), value))
+        }))
+    }
+
+    fn flush(&mut self) -> EasyDbResult<()> {
+        self.syncer.sync(&self.file)?;
+        if self.garbage >= COMPACT_MIN_GARBAGE && self.garbage >= self.live_size() {
+            self.compact()?;
+        }
+        Ok(())
+    }
+
+    fn sync(&mut self) -> EasyDbResult<()> {
+        self.flush()?;
+        self.syncer.sync_now(&self.file)
+    }
+
+    fn status(&mut self) -> EasyDbResult<Status> {
+        Ok(Status {
+            name: "log".into(),
+            keys: self.keydir.len() as u64,
+            size: self
+                .keydir
+                .iter()
+                .map(|(key, (_, len))| key.len() as u64 + *len as u64)
+                .sum(),
+            disk_size: self.file.metadata()?.len(),
+            garbage_disk_size: self.garbage,
+            cache: None,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn open(path: &Path, encryption: Option<Encryption>) -> EasyDbResult<Log> {
+        Log::open(path, SyncPolicy::Always, Compression::None, encryption)
+    }
+
+    /// Collects a scan's key/value pairs
+    fn collect(scan: Box<dyn ScanIterator + '_>) -> EasyDbResult<Vec<(Vec<u8>, Vec<u8>)>> {
+        scan.collect()
+    }
+
+    #[test]
+    fn get_set_delete_scan() -> EasyDbResult<()> {
+        let dir = tempfile::tempdir()?;
+        let mut log = open(&dir.path().join("log"), None)?;
+        log.set(b"b", vec![2])?;
+        log.set(b"a", vec![1])?;
+        log.set(b"c", vec![])?;
+        log.set(b"b", vec![22])?;
+        log.delete(b"c")?;
+        log.delete(b"missing")?;
+        assert_eq!(log.get(b"a")?, Some(vec![1]));
+        assert_eq!(log.get(b"b")?, Some(vec![22]));
+        assert_eq!(log.get(b"c")?, None);
+        assert_eq!(
+            collect(log.scan((Bound::Unbounded, Bound::Unbounded)))?,
+            vec![(b"a".to_vec(), vec![1]), (b"b".to_vec(), vec![22])]
+        );
+        assert_eq!(
+            collect(Box::new(
+                log.scan((Bound::Excluded(b"a".to_vec()), Bound::Unbounded))
+                    .rev()
+            ))?,
+            vec![(b"b".to_vec(), vec![22])]
+        );
+        log.set(b"ab", vec![3])?;
+        assert_eq!(
+            collect(log.scan_prefix(b"a"))?,
+            vec![(b"a".to_vec(), vec![1]), (b"ab".to_vec(), vec![3])]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn reopen() -> EasyDbResult<()> {
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("log");
+        let mut log = open(&path, None)?;
+        log.set(b"a", vec![1])?;
+        log.set(b"b", vec![2])?;
+        log.set(b"a", vec![3])?;
+        log.delete(b"b")?;
+        let status = log.status()?;
+        drop(log);
+
+        let mut log = open(&path, None)?;
+        assert_eq!(
+            log.recovery(),
+            Recovery {
+                entries: 4,
+                truncated: 0
+            }
+        );
+        assert_eq!(log.get(b"a")?, Some(vec![3]));
+        assert_eq!(log.get(b"b")?, None);
+        // Garbage took up more than the live entry, so opening compacted
+        assert_eq!(log.status()?.garbage_disk_size, 0);
+        assert!(log.status()?.disk_size < status.disk_size);
+        assert_eq!(log.status()?.keys, 1);
+        Ok(())
+    }
+
+    #[test]
+    fn compact() -> EasyDbResult<()> {
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("log");
+        let mut log = open(&path, None)?;
+        for i in 0..10u8 {
+            log.set(&[i % 3], vec![i; 10])?;
+        }
+        assert!(log.status()?.garbage_disk_size > 0);
+        log.compact()?;
+        let status = log.status()?;
+        assert_eq!(status.garbage_disk_size, 0);
+        assert_eq!(status.keys, 3);
+        assert_eq!(status.disk_size, 3 * (HEADER_LEN + 1 + 1 + 10));
+        assert_eq!(log.get(&[0])?, Some(vec![9; 10]));
+        drop(log);
+
+        let mut log = open(&path, None)?;
+        assert_eq!(log.recovery().entries, 3);
+        assert_eq!(log.get(&[2])?, Some(vec![8; 10]));
+        Ok(())
+    }
+
+    #[test]
+    fn encryption() -> EasyDbResult<()> {
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("log");
+        let key = Encryption::new(&[1; 32]);
+        let mut log = open(&path, Some(key.clone()))?;
+        log.set(b"secret key", b"secret value".to_vec())?;
+        drop(log);
+
+        let contents = std::fs::read(&path)?;
+        assert!(!contents.windows(6).any(|w| w == b"secret"));
+        let mut log = open(&path, Some(key))?;
+        assert_eq!(log.get(b"secret key")?, Some(b"secret value".to_vec()));
+        drop(log);
+        assert!(open(&path, Some(Encryption::new(&[2; 32]))).is_err());
+        Ok(())
+    }
+
+    /// Writes a log with three entries, returning its contents
+    fn write_three(path: &Path) -> EasyDbResult<Vec<u8>> {
+        let mut log = open(path, None)?;
+        log.set(b"a", b"one".to_vec())?;
+        log.set(b"b", b"two".to_vec())?;
+        log.set(b"c", b"three".to_vec())?;
+        Ok(std::fs::read(path)?)
+    }
+
+    #[test]
+    fn replay_truncates_torn_entry() -> EasyDbResult<()> {
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("log");
+        let contents = write_three(&path)?;
+        // The last entry lost its last bytes, or only its lengths reached
+        // the disk and the rest is zeros
+        let mut zeroed = contents.clone();
+        zeroed[contents.len() - 4..].fill(0);
+        for torn in [contents[..contents.len() - 3].to_vec(), zeroed] {
+            std::fs::write(&path, &torn)?;
+            let mut log = open(&path, None)?;
+            let last_len = HEADER_LEN + 1 + 1 + 5;
+            assert_eq!(log.recovery().entries, 2);
+            assert_eq!(
+                log.recovery().truncated,
+                torn.len() as u64 + last_len - contents.len() as u64
+            );
+            assert_eq!(log.get(b"b")?, Some(b"two".to_vec()));
+            assert_eq!(log.get(b"c")?, None);
+            assert_eq!(
+                std::fs::metadata(&path)?.len(),
+                contents.len() as u64 - last_len
+            );
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn replay_rejects_damage_before_the_end() -> EasyDbResult<()> {
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("log");
+        let contents = write_three(&path)?;
+        // A key length running past the end of the file, and a damaged key
+        for (at, byte) in [(4, 0x7f), (HEADER_LEN as usize, b'x')] {
+            let mut damaged = contents.clone();
+            damaged[at] = byte;
+            std::fs::write(&path, &damaged)?;
+            assert!(open(&path, None).is_err());
+            assert_eq!(std::fs::read(&path)?, damaged);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn damaged_value_is_error() -> EasyDbResult<()> {
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("log");
+        let mut log = open(&path, None)?;
+        log.set(b"a", b"value".to_vec())?;
+        let mut contents = std::fs::read(&path)?;
+        *contents.last_mut().unwrap() ^= 1;
+        std::fs::write(&path, &contents)?;
+        assert!(log.get(b"a").is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,62 @@
+use super::{Engine, ScanIterator, Status};
+use crate::error::EasyDbResult;
+
+use std::collections::BTreeMap;
+use std::ops::Bound;
+
+/// An engine holding its data in memory, which is lost on exit
+#[derive(Clone, Debug, Default)]
+pub struct Memory {
+    data: BTreeMap<Vec<u8>, Vec<u8>>,
+}
+
+impl Memory {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl Engine for Memory {
+    fn get(&mut self, key: &[u8]) -> EasyDbResult<Option<Vec<u8>>> {
+        Ok(self.data.get(key).cloned())
+    }
+
+    fn set(&mut self, key: &[u8], value: Vec<u8>) -> EasyDbResult<()> {
+        self.data.insert(key.to_vec(), value);
+        Ok(())
+    }
+
+    fn delete(&mut self, key: &[u8]) -> EasyDbResult<()> {
+        self.data.remove(key);
+        Ok(())
+    }
+
+    fn scan(&mut self, range: (Bound<Vec<u8>>, Bound<Vec<u8>>)) -> Box<dyn ScanIterator + '_> {
+        Box::new(
+            self.data
+                .range(range)
+                .map(|(key, value)| Ok((key.clone(), value.clone()))),
+        )
+    }
+
+    fn flush(&mut self) -> EasyDbResult<()> {
+        Ok(())
+    }
+
+    fn sync(&mut self) -> EasyDbResult<()> {
+        Ok(())
+    }
+
+    fn status(&mut self) -> EasyDbResult<Status> {
+        Ok(Status {
+            name: "memory".into(),
+            keys: self.data.len() as u64,
+            size: self
+                .data
+                .iter()
+                .map(|(key, value)| (key.len() + value.len()) as u64)
+                .sum(),
+            ..Status::default()
+        })
+    }
+}
@@ -0,0 +1,186 @@
+pub mod buffer;
+pub mod compression;
+pub mod encryption;
+pub mod index;
+pub mod keycode;
+pub mod log;
+pub mod memory;
+pub mod paged;
+pub mod row;
+pub mod wal;
+
+pub use self::log::{Log, Recovery};
+pub use compression::Compression;
+pub use encryption::Encryption;
+pub use index::SecondaryIndexes;
+pub use memory::Memory;
+pub use paged::Paged;
+pub use row::{decode_row, encode_row};
+pub use wal::Wal;
+
+use crate::error::EasyDbResult;
+
+use std::fs::File;
+use std::ops::Bound;
+use std::time::{Duration, Instant};
+
+/// An iterator over key/value pairs ordered by key, as returned by
+/// Engine::scan(), which can also iterate in reverse. Reading a pair can
+/// fail, e.g. on an I/O error.
+pub trait ScanIterator: DoubleEndedIterator<Item = EasyDbResult<(Vec<u8>, Vec<u8>)>> {}
+
+impl<I: DoubleEndedIterator<Item = EasyDbResult<(Vec<u8>, Vec<u8>)>>> ScanIterator for I {}
+
+/// A key/value storage engine over byte keys, ordered by key. Everything the
+/// SQL layer persists goes through this trait, so that on-disk formats can
+/// be swapped without touching it.
+pub trait Engine {
+    /// Reads a key's value, if it exists
+    fn get(&mut self, key: &[u8]) -> EasyDbResult<Option<Vec<u8>>>;
+
+    /// Sets a key's value, replacing any existing one
+    fn set(&mut self, key: &[u8], value: Vec<u8>) -> EasyDbResult<()>;
+
+    /// Deletes a key, doing nothing if it doesn't exist
+    fn delete(&mut self, key: &[u8]) -> EasyDbResult<()>;
+
+    /// Iterates over the key/value pairs within a key range, ordered by key
+    fn scan(&mut self, range: (Bound<Vec<u8>>, Bound<Vec<u8>>)) -> Box<dyn ScanIterator + '_>;
+
+    /// Iterates over the key/value pairs whose keys start with a prefix,
+    /// ordered by key
+    fn scan_prefix(&mut self, prefix: &[u8]) -> Box<dyn ScanIterator + '_> {
+        // The keys after the prefix start at the prefix incremented as a
+        // number, ignoring trailing 0xff bytes which can't be incremented
+        let mut end = prefix.to_vec();
+        while end.last() == Some(&0xff) {
+            end.pop();
+        }
+        let end = match end.last_mut() {
+            Some(last) => {
+                *last += 1;
+                Bound::Excluded(end)
+            }
+            None => Bound::Unbounded,
+        };
+        self.scan((Bound::Included(prefix.to_vec()), end))
+    }
+
+    /// Makes the writes so far durable, as far as the engine's sync policy
+    /// has it sync them
+    fn flush(&mut self) -> EasyDbResult<()>;
+
+    /// Flushes and syncs the writes so far regardless of the sync policy,
+    /// e.g. before discarding another log which holds them
+    fn sync(&mut self) -> EasyDbResult<()>;
+
+    /// Reports statistics about the stored data
+    fn status(&mut self) -> EasyDbResult<Status>;
+}
+
+/// Statistics about an engine's data, as returned by Engine::status()
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Status {
+    /// The name of the engine, e.g. "log"
+    pub name: String,
+    /// The number of live keys
+    pub keys: u64,
+    /// The logical size: the length of the live keys and values, as stored
+    /// after any compression
+    pub size: u64,
+    /// The physical size: the length of the engine's files
+    pub disk_size: u64,
+    /// The number of bytes of the files taken up by replaced or deleted
+    /// entries
+    pub garbage_disk_size: u64,
+    /// The page cache's statistics, for engines that have one
+    pub cache: Option<CacheStatus>,
+}
+
+impl Status {
+    /// The fraction of the files taken up by garbage
+    pub fn garbage_ratio(&self) -> f64 {
+        match self.disk_size {
+            0 => 0.0,
+            size => self.garbage_disk_size as f64 / size as f64,
+        }
+    }
+}
+
+/// Statistics about a buffer pool, as returned by BufferPool::status()
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct CacheStatus {
+    /// The number of pages the pool can hold
+    pub capacity: u64,
+    /// The number of pages it holds
+    pub pages: u64,
+    /// The number of pins which found their page cached
+    pub hits: u64,
+    /// The number of pins which had to read their page from the file
+    pub misses: u64,
+}
+
+impl CacheStatus {
+    /// The fraction of pins which found their page cached
+    pub fn hit_rate(&self) -> f64 {
+        match self.hits + self.misses {
+            0 => 0.0,
+            pins => self.hits as f64 / pins as f64,
+        }
+    }
+}
+
+/// When writes are synced to disk, trading durability for write throughput.
+/// A sync is due when an engine commits its writes: on every write for Wal,
+/// and on flush() for Log.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum SyncPolicy {
+    /// Sync on every commit, so that no committed write is lost in a crash
+    #[default]
+    Always,
+    /// Sync on a commit if the last sync was at least this long ago. A crash
+    /// may lose the commits of about the last interval. There is no
+    /// background thread, so the last commits before a pause stay unsynced
+    /// until a later commit finds the interval has passed.
+    Interval(Duration),
+    /// Leave syncing to the operating system. A crash may lose any number
+    /// of recent commits, but never leaves one half written.
+    Never,
+}
+
+/// Syncs a file according to a sync policy
+#[derive(Debug)]
+pub(crate) struct Syncer {
+    policy: SyncPolicy,
+    /// When the file was last synced
+    last: Instant,
+}
+
+impl Syncer {
+    pub(crate) fn new(policy: SyncPolicy) -> Self {
+        Self {
+            policy,
+            last: Instant::now(),
+        }
+    }
+
+    /// Syncs the file if the policy says a commit should be synced now
+    pub(crate) fn sync(&mut self, file: &File) -> EasyDbResult<()> {
+        let due = match self.policy {
+            SyncPolicy::Always => true,
+            SyncPolicy::Interval(interval) => self.last.elapsed() >= interval,
+            SyncPolicy::Never => false,
+        };
+        if due {
+            self.sync_now(file)?;
+        }
+        Ok(())
+    }
+
+    /// Syncs the file whatever the policy says
+    pub(crate) fn sync_now(&mut self, file: &File) -> EasyDbResult<()> {
+        file.sync_data()?;
+        self.last = Instant::now();
+        Ok(())
+    }
+}
@@ -0,0 +1,378 @@
+use super::buffer::{BufferPool, PageFile, PAGE_DATA_SIZE, PAGE_SIZE};
+use super::log::{encode, HEADER_LEN, TOMBSTONE};
+use super::{Encryption, Engine, ScanIterator, Status};
+use crate::error::EasyDbResult;
+
+use std::collections::BTreeMap;
+use std::ops::Bound;
+use std::path::Path;
+
+/// An engine storing its data in a page file read and written through a
+/// buffer pool, so that only the pages in use are held in memory. The pages
+/// hold an append-only heap of entries in the log's format, which may span
+/// pages, and an in-memory index of where each key's latest value is.
+///
+/// flush() writes back the dirty pages and syncs the file. Opening the file
+/// replays the heap to rebuild the index, up to the first entry whose
+/// checksum doesn't match. That is where a crash cut the heap short, since
+/// pages damaged otherwise fail their own checksums, so the rest is dropped
+/// and later writes go there.
+///
+/// Replaced values and deletions are garbage, which is reported by status()
+/// but not reclaimed.
+#[derive(Debug)]
+pub struct Paged {
+    pool: BufferPool,
+    /// The position and length of each key's value in the heap
+    keydir: BTreeMap<Vec<u8>, (u64, u32)>,
+    /// The position the next entry is written at
+    end: u64,
+    /// The number of bytes taken up by garbage entries
+    garbage: u64,
+}
+
+impl Paged {
+    /// Opens the page file at the given path, creating it if it doesn't
+    /// exist, with a buffer pool of `capacity` pages. A file written with an
+    /// encryption must be opened with the same one.
+    pub fn open(
+        path: impl AsRef<Path>,
+        capacity: usize,
+        encryption: Option<Encryption>,
+    ) -> EasyDbResult<Self> {
+        let pool = BufferPool::new(PageFile::open(path, encryption)?, capacity)?;
+        let mut paged = Self {
+            pool,
+            keydir: BTreeMap::new(),
+            end: 0,
+            garbage: 0,
+        };
+        paged.load()?;
+        Ok(paged)
+    }
+
+    /// Replays the heap to rebuild the index, then clears whatever follows
+    /// its last entry
+    fn load(&mut self) -> EasyDbResult<()> {
+        let limit = self.pool.disk_size()? / PAGE_SIZE as u64 * PAGE_DATA_SIZE as u64;
+        let mut pos = 0;
+        loop {
+            let mut entry = vec![0; HEADER_LEN as usize];
+            if pos + HEADER_LEN > limit {
+                break;
+            }
+            read_at(&mut self.pool, pos, &mut entry)?;
+            let key_len = u32::from_be_bytes(entry[4..8].try_into().unwrap());
+            let value_len = u32::from_be_bytes(entry[8..12].try_into().unwrap());
+            let len = key_len as u64
+                + if value_len == TOMBSTONE {
+                    0
+                } else {
+                    value_len as u64
+                };
+            if pos + HEADER_LEN + len > limit {
+                break;
+            }
+            entry.resize(HEADER_LEN as usize + len as usize, 0);
+            read_at(
+                &mut self.pool,
+                pos + HEADER_LEN,
+                &mut entry[HEADER_LEN as usize..],
+            )?;
+            if entry[..4] != crc32fast::hash(&entry[4..]).to_be_bytes() {
+                break;
+            }
+            let key = entry[HEADER_LEN as usize..][..key_len as usize].to_vec();
+            let old = if value_len == TOMBSTONE {
+                self.garbage += HEADER_LEN + key_len as u64;
+                self.keydir.remove(&key)
+            } else {
+                let value_pos = pos + HEADER_LEN + key_len as u64;
+                self.keydir.insert(key, (value_pos, value_len))
+            };
+            if let Some((_, old_len)) = old {
+                self.garbage += HEADER_LEN + key_len as u64 + old_len as u64;
+            }
+            pos += HEADER_LEN + len;
+        }
+        self.end = pos;
+
+        let page = pos / PAGE_DATA_SIZE as u64;
+        let offset = (pos % PAGE_DATA_SIZE as u64) as usize;
+        self.pool.pin(page)?;
+        if self.pool.read(page)?[offset..].iter().any(|b| *b != 0) {
+            self.pool.write(page)?[offset..].fill(0);
+        }
+        self.pool.unpin(page)?;
+        self.pool.truncate(page + 1)
+    }
+
+    /// Appends an entry to the heap, returning the position of its value
+    fn append(&mut self, key: &[u8], value: Option<&[u8]>) -> EasyDbResult<u64> {
+        let entry = encode(key, value)?;
+        write_at(&mut self.pool, self.end, &entry)?;
+        let pos = self.end + HEADER_LEN + key.len() as u64;
+        self.end += entry.len() as u64;
+        Ok(pos)
+    }
+}
+
+/// Reads bytes of the heap at a position, across pages
+fn read_at(pool: &mut BufferPool, mut pos: u64, buf: &mut [u8]) -> EasyDbResult<()> {
+    let mut done = 0;
+    while done < buf.len() {
+        let page = pos / PAGE_DATA_SIZE as u64;
+        let offset = (pos % PAGE_DATA_SIZE as u64) as usize;
+        let len = (PAGE_DATA_SIZE - offset).min(buf.len() - done);
+        pool.pin(page)?;
+        buf[done..done + len].copy_from_slice(&pool.read(page)?[offset..offset + len]);
+        pool.unpin(page)?;
+        done += len;
+        pos += len as u64;
+    }
+    Ok(())
+}
+
+/// Writes bytes to the heap at a position, across pages
+fn write_at(pool: &mut BufferPool, mut pos: u64, data: &[u8]) -> EasyDbResult<()> {
+    let mut done = 0;
+    while done < data.len() {
+        let page = pos / PAGE_DATA_SIZE as u64;
+        let offset = (pos % PAGE_DATA_SIZE as u64) as usize;
+        let len = (PAGE_DATA_SIZE - offset).min(data.len() - done);
+        pool.pin(page)?;
+        pool.write(page)?[offset..offset + len].copy_from_slice(&data[done..done + len]);
+        pool.unpin(page)?;
+        done += len;
+        pos += len as u64;
+    }
+    Ok(())
+}
+
+impl Engine for Paged {
+    fn get(&mut self, key: &[u8]) -> EasyDbResult<Option<Vec<u8>>> {
+        match self.keydir.get(key) {
+            Some((pos, len)) => {
+                let mut value = vec![0; *len as usize];
+                read_at(&mut self.pool, *pos, &mut value)?;
+                Ok(Some(value))
+            }
+            None => Ok(None),
+        }
+    }
+
+    fn set(&mut self, key: &[u8], value: Vec<u8>) -> EasyDbResult<()> {
+        let pos = self.append(key, Some(&value))?;
+        if let Some((_, old_len)) = self.keydir.insert(key.to_vec(), (pos, value.len() as u32)) {
+            self.garbage += HEADER_LEN + key.len() as u64 + old_len as u64;
+        }
+        Ok(())
+    }
+
+    fn delete(&mut self, key: &[u8]) -> EasyDbResult<()> {
+        if let Some((_, old_len)) = self.keydir.get(key).copied() {
+            self.append(key, None)?;
+            self.keydir.remove(key);
+            self.garbage += 2 * (HEADER_LEN + key.len() as u64) + old_len as u64;
+        }
+        Ok(())
+    }
+
+    fn scan(&mut self, range: (Bound<Vec<u8>>, Bound<Vec<u8>>)) -> Box<dyn ScanIterator + '_> {
+        let pool = &mut self.pool;
+        Box::new(self.keydir.range(range).map(move |(key, (pos, len))| {
+            let mut value = vec![0; *len as usize];
+            read_at(pool, *pos, &mut value)?;
+            Ok((key.clone(), value))
+        }))
+    }
+
+    fn flush(&mut self) -> EasyDbResult<()> {
+        self.pool.flush()
+    }
+
+    fn sync(&mut self) -> EasyDbResult<()> {
+        self.pool.flush()
+    }
+
+    fn status(&mut self) -> EasyDbResult<Status> {
+        let pages = self.end.div_ceil(PAGE_DATA_SIZE as u64);
+        Ok(Status {
+            name: "paged".into(),
+            keys: self.keydir.len() as u64,
+            size: self
+                .keydir
+                .iter()
+                .map(|(key, (_, len))| key.len() as u64 + *len as u64)
+                .sum(),
+            disk_size: pages * PAGE_SIZE as u64,
+            garbage_disk_size: self.garbage,
+            cache: Some(self.pool.status()),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::fs::OpenOptions;
+    use std::io::{Seek, SeekFrom, Write};
+
+    fn collect(scan: Box<dyn ScanIterator + '_>) -> EasyDbResult<Vec<(Vec<u8>, Vec<u8>)>> {
+        scan.collect()
+    }
+
+    /// Returns the status without the cache statistics, which reopening
+    /// resets
+    fn stored(paged: &mut Paged) -> EasyDbResult<Status> {
+        Ok(Status {
+            cache: None,
+            ..paged.status()?
+        })
+    }
+
+    #[test]
+    fn get_set_delete_scan() -> EasyDbResult<()> {
+        let dir = tempfile::tempdir()?;
+        let mut paged = Paged::open(dir.path().join("pages"), 2, None)?;
+        paged.set(b"b", vec![2])?;
+        paged.set(b"a", vec![1])?;
+        paged.set(b"c", vec![])?;
+        paged.set(b"b", vec![22])?;
+        paged.delete(b"c")?;
+        paged.delete(b"missing")?;
+        assert_eq!(paged.get(b"a")?, Some(vec![1]));
+        assert_eq!(paged.get(b"b")?, Some(vec![22]));
+        assert_eq!(paged.get(b"c")?, None);
+        assert_eq!(
+            collect(paged.scan_prefix(b""))?,
+            vec![(b"a".to_vec(), vec![1]), (b"b".to_vec(), vec![22])]
+        );
+        assert_eq!(
+            collect(paged.scan((Bound::Excluded(b"a".to_vec()), Bound::Unbounded)))?,
+            vec![(b"b".to_vec(), vec![22])]
+        );
+
+        let status = paged.status()?;
+        assert_eq!(
+            (status.name.as_str(), status.keys, status.size),
+            ("paged", 2, 4)
+        );
+        assert_eq!(status.disk_size, PAGE_SIZE as u64);
+        // b's first value, and c's value and deletion
+        assert_eq!(status.garbage_disk_size, 14 + 2 * 13);
+        Ok(())
+    }
+
+    #[test]
+    fn more_than_the_pool_holds() -> EasyDbResult<()> {
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("pages");
+        let value = |i: u32| i.to_be_bytes().repeat(1000);
+        let mut paged = Paged::open(&path, 2, None)?;
+        for i in 0..20u32 {
+            paged.set(&i.to_be_bytes(), value(i))?;
+        }
+        for i in (0..20u32).rev() {
+            assert_eq!(paged.get(&i.to_be_bytes())?, Some(value(i)));
+        }
+        assert_eq!(collect(paged.scan_prefix(b""))?.len(), 20);
+        paged.flush()?;
+        let status = stored(&mut paged)?;
+        drop(paged);
+
+        let mut paged = Paged::open(&path, 2, None)?;
+        assert_eq!(stored(&mut paged)?, status);
+        for i in 0..20u32 {
+            assert_eq!(paged.get(&i.to_be_bytes())?, Some(value(i)));
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn reopen() -> EasyDbResult<()> {
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("pages");
+        let mut paged = Paged::open(&path, 4, None)?;
+        paged.set(b"a", vec![1])?;
+        paged.set(b"b", vec![2])?;
+        paged.set(b"a", vec![3])?;
+        paged.delete(b"b")?;
+        paged.flush()?;
+        let status = stored(&mut paged)?;
+        // Writes that weren't flushed are lost
+        paged.set(b"c", vec![4])?;
+        drop(paged);
+
+        let mut paged = Paged::open(&path, 4, None)?;
+        assert_eq!(stored(&mut paged)?, status);
+        assert_eq!(
+            collect(paged.scan_prefix(b""))?,
+            vec![(b"a".to_vec(), vec![3])]
+        );
+        paged.set(b"d", vec![5])?;
+        paged.flush()?;
+        drop(paged);
+
+        let mut paged = Paged::open(&path, 4, None)?;
+        assert_eq!(paged.get(b"a")?, Some(vec![3]));
+        assert_eq!(paged.get(b"d")?, Some(vec![5]));
+        Ok(())
+    }
+
+    #[test]
+    fn torn_entry() -> EasyDbResult<()> {
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("pages");
+        let mut paged = Paged::open(&path, 1, None)?;
+        paged.set(b"a", vec![1])?;
+        paged.set(b"b", vec![2; PAGE_DATA_SIZE])?;
+        paged.flush()?;
+        drop(paged);
+
+        // Drop the second page, as if a crash kept it from being written,
+        // which leaves b's entry cut short
+        OpenOptions::new()
+            .write(true)
+            .open(&path)?
+            .set_len(PAGE_SIZE as u64)?;
+        let mut paged = Paged::open(&path, 1, None)?;
+        assert_eq!(
+            collect(paged.scan_prefix(b""))?,
+            vec![(b"a".to_vec(), vec![1])]
+        );
+        paged.set(b"c", vec![3])?;
+        paged.flush()?;
+        drop(paged);
+        let mut paged = Paged::open(&path, 1, None)?;
+        assert_eq!(
+            collect(paged.scan_prefix(b""))?,
+            vec![(b"a".to_vec(), vec![1]), (b"c".to_vec(), vec![3])]
+        );
+        drop(paged);
+
+        // Damage to a page is an error
+        let mut file = OpenOptions::new().write(true).open(&path)?;
+        file.seek(SeekFrom::Start(0))?;
+        file.write_all(&[0xff])?;
+        assert!(Paged::open(&path, 1, None).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn encryption() -> EasyDbResult<()> {
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("pages");
+        let mut paged = Paged::open(&path, 1, Some(Encryption::new(&[1; 32])))?;
+        paged.set(b"key", b"secret".to_vec())?;
+        paged.flush()?;
+        drop(paged);
+        assert!(!std::fs::read(&path)?.windows(6).any(|w| w == b"secret"));
+
+        let mut paged = Paged::open(&path, 1, Some(Encryption::new(&[1; 32])))?;
+        assert_eq!(paged.get(b"key")?, Some(b"secret".to_vec()));
+        assert!(Paged::open(&path, 1, Some(Encryption::new(&[2; 32]))).is_err());
+        Ok(())
+    }
+}
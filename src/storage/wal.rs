@@ -0,0 +1,176 @@
+use super::encryption::{seal, unseal};
+use super::log::{encode, read_value, replay, Recovery};
+use super::{Encryption, Engine, ScanIterator, Status, SyncPolicy, Syncer};
+use crate::error::EasyDbResult;
+
+use std::fs::{File, OpenOptions};
+use std::io::{Seek, SeekFrom, Write};
+use std::ops::Bound;
+use std::path::Path;
+
+/// A write-ahead log in front of another engine. Each write is appended to
+/// the log, synced according to the sync policy, and only then applied to
+/// the engine. Opening the log replays it into the engine, recovering
+/// writes the engine lost in a crash, e.g. all of them for Memory.
+///
+/// Log entries use the same format as the Log engine, checksums included,
+/// so a damaged entry is found on replay, and encryption if given. Values
+/// aren't compressed here, the engine compresses them if it does so.
+/// checkpoint() syncs the engine and empties the log, once the engine
+/// holds every write.
+#[derive(Debug)]
+pub struct Wal<E: Engine> {
+    engine: E,
+    file: File,
+    encryption: Option<Encryption>,
+    syncer: Syncer,
+    recovery: Recovery,
+}
+
+impl<E: Engine> Wal<E> {
+    /// Opens the log at the given path, creating it if it doesn't exist, and
+    /// replays it into the engine. An incomplete entry at the end, from a
+    /// write that was never acknowledged, is discarded. What was replayed
+    /// is reported by recovery(). A log written with an encryption must be
+    /// opened with the same one.
+    pub fn open(
+        path: impl AsRef<Path>,
+        mut engine: E,
+        sync: SyncPolicy,
+        encryption: Option<Encryption>,
+    ) -> EasyDbResult<Self> {
+        let path = path.as_ref();
+        let mut file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(path)?;
+        let mut entries = Vec::new();
+        let recovery = replay(&mut file, path, |key, value| {
+            entries.push((key, value));
+            Ok(())
+        })?;
+        for (stored_key, value) in entries {
+            let key = unseal(&encryption, &stored_key, "a write-ahead log entry's key")?;
+            match value {
+                Some((pos, len)) => {
+                    let value = read_value(&mut file, path, stored_key.len() as u64, pos, len)?;
+                    let value = unseal(&encryption, &value, "a write-ahead log entry's value")?;
+                    engine.set(&key, value)?
+                }
+                None => engine.delete(&key)?,
+            }
+        }
+        file.seek(SeekFrom::End(0))?;
+        Ok(Self {
+            engine,
+            file,
+            encryption,
+            syncer: Syncer::new(sync),
+            recovery,
+        })
+    }
+
+    /// Appends an entry to the log, encrypted if need be, syncing it if the
+    /// policy says to
+    fn append(&mut self, key: &[u8], value: Option<&[u8]>) -> EasyDbResult<()> {
+        let key = seal(&self.encryption, key)?;
+        let value = value
+            .map(|value| seal(&self.encryption, value))
+            .transpose()?;
+        self.file.write_all(&encode(&key, value.as_deref())?)?;
+        self.syncer.sync(&self.file)
+    }
+
+    /// Reports what was replayed when the log was opened
+    pub fn recovery(&self) -> Recovery {
+        self.recovery
+    }
+
+    /// Syncs the engine, whatever its sync policy, and empties the log,
+    /// which is no longer needed to recover the engine's writes
+    pub fn checkpoint(&mut self) -> EasyDbResult<()> {
+        self.engine.sync()?;
+        self.file.set_len(0)?;
+        self.file.seek(SeekFrom::Start(0))?;
+        Ok(self.file.sync_all()?)
+    }
+}
+
+impl<E: Engine> Engine for Wal<E> {
+    fn get(&mut self, key: &[u8]) -> EasyDbResult<Option<Vec<u8>>> {
+        self.engine.get(key)
+    }
+
+    fn set(&mut self, key: &[u8], value: Vec<u8>) -> EasyDbResult<()> {
+        self.append(key, Some(&value))?;
+        self.engine.set(key, value)
+    }
+
+    fn delete(&mut self, key: &[u8]) -> EasyDbResult<()> {
+        self.append(key, None)?;
+        self.engine.delete(key)
+    }
+
+    fn scan(&mut self, range: (Bound<Vec<u8>>, Bound<Vec<u8>>)) -> Box<dyn ScanIterator + '_> {
+        self.engine.scan(range)
+    }
+
+    fn flush(&mut self) -> EasyDbResult<()> {
+        self.syncer.sync(&self.file)
+    }
+
+    fn sync(&mut self) -> EasyDbResult<()> {
+        self.syncer.sync_now(&self.file)
+    }
+
+    /// Reports the engine's status, with the log counted in its disk size
+    fn status(&mut self) -> EasyDbResult<Status> {
+        let status = self.engine.status()?;
+        Ok(Status {
+            name: format!("wal+{}", status.name),
+            disk_size: status.disk_size + self.file.metadata()?.len(),
+            ..status
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{Compression, Log, Memory};
+    use super::*;
+
+    #[test]
+    fn replays_unapplied_writes() -> EasyDbResult<()> {
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("wal");
+        let mut wal = Wal::open(&path, Memory::new(), SyncPolicy::Always, None)?;
+        wal.set(b"a", vec![1])?;
+        wal.set(b"b", vec![2])?;
+        wal.delete(b"a")?;
+        drop(wal);
+
+        let mut wal = Wal::open(&path, Memory::new(), SyncPolicy::Always, None)?;
+        assert_eq!(wal.recovery().entries, 3);
+        assert_eq!(wal.get(b"a")?, None);
+        assert_eq!(wal.get(b"b")?, Some(vec![2]));
+        Ok(())
+    }
+
+    #[test]
+    fn checkpoint_syncs_engine_whatever_its_policy() -> EasyDbResult<()> {
+        let dir = tempfile::tempdir()?;
+        let (wal_path, log_path) = (dir.path().join("wal"), dir.path().join("log"));
+        let log = Log::open(&log_path, SyncPolicy::Never, Compression::None, None)?;
+        let mut wal = Wal::open(&wal_path, log, SyncPolicy::Never, None)?;
+        wal.set(b"a", vec![1])?;
+        wal.checkpoint()?;
+        assert_eq!(std::fs::metadata(&wal_path)?.len(), 0);
+        drop(wal);
+
+        let mut log = Log::open(&log_path, SyncPolicy::Never, Compression::None, None)?;
+        assert_eq!(log.get(b"a")?, Some(vec![1]));
+        Ok(())
+    }
+}